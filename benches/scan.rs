@@ -0,0 +1,76 @@
+//! Scanner throughput and heap allocations per statement on a synthetic
+//! mysqldump-style input: `cargo bench --bench scan`.
+
+use sql_splitter::{Dialect, Scanner};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Counts allocations and reallocations, passing them on to the system.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn dump() -> String {
+    let mut dump = String::from("/*!40101 SET NAMES utf8mb4 */;\n");
+    for table in 0..20 {
+        dump.push_str(&format!(
+            "-- Table structure for `t{0}`\nCREATE TABLE `t{0}` (\n  `id` int NOT NULL,\n  \
+             `name` varchar(255) DEFAULT 'n/a',\n  PRIMARY KEY (`id`)\n);\n",
+            table
+        ));
+        for row in 0..2_000 {
+            dump.push_str(&format!(
+                "INSERT INTO `t{}` VALUES ({}, 'row {} of a fairly ordinary string, it''s \
+                 long enough to matter'), ({}, NULL);\n",
+                table,
+                row,
+                row,
+                row + 1
+            ));
+        }
+    }
+    dump
+}
+
+fn main() {
+    let dump = dump();
+    for (name, preserve) in [("trimmed", false), ("preserved", true)] {
+        let input = dump.clone();
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let statements = Scanner::new(input)
+            .dialect(Dialect::Mysql)
+            .preserve_whitespace(preserve)
+            .statements()
+            .count();
+        let elapsed = start.elapsed();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        println!(
+            "{:<9} {} statements, {:.2} allocations/statement, {:.0} MB/s",
+            name,
+            statements,
+            allocations as f64 / statements as f64,
+            dump.len() as f64 / elapsed.as_secs_f64() / 1_000_000.0
+        );
+    }
+}
@@ -0,0 +1,98 @@
+/// What `--ascii` does with statements containing non-ASCII characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AsciiMode {
+    /// Fail without writing output.
+    Error,
+    /// Write them unchanged and count them as a warning.
+    Warn,
+    /// Replace each non-ASCII character with an ASCII approximation.
+    Transliterate,
+}
+
+/// The first non-ASCII character in `text`, if any.
+pub fn first_non_ascii(text: &str) -> Option<char> {
+    text.chars().find(|c| !c.is_ascii())
+}
+
+/// Replaces every non-ASCII character in `text` with an ASCII
+/// approximation: accents are dropped, ligatures spelled out and typographic
+/// punctuation straightened. Characters with no approximation become `?`.
+pub fn transliterate(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            out.push_str(approximate(c));
+        }
+    }
+    out
+}
+
+fn approximate(c: char) -> &'static str {
+    match c {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ð' | 'Ď' | 'Đ' => "D",
+        'ð' | 'ď' | 'đ' => "d",
+        'È'..='Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ì'..='Ï' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ì'..='ï' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ł' | 'Ľ' | 'Ĺ' | 'Ļ' => "L",
+        'ł' | 'ľ' | 'ĺ' | 'ļ' => "l",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ŕ' | 'Ř' => "R",
+        'ŕ' | 'ř' => "r",
+        'Ś' | 'Ş' | 'Š' => "S",
+        'ś' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'Ţ' | 'Ť' => "T",
+        'ţ' | 'ť' => "t",
+        'Þ' => "TH",
+        'þ' => "th",
+        'Ù'..='Ü' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ù'..='ü' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ý' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        '‘' | '’' | '‚' | '′' => "'",
+        '“' | '”' | '„' | '″' | '«' | '»' => "\"",
+        '‐' | '‑' | '‒' | '–' | '—' | '−' => "-",
+        '…' => "...",
+        '\u{a0}' | '\u{2002}'..='\u{200a}' => " ",
+        '×' => "x",
+        '©' => "(c)",
+        '®' => "(R)",
+        '€' => "EUR",
+        '£' => "GBP",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(transliterate("Café Straße"), "Cafe Strasse");
+        assert_eq!(transliterate("“quoted” – Łódź…"), "\"quoted\" - Lodz...");
+        assert_eq!(transliterate("日本"), "??");
+        assert_eq!(transliterate("plain;"), "plain;");
+        assert_eq!(first_non_ascii("SELECT 'naïve'"), Some('ï'));
+        assert_eq!(first_non_ascii("SELECT 1"), None);
+    }
+}
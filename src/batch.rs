@@ -0,0 +1,296 @@
+use crate::category::{self, Category};
+use crate::scanner::LocatedStatement;
+
+/// Written between consecutive statements unless whitespace is preserved.
+pub const STATEMENT_SEPARATOR: &str = "\n\n";
+
+/// The statements of one output file, in order.
+pub type Batch = Vec<LocatedStatement>;
+
+/// How [`batch_statements`] cuts statements into batches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Largest batch, in bytes of output with separators included. A
+    /// statement larger than this gets a batch of its own.
+    pub max_bytes: usize,
+    /// Bytes written between consecutive statements of a batch.
+    pub separator_len: usize,
+    /// Statements past each cut considered for evening out neighbouring
+    /// batches (0 to keep the greedy cuts); see
+    /// [`SqlSplitter::with_lookahead`](crate::SqlSplitter::with_lookahead).
+    pub lookahead: usize,
+    /// Distributes statements across exactly this many batches of similar
+    /// size instead, ignoring the other limits; see
+    /// [`SqlSplitter::with_balance`](crate::SqlSplitter::with_balance).
+    pub balance_files: Option<usize>,
+    /// Packs whole transactions (`BEGIN` through `COMMIT` or `ROLLBACK`)
+    /// as the unit instead of single statements, so none is cut across
+    /// files; see
+    /// [`SqlSplitter::with_group_transactions`](crate::SqlSplitter::with_group_transactions).
+    pub group_transactions: bool,
+}
+
+impl BatchLimits {
+    /// Greedy batches of at most `max_bytes`, separated as the splitter
+    /// separates them by default.
+    pub fn new(max_bytes: usize) -> Self {
+        BatchLimits {
+            max_bytes,
+            separator_len: STATEMENT_SEPARATOR.len(),
+            lookahead: 0,
+            balance_files: None,
+            group_transactions: false,
+        }
+    }
+}
+
+/// Cuts `statements` into batches, one per output file, as the splitter
+/// does before writing: greedily packed in order up to `limits.max_bytes`
+/// (then evened out under `lookahead`), or balanced across a fixed number
+/// of files. No statement is split or dropped; empty input gives no
+/// batches.
+pub fn batch_statements(statements: Vec<LocatedStatement>, limits: &BatchLimits) -> Vec<Batch> {
+    if let Some(num_files) = limits.balance_files {
+        return balance_statements(statements, num_files);
+    }
+    if limits.group_transactions {
+        return pack_transactions(statements, limits);
+    }
+    let mut batches = Vec::new();
+    let mut current_batch = Vec::new();
+    let mut current_size = 0;
+
+    // All sizes are in bytes of UTF-8 output, separators included
+    for statement in statements {
+        let mut statement_size = statement.output_len();
+        if !current_batch.is_empty() {
+            statement_size += limits.separator_len;
+        }
+
+        if current_size + statement_size > limits.max_bytes && !current_batch.is_empty() {
+            batches.push(current_batch);
+            current_batch = Vec::new();
+            current_size = 0;
+            statement_size = statement.output_len();
+        }
+
+        current_batch.push(statement);
+        current_size += statement_size;
+    }
+
+    if !current_batch.is_empty() {
+        batches.push(current_batch);
+    }
+    if limits.lookahead > 0 {
+        even_out_batches(
+            &mut batches,
+            limits.max_bytes,
+            limits.separator_len,
+            limits.lookahead,
+        );
+    }
+    batches
+}
+
+/// Greedily packs `statements` up to `limits.max_bytes` a transaction at a
+/// time: a transaction start and every statement through the end that
+/// closes it (or the end of input) form one unit, as does each statement
+/// outside a transaction. A unit larger than the limit gets a batch of its
+/// own. Lookahead doesn't apply, as it moves single statements.
+fn pack_transactions(statements: Vec<LocatedStatement>, limits: &BatchLimits) -> Vec<Batch> {
+    let mut batches = Vec::new();
+    let mut current_batch: Batch = Vec::new();
+    let mut current_size = 0;
+    let mut unit = Vec::new();
+
+    let mut statements = statements.into_iter();
+    while let Some(statement) = statements.next() {
+        let opens = category::is_transaction_start(&statement.text);
+        unit.push(statement);
+        if opens {
+            for statement in statements.by_ref() {
+                let closes = category::is_transaction_end(&statement.text);
+                unit.push(statement);
+                if closes {
+                    break;
+                }
+            }
+        }
+
+        let mut unit_size = batch_size(&unit, limits.separator_len);
+        if !current_batch.is_empty() {
+            unit_size += limits.separator_len;
+        }
+        if current_size + unit_size > limits.max_bytes && !current_batch.is_empty() {
+            batches.push(std::mem::take(&mut current_batch));
+            current_size = 0;
+            unit_size -= limits.separator_len;
+        }
+        current_batch.append(&mut unit);
+        current_size += unit_size;
+    }
+
+    if !current_batch.is_empty() {
+        batches.push(current_batch);
+    }
+    batches
+}
+
+/// Output size of `batch`, separators included.
+fn batch_size(batch: &[LocatedStatement], separator_len: usize) -> usize {
+    let statements: usize = batch.iter().map(LocatedStatement::output_len).sum();
+    statements + separator_len * batch.len().saturating_sub(1)
+}
+
+/// Moves statements from the end of each of the greedily cut `batches` to
+/// the start of the next while that raises the smaller of the two sizes
+/// and keeps the next within `max_size`. Only a next batch of at most
+/// `lookahead` statements is considered, as a batcher seeing that far past
+/// the cut could know where it ends.
+fn even_out_batches(
+    batches: &mut [Vec<LocatedStatement>],
+    max_size: usize,
+    separator_len: usize,
+    lookahead: usize,
+) {
+    for i in 0..batches.len().saturating_sub(1) {
+        let (head, tail) = batches.split_at_mut(i + 1);
+        let (current, next) = (&mut head[i], &mut tail[0]);
+        if next.len() > lookahead {
+            continue;
+        }
+        let mut current_size = batch_size(current, separator_len);
+        let mut next_size = batch_size(next, separator_len);
+        while current.len() > 1 {
+            let moved = current[current.len() - 1].output_len() + separator_len;
+            let (new_current, new_next) = (current_size - moved, next_size + moved);
+            if new_next > max_size || new_current.min(new_next) <= current_size.min(next_size) {
+                break;
+            }
+            let statement = current.pop().expect("batch has more than one statement");
+            next.insert(0, statement);
+            (current_size, next_size) = (new_current, new_next);
+        }
+    }
+}
+
+/// Packs statements into `num_files` bins of similar size, largest first.
+///
+/// DDL statements are pinned, in order, to the head of the first file so
+/// schema changes still load before any data. Each file keeps its
+/// statements in input order; bins left empty are dropped.
+fn balance_statements(
+    statements: Vec<LocatedStatement>,
+    num_files: usize,
+) -> Vec<Vec<LocatedStatement>> {
+    let mut bins: Vec<Vec<usize>> = vec![Vec::new(); num_files];
+    let mut sizes = vec![0; num_files];
+    let mut dml = Vec::new();
+    for (i, statement) in statements.iter().enumerate() {
+        if category::categorize(&statement.text) == Category::Ddl {
+            bins[0].push(i);
+            sizes[0] += statement.output_len();
+        } else {
+            dml.push(i);
+        }
+    }
+
+    dml.sort_by_key(|&i| std::cmp::Reverse(statements[i].output_len()));
+    for i in dml {
+        let (smallest, _) = sizes
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, size)| *size)
+            .expect("at least one bin");
+        bins[smallest].push(i);
+        sizes[smallest] += statements[i].output_len();
+    }
+
+    let mut statements: Vec<Option<LocatedStatement>> = statements.into_iter().map(Some).collect();
+    bins.into_iter()
+        .filter(|bin| !bin.is_empty())
+        .map(|mut bin| {
+            bin.sort_unstable();
+            bin.into_iter()
+                .filter_map(|i| statements[i].take())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(text: &str) -> LocatedStatement {
+        LocatedStatement {
+            text: text.to_string(),
+            line: 1,
+            data: None,
+            delimiter: None,
+            boundary: None,
+            terminated: true,
+        }
+    }
+
+    fn texts(batches: &[Batch]) -> Vec<Vec<&str>> {
+        batches
+            .iter()
+            .map(|batch| batch.iter().map(|s| s.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_empty_input_has_no_batches() {
+        assert!(batch_statements(Vec::new(), &BatchLimits::new(100)).is_empty());
+    }
+
+    #[test]
+    fn test_oversized_statement_gets_its_own_batch() {
+        let big = "x".repeat(50);
+        let statements = vec![statement("a"), statement(&big), statement("b")];
+        let batches = batch_statements(statements, &BatchLimits::new(10));
+        assert_eq!(
+            texts(&batches),
+            vec![vec!["a"], vec![big.as_str()], vec!["b"]]
+        );
+    }
+
+    #[test]
+    fn test_statements_exactly_at_limit() {
+        // Each statement is 4 bytes with its `;`, plus 2 per separator
+        let statements = vec![statement("aaa"), statement("bbb"), statement("ccc")];
+        let batches = batch_statements(statements.clone(), &BatchLimits::new(10));
+        assert_eq!(texts(&batches), vec![vec!["aaa", "bbb"], vec!["ccc"]]);
+
+        let batches = batch_statements(statements.clone(), &BatchLimits::new(9));
+        assert_eq!(texts(&batches), vec![vec!["aaa"], vec!["bbb"], vec!["ccc"]]);
+
+        let limits = BatchLimits {
+            separator_len: 0,
+            ..BatchLimits::new(12)
+        };
+        assert_eq!(batch_statements(statements, &limits).len(), 1);
+    }
+
+    #[test]
+    fn test_balance_ignores_size() {
+        let statements = vec![
+            statement("CREATE TABLE t (id INT)"),
+            statement("INSERT INTO t VALUES (1)"),
+            statement("INSERT INTO t VALUES (2)"),
+        ];
+        let limits = BatchLimits {
+            balance_files: Some(2),
+            ..BatchLimits::new(1)
+        };
+        let batches = batch_statements(statements, &limits);
+        assert_eq!(
+            texts(&batches),
+            vec![
+                vec!["CREATE TABLE t (id INT)", "INSERT INTO t VALUES (2)"],
+                vec!["INSERT INTO t VALUES (1)"]
+            ]
+        );
+    }
+}
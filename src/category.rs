@@ -0,0 +1,133 @@
+use crate::table::Cursor;
+
+/// Coarse statement categories, keyed off the leading keyword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Schema changes: `CREATE`, `ALTER`, `DROP`, `TRUNCATE`, `RENAME`, `COMMENT`.
+    Ddl,
+    /// Data changes: `INSERT`, `UPDATE`, `DELETE`, `REPLACE`, `MERGE`, `COPY`.
+    Dml,
+    /// Everything else (`SET`, `USE`, `SELECT`, transaction control, ...).
+    Other,
+}
+
+impl Category {
+    /// Lowercase name, used for `--split-by-type` subdirectories.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Ddl => "ddl",
+            Category::Dml => "dml",
+            Category::Other => "other",
+        }
+    }
+}
+
+/// Categorizes `statement` by its first keyword, ignoring leading comments.
+pub fn categorize(statement: &str) -> Category {
+    let Some(keyword) = Cursor::new(statement).word() else {
+        return Category::Other;
+    };
+    match keyword.to_ascii_uppercase().as_str() {
+        "CREATE" | "ALTER" | "DROP" | "TRUNCATE" | "RENAME" | "COMMENT" => Category::Ddl,
+        "INSERT" | "UPDATE" | "DELETE" | "REPLACE" | "MERGE" | "COPY" => Category::Dml,
+        _ => Category::Other,
+    }
+}
+
+/// Whether `statement` assigns a MySQL user or session variable
+/// (`SET @x := 1`, `SET @@session.sql_mode = ...`).
+pub fn is_variable_assignment(statement: &str) -> bool {
+    let mut cursor = Cursor::new(statement);
+    cursor.keyword("SET") && cursor.rest().starts_with('@')
+}
+
+/// Whether `statement` is a `DROP` of any kind of object.
+pub fn is_drop(statement: &str) -> bool {
+    Cursor::new(statement).keyword("DROP")
+}
+
+/// Whether `statement` opens a transaction: `START TRANSACTION`, or a
+/// `BEGIN` on its own or followed by `TRANSACTION`, `TRAN`, `WORK` or its
+/// mode (`BEGIN ISOLATION LEVEL ...`, SQLite's `BEGIN IMMEDIATE`), not the
+/// start of a compound block.
+pub fn is_transaction_start(statement: &str) -> bool {
+    let mut cursor = Cursor::new(statement);
+    if cursor.keyword("START") {
+        return cursor.keyword("TRANSACTION");
+    }
+    cursor.keyword("BEGIN")
+        && (cursor.rest().is_empty()
+            || [
+                "TRANSACTION",
+                "TRAN",
+                "WORK",
+                "ISOLATION",
+                "READ",
+                "DEFERRED",
+                "IMMEDIATE",
+                "EXCLUSIVE",
+            ]
+            .iter()
+            .any(|kw| cursor.keyword(kw)))
+}
+
+/// Whether `statement` ends a transaction: `COMMIT`, Postgres' `END` or
+/// a `ROLLBACK` that isn't to a savepoint.
+pub fn is_transaction_end(statement: &str) -> bool {
+    let mut cursor = Cursor::new(statement);
+    if cursor.keyword("COMMIT") {
+        return true;
+    }
+    if cursor.keyword("END") {
+        return cursor.rest().is_empty() || cursor.keyword("TRANSACTION") || cursor.keyword("WORK");
+    }
+    if cursor.keyword("ROLLBACK") {
+        let _ = cursor.keyword("TRANSACTION") || cursor.keyword("TRAN") || cursor.keyword("WORK");
+        return !cursor.keyword("TO");
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize() {
+        assert_eq!(categorize("CREATE TABLE t (id INT)"), Category::Ddl);
+        assert_eq!(
+            categorize("-- header\nalter table t add x int"),
+            Category::Ddl
+        );
+        assert_eq!(categorize("INSERT INTO t VALUES (1)"), Category::Dml);
+        assert_eq!(categorize("COPY t FROM stdin"), Category::Dml);
+        assert_eq!(categorize("SET NAMES utf8"), Category::Other);
+        assert_eq!(categorize("/* empty */"), Category::Other);
+        assert!(is_drop("-- cleanup\ndrop table if exists t"));
+        assert!(!is_drop("DROPPED_AT = NOW()"));
+        assert!(is_variable_assignment("-- ctx\nset @x := 1"));
+        assert!(!is_variable_assignment("SET NAMES utf8"));
+    }
+
+    #[test]
+    fn test_transaction_boundaries() {
+        for start in [
+            "BEGIN",
+            "begin transaction",
+            "START TRANSACTION READ ONLY",
+            "BEGIN ISOLATION LEVEL SERIALIZABLE",
+            "-- load\nBEGIN IMMEDIATE",
+        ] {
+            assert!(is_transaction_start(start), "{}", start);
+        }
+        assert!(!is_transaction_start("BEGIN SELECT 1; END"));
+        assert!(!is_transaction_start("START SLAVE"));
+
+        for end in ["COMMIT", "commit work", "END", "ROLLBACK", "ROLLBACK WORK"] {
+            assert!(is_transaction_end(end), "{}", end);
+        }
+        assert!(!is_transaction_end("ROLLBACK TO SAVEPOINT s1"));
+        assert!(!is_transaction_end("END IF"));
+        assert!(!is_transaction_end("SELECT 1"));
+    }
+}
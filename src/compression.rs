@@ -0,0 +1,51 @@
+use async_compression::tokio::write::{BzEncoder, GzipEncoder, ZstdEncoder};
+use async_compression::Level;
+use tokio::io::AsyncWrite;
+
+/// Codec applied to each output file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Suffix appended to the file name, including the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+            Compression::Bzip2 => ".bz2",
+        }
+    }
+
+    /// Shell command writing a file in this codec decompressed to stdout, or
+    /// `None` when files are uncompressed.
+    pub fn decompress_command(self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip -dc"),
+            Compression::Zstd => Some("zstd -dc"),
+            Compression::Bzip2 => Some("bzip2 -dc"),
+        }
+    }
+
+    /// Wraps `inner` in the encoder for this codec. `level` is codec-specific
+    /// (e.g. 1-9 for gzip, 1-22 for zstd); `None` uses the codec default.
+    pub fn encoder<W>(self, inner: W, level: Option<i32>) -> Box<dyn AsyncWrite + Unpin + Send>
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let level = level.map_or(Level::Default, Level::Precise);
+        match self {
+            Compression::None => Box::new(inner),
+            Compression::Gzip => Box::new(GzipEncoder::with_quality(inner, level)),
+            Compression::Zstd => Box::new(ZstdEncoder::with_quality(inner, level)),
+            Compression::Bzip2 => Box::new(BzEncoder::with_quality(inner, level)),
+        }
+    }
+}
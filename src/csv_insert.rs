@@ -0,0 +1,95 @@
+use crate::dialect::Dialect;
+use std::io::Read;
+
+/// SQL script of one `INSERT INTO <table> (<columns>) VALUES (...);` line
+/// per record of the CSV read from `reader`, whose first record names the
+/// columns.
+///
+/// `table` is written as given, so it may be schema-qualified or quoted.
+/// Column names that aren't plain identifiers are quoted for `dialect`.
+/// Every value is a string literal for the database to convert to the
+/// column's type, except empty fields, which become `NULL`.
+pub fn csv_to_inserts<R: Read>(
+    reader: R,
+    table: &str,
+    dialect: Dialect,
+) -> Result<String, csv::Error> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let columns: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|name| identifier(name, dialect))
+        .collect();
+    let head = format!("INSERT INTO {} ({}) VALUES ", table, columns.join(", "));
+
+    let mut script = String::new();
+    for record in reader.records() {
+        let values: Vec<String> = record?
+            .iter()
+            .map(|value| literal(value, dialect))
+            .collect();
+        script.push_str(&head);
+        script.push('(');
+        script.push_str(&values.join(", "));
+        script.push_str(");\n");
+    }
+    Ok(script)
+}
+
+/// `name`, quoted for `dialect` unless it is a plain identifier.
+fn identifier(name: &str, dialect: Dialect) -> String {
+    let plain = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        return name.to_string();
+    }
+    let quote = match dialect {
+        Dialect::Mysql => '`',
+        Dialect::Generic | Dialect::Postgres => '"',
+    };
+    let doubled = name.replace(quote, &format!("{}{}", quote, quote));
+    format!("{}{}{}", quote, doubled, quote)
+}
+
+/// `value` as a string literal (`NULL` if empty). Quotes are doubled;
+/// backslashes, which MySQL reads as escapes, are doubled too, inside an
+/// `E'...'` string for Postgres so it reads them the same way.
+fn literal(value: &str, dialect: Dialect) -> String {
+    if value.is_empty() {
+        return "NULL".to_string();
+    }
+    let escaped = value.replace('\'', "''");
+    if !value.contains('\\') {
+        return format!("'{}'", escaped);
+    }
+    let escaped = escaped.replace('\\', "\\\\");
+    match dialect {
+        Dialect::Postgres => format!("E'{}'", escaped),
+        Dialect::Generic | Dialect::Mysql => format!("'{}'", escaped),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_to_inserts() {
+        let csv = "id,name,\"unit price\"\n1,\"O'Neil, Pat\",2.50\n2,,\"C:\\tmp\"\n";
+        assert_eq!(
+            csv_to_inserts(csv.as_bytes(), "shop.items", Dialect::Mysql).unwrap(),
+            "INSERT INTO shop.items (id, name, `unit price`) VALUES ('1', 'O''Neil, Pat', '2.50');\n\
+             INSERT INTO shop.items (id, name, `unit price`) VALUES ('2', NULL, 'C:\\\\tmp');\n"
+        );
+        assert_eq!(
+            csv_to_inserts(csv.as_bytes(), "items", Dialect::Postgres)
+                .unwrap()
+                .lines()
+                .nth(1),
+            Some("INSERT INTO items (id, name, \"unit price\") VALUES ('2', NULL, E'C:\\\\tmp');")
+        );
+
+        // A record with the wrong number of fields is an error
+        assert!(csv_to_inserts("a,b\n1\n".as_bytes(), "t", Dialect::Generic).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+/// Words that may come between `CREATE` and the object keyword.
+const MODIFIERS: [&str; 10] = [
+    "OR",
+    "REPLACE",
+    "ALGORITHM",
+    "UNDEFINED",
+    "MERGE",
+    "TEMPTABLE",
+    "SQL",
+    "SECURITY",
+    "INVOKER",
+    "AGGREGATE",
+];
+
+/// `statement` without the `DEFINER = account` clause of a `CREATE VIEW`,
+/// `PROCEDURE`, `FUNCTION`, `TRIGGER` or `EVENT`, or `None` if it has none.
+///
+/// Only a clause before the object keyword is removed, so a `definer`
+/// column or string in the body is left alone. mysqldump's versioned
+/// comments (`/*!50013 DEFINER=... */`) are read as code, as MySQL reads
+/// them; other comments and quoted text are skipped. The account may be
+/// quoted or bare, with or without `@host`, or `CURRENT_USER[()]`.
+pub fn strip_definer(statement: &str) -> Option<String> {
+    let mut pos = 0;
+    let mut seen_create = false;
+    loop {
+        let rest = &statement[pos..];
+        let c = rest.chars().next()?;
+        match c {
+            c if c.is_whitespace() => pos += c.len_utf8(),
+            '-' if rest.starts_with("--") => pos += line_comment_len(rest),
+            '#' => pos += line_comment_len(rest),
+            '/' if rest.starts_with("/*!") => {
+                let version = rest[3..].bytes().take_while(u8::is_ascii_digit).count();
+                pos += 3 + version;
+            }
+            '/' if rest.starts_with("/*") => pos += 2 + rest[2..].find("*/")? + 2,
+            // The end of a versioned comment
+            '*' if rest.starts_with("*/") => pos += 2,
+            '\'' | '"' | '`' => pos += quoted_len(rest)?,
+            c if is_word_char(c) => {
+                let len = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+                let word = &rest[..len];
+                if !seen_create {
+                    if !word.eq_ignore_ascii_case("CREATE") {
+                        return None;
+                    }
+                    seen_create = true;
+                } else if word.eq_ignore_ascii_case("DEFINER") {
+                    if let Some(clause) = definer_clause_len(rest) {
+                        let end = pos + clause;
+                        let trailing = statement[end..]
+                            .find(|c| c != ' ' && c != '\t')
+                            .unwrap_or(statement.len() - end);
+                        return Some(format!(
+                            "{}{}",
+                            &statement[..pos],
+                            &statement[end + trailing..]
+                        ));
+                    }
+                } else if !MODIFIERS.iter().any(|m| word.eq_ignore_ascii_case(m)) {
+                    // The object keyword, or not a statement with a definer
+                    return None;
+                }
+                pos += len;
+            }
+            // The clause comes before any parenthesised list or body
+            '(' => return None,
+            _ => pos += c.len_utf8(),
+        }
+    }
+}
+
+/// Length of `DEFINER = account` at the start of `rest`, if it is one.
+fn definer_clause_len(rest: &str) -> Option<usize> {
+    let mut pos = "DEFINER".len();
+    pos += whitespace_len(&rest[pos..]);
+    if !rest[pos..].starts_with('=') {
+        return None;
+    }
+    pos += 1;
+    pos += whitespace_len(&rest[pos..]);
+
+    let user = &rest[pos..];
+    if user
+        .get(.."CURRENT_USER".len())
+        .is_some_and(|word| word.eq_ignore_ascii_case("CURRENT_USER"))
+    {
+        pos += "CURRENT_USER".len();
+        if rest[pos..].starts_with("()") {
+            pos += 2;
+        }
+        return Some(pos);
+    }
+    pos += account_part_len(user)?;
+    if rest[pos..].starts_with('@') {
+        pos += 1;
+        pos += account_part_len(&rest[pos..])?;
+    }
+    Some(pos)
+}
+
+/// Length of a user or host name at the start of `rest`: quoted, or a run
+/// of the characters a bare name or host pattern can hold.
+fn account_part_len(rest: &str) -> Option<usize> {
+    match rest.chars().next()? {
+        '\'' | '"' | '`' => quoted_len(rest),
+        _ => {
+            let len = rest
+                .find(|c: char| !(is_word_char(c) || matches!(c, '.' | '-' | '%')))
+                .unwrap_or(rest.len());
+            (len > 0).then_some(len)
+        }
+    }
+}
+
+/// Length of the quoted text opening `rest`, quotes included. A doubled
+/// quote is an escaped one; backslashes escape inside string literals.
+fn quoted_len(rest: &str) -> Option<usize> {
+    let quote = rest.chars().next()?;
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quote != '`' {
+            chars.next();
+        } else if c == quote {
+            if rest[i + 1..].starts_with(quote) {
+                chars.next();
+                continue;
+            }
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+fn line_comment_len(rest: &str) -> usize {
+    rest.find('\n').map_or(rest.len(), |i| i + 1)
+}
+
+fn whitespace_len(rest: &str) -> usize {
+    rest.len() - rest.trim_start().len()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_definer_clauses() {
+        assert_eq!(
+            strip_definer("CREATE DEFINER=`root`@`localhost` PROCEDURE p() BEGIN SELECT 1; END")
+                .as_deref(),
+            Some("CREATE PROCEDURE p() BEGIN SELECT 1; END")
+        );
+        assert_eq!(
+            strip_definer(
+                "/*!50001 CREATE ALGORITHM=UNDEFINED */\n\
+                 /*!50013 DEFINER=`app`@`%` SQL SECURITY DEFINER */\n\
+                 /*!50001 VIEW `v` AS select 'DEFINER=`x`@`y`' AS `d` */"
+            )
+            .as_deref(),
+            Some(
+                "/*!50001 CREATE ALGORITHM=UNDEFINED */\n\
+                 /*!50013 SQL SECURITY DEFINER */\n\
+                 /*!50001 VIEW `v` AS select 'DEFINER=`x`@`y`' AS `d` */"
+            )
+        );
+        assert_eq!(
+            strip_definer(
+                "create or replace definer = 'o''neil'@'10.0.%' function f() returns int return 1"
+            )
+            .as_deref(),
+            Some("create or replace function f() returns int return 1")
+        );
+        assert_eq!(
+            strip_definer(
+                "/*!50003 CREATE*/ /*!50017 DEFINER=root@localhost*/ /*!50003 TRIGGER t */"
+            )
+            .as_deref(),
+            Some("/*!50003 CREATE*/ /*!50017 */ /*!50003 TRIGGER t */")
+        );
+        assert_eq!(
+            strip_definer("CREATE DEFINER = CURRENT_USER() EVENT e DO SELECT 1").as_deref(),
+            Some("CREATE EVENT e DO SELECT 1")
+        );
+    }
+
+    #[test]
+    fn test_leaves_other_statements() {
+        assert_eq!(
+            strip_definer("CREATE VIEW v AS SELECT definer = 1 FROM t"),
+            None
+        );
+        assert_eq!(
+            strip_definer("CREATE TABLE t (definer VARCHAR(10) DEFAULT 'DEFINER=x')"),
+            None
+        );
+        assert_eq!(
+            strip_definer("INSERT INTO t VALUES ('DEFINER=`a`@`b`')"),
+            None
+        );
+        assert_eq!(
+            strip_definer("CREATE SQL SECURITY DEFINER VIEW v AS SELECT 1"),
+            None
+        );
+        assert_eq!(
+            strip_definer("CREATE TABLE t AS SELECT definer = 'x' AS d FROM u"),
+            None
+        );
+    }
+}
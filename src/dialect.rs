@@ -0,0 +1,291 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// SQL dialect, selecting the dialect-specific scanner rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dialect {
+    /// Plain `;`-terminated statements.
+    #[default]
+    Generic,
+    /// Honors client `DELIMITER` directives.
+    Mysql,
+    /// Honors `$tag$ ... $tag$` dollar-quoted strings.
+    Postgres,
+}
+
+impl Dialect {
+    /// Guesses the dialect from `content` for `--auto-delimiter`: a
+    /// `DELIMITER` directive means MySQL, a dollar-quote tag means Postgres.
+    pub fn sniff(content: &str) -> Dialect {
+        static DELIMITER: OnceLock<Regex> = OnceLock::new();
+        static DOLLAR_QUOTE: OnceLock<Regex> = OnceLock::new();
+        let delimiter =
+            DELIMITER.get_or_init(|| Regex::new(r"(?im)^[ \t]*DELIMITER[ \t]+\S").unwrap());
+        let dollar_quote =
+            DOLLAR_QUOTE.get_or_init(|| Regex::new(r"(^|[^\w$])\$([A-Za-z_]\w*)?\$").unwrap());
+
+        if delimiter.is_match(content) {
+            Dialect::Mysql
+        } else if dollar_quote.is_match(content) {
+            Dialect::Postgres
+        } else {
+            Dialect::Generic
+        }
+    }
+}
+
+/// A dialect `--detect-dialect` can recognize, including ones without
+/// scanner rules of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceDialect {
+    Mysql,
+    Postgres,
+    SqlServer,
+    Sqlite,
+}
+
+impl SourceDialect {
+    const ALL: [SourceDialect; 4] = [
+        SourceDialect::Mysql,
+        SourceDialect::Postgres,
+        SourceDialect::SqlServer,
+        SourceDialect::Sqlite,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SourceDialect::Mysql => "MySQL",
+            SourceDialect::Postgres => "Postgres",
+            SourceDialect::SqlServer => "SQL Server",
+            SourceDialect::Sqlite => "SQLite",
+        }
+    }
+
+    /// The scanner dialect to split this dialect's dumps with.
+    pub fn scanner_dialect(self) -> Dialect {
+        match self {
+            SourceDialect::Mysql => Dialect::Mysql,
+            SourceDialect::Postgres => Dialect::Postgres,
+            SourceDialect::SqlServer | SourceDialect::Sqlite => Dialect::Generic,
+        }
+    }
+}
+
+/// A dialect-specific construct found by [`detect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signal {
+    pub dialect: SourceDialect,
+    pub description: &'static str,
+    /// Occurrences in the sample.
+    pub count: usize,
+}
+
+/// The outcome of [`detect`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Detection {
+    /// The most likely dialect, or `None` when no signal was found.
+    pub dialect: Option<SourceDialect>,
+    /// Every signal found, strongest first within each dialect.
+    pub signals: Vec<Signal>,
+}
+
+/// Constructs `detect` looks for: dialect, description, pattern and weight.
+const SIGNALS: [(SourceDialect, &str, &str, usize); 18] = [
+    (
+        SourceDialect::Mysql,
+        "DELIMITER directives",
+        r"(?im)^[ \t]*DELIMITER[ \t]+\S",
+        3,
+    ),
+    (SourceDialect::Mysql, "/*! executable comments", r"/\*!", 3),
+    (
+        SourceDialect::Mysql,
+        "ENGINE= table options",
+        r"(?i)\)\s*ENGINE\s*=",
+        2,
+    ),
+    (
+        SourceDialect::Mysql,
+        "LOCK TABLES",
+        r"(?im)^[ \t]*LOCK\s+TABLES\b",
+        2,
+    ),
+    (
+        SourceDialect::Mysql,
+        "backtick-quoted identifiers",
+        r"`\w+`",
+        1,
+    ),
+    (
+        SourceDialect::Postgres,
+        "dollar-quoted strings",
+        r"(^|[^\w$])\$([A-Za-z_]\w*)?\$",
+        3,
+    ),
+    (
+        SourceDialect::Postgres,
+        "COPY ... FROM stdin",
+        r"(?im)^[ \t]*COPY\b[^;]*\bFROM\s+stdin",
+        3,
+    ),
+    (
+        SourceDialect::Postgres,
+        "SET search_path",
+        r"(?i)\bSET\s+search_path\b",
+        2,
+    ),
+    (
+        SourceDialect::Postgres,
+        "pg_catalog references",
+        r"\bpg_catalog\.",
+        2,
+    ),
+    (SourceDialect::Postgres, ":: type casts", r"[\w)']::\w", 1),
+    (
+        SourceDialect::SqlServer,
+        "GO batch separators",
+        r"(?im)^[ \t]*GO[ \t]*\r?$",
+        3,
+    ),
+    (
+        SourceDialect::SqlServer,
+        "SET ANSI_NULLS/QUOTED_IDENTIFIER/IDENTITY_INSERT",
+        r"(?i)\bSET\s+(ANSI_NULLS|QUOTED_IDENTIFIER|IDENTITY_INSERT)\b",
+        2,
+    ),
+    (
+        SourceDialect::SqlServer,
+        "IDENTITY columns",
+        r"(?i)\bIDENTITY\s*\(",
+        2,
+    ),
+    (
+        SourceDialect::SqlServer,
+        "bracket-quoted identifiers",
+        r"\[\w+\]\.\[\w+\]",
+        1,
+    ),
+    (
+        SourceDialect::Sqlite,
+        "PRAGMA statements",
+        r"(?im)^[ \t]*PRAGMA\b",
+        3,
+    ),
+    (
+        SourceDialect::Sqlite,
+        "sqlite_* system tables",
+        r"\bsqlite_(sequence|master|schema)\b",
+        3,
+    ),
+    (
+        SourceDialect::Sqlite,
+        "AUTOINCREMENT",
+        r"(?i)\bAUTOINCREMENT\b",
+        2,
+    ),
+    (
+        SourceDialect::Sqlite,
+        "BEGIN TRANSACTION",
+        r"(?im)^BEGIN\s+TRANSACTION\s*;",
+        1,
+    ),
+];
+
+/// Guesses which dialect `sample` (typically the head of a dump) was
+/// written for. Each dialect scores the weights of the signals it shows,
+/// however often each occurs; the highest score wins, ties going to the
+/// earlier of MySQL, Postgres, SQL Server and SQLite. Matching is textual,
+/// so a signal inside a string literal or comment counts too.
+pub fn detect(sample: &str) -> Detection {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    let patterns = PATTERNS.get_or_init(|| {
+        SIGNALS
+            .iter()
+            .map(|(_, _, pattern, _)| Regex::new(pattern).unwrap())
+            .collect()
+    });
+
+    let mut detection = Detection::default();
+    let mut scores = [0; SourceDialect::ALL.len()];
+    for ((dialect, description, _, weight), pattern) in SIGNALS.iter().zip(patterns) {
+        let count = pattern.find_iter(sample).count();
+        if count == 0 {
+            continue;
+        }
+        if let Some(i) = SourceDialect::ALL.iter().position(|d| d == dialect) {
+            scores[i] += weight;
+        }
+        detection.signals.push(Signal {
+            dialect: *dialect,
+            description,
+            count,
+        });
+    }
+    let best = (0..scores.len()).fold(0, |best, i| if scores[i] > scores[best] { i } else { best });
+    if scores[best] > 0 {
+        detection.dialect = Some(SourceDialect::ALL[best]);
+    }
+    detection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(Dialect::sniff("DELIMITER $$\nSELECT 1$$\n"), Dialect::Mysql);
+        assert_eq!(
+            Dialect::sniff("CREATE FUNCTION f() AS $body$ SELECT 1 $body$;"),
+            Dialect::Postgres
+        );
+        assert_eq!(Dialect::sniff("SELECT price$usd FROM t;"), Dialect::Generic);
+    }
+
+    #[test]
+    fn test_detect_representative_snippets() {
+        let cases = [
+            (
+                "/*!40101 SET NAMES utf8 */;\n\
+                 CREATE TABLE `users` (`id` int) ENGINE=InnoDB;\n\
+                 LOCK TABLES `users` WRITE;\n",
+                Some(SourceDialect::Mysql),
+            ),
+            (
+                "SET search_path = public, pg_catalog;\n\
+                 CREATE FUNCTION f() RETURNS int AS $$ SELECT 1 $$ LANGUAGE sql;\n\
+                 COPY public.users (id) FROM stdin;\n1\n\\.\n",
+                Some(SourceDialect::Postgres),
+            ),
+            (
+                "SET ANSI_NULLS ON\nGO\n\
+                 CREATE TABLE [dbo].[users] ([id] int IDENTITY(1,1))\nGO\n",
+                Some(SourceDialect::SqlServer),
+            ),
+            (
+                "PRAGMA foreign_keys=OFF;\nBEGIN TRANSACTION;\n\
+                 CREATE TABLE users (id INTEGER PRIMARY KEY AUTOINCREMENT);\n\
+                 DELETE FROM sqlite_sequence;\nCOMMIT;\n",
+                Some(SourceDialect::Sqlite),
+            ),
+            ("SELECT 1;\nINSERT INTO t VALUES (1);\n", None),
+        ];
+        for (sample, expected) in cases {
+            assert_eq!(detect(sample).dialect, expected, "{}", sample);
+        }
+
+        let detection = detect("SELECT `a` FROM `t`;\nSELECT `b` FROM `t`;");
+        assert_eq!(
+            detection.signals,
+            vec![Signal {
+                dialect: SourceDialect::Mysql,
+                description: "backtick-quoted identifiers",
+                count: 4,
+            }]
+        );
+        assert_eq!(
+            detection.dialect.map(SourceDialect::scanner_dialect),
+            Some(Dialect::Mysql)
+        );
+    }
+}
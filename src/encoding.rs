@@ -0,0 +1,87 @@
+use encoding_rs::{EncoderResult, Encoding, UTF_8};
+use std::borrow::Cow;
+
+/// What `--on-unmappable` does with characters the output encoding lacks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnUnmappable {
+    /// Write `?` in their place.
+    #[default]
+    Replace,
+    /// Fail without writing output.
+    Error,
+}
+
+/// Looks up an output encoding by its WHATWG label (`latin1`,
+/// `windows-1252`, `shift_jis`, ...). UTF-16 is refused, as `encoding_rs`
+/// only decodes it.
+pub fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    match Encoding::for_label(label.trim().as_bytes()) {
+        Some(encoding) if encoding.output_encoding() == encoding => Ok(encoding),
+        Some(encoding) => Err(format!("cannot write {} output", encoding.name())),
+        None => Err(format!("unknown encoding `{}`", label)),
+    }
+}
+
+/// `text` in `encoding`, with `?` for each character it can't represent.
+/// Borrows `text` when its bytes are already right, as for ASCII text in
+/// an ASCII-compatible encoding.
+pub fn encode<'a>(text: &'a str, encoding: &'static Encoding) -> Cow<'a, [u8]> {
+    match encode_with(text, encoding, true) {
+        Ok(bytes) => bytes,
+        Err(_) => unreachable!("replacing encodes every character"),
+    }
+}
+
+/// The first character of `text` that `encoding` can't represent, if any.
+pub fn first_unmappable(text: &str, encoding: &'static Encoding) -> Option<char> {
+    encode_with(text, encoding, false).err()
+}
+
+/// Encodes `text`, writing `?` for an unmappable character under
+/// `replace` and otherwise stopping at it.
+fn encode_with<'a>(
+    text: &'a str,
+    encoding: &'static Encoding,
+    replace: bool,
+) -> Result<Cow<'a, [u8]>, char> {
+    if encoding == UTF_8 || (encoding.is_ascii_compatible() && text.is_ascii()) {
+        return Ok(Cow::Borrowed(text.as_bytes()));
+    }
+    let mut encoder = encoding.new_encoder();
+    let mut out = Vec::with_capacity(
+        encoder
+            .max_buffer_length_from_utf8_without_replacement(text.len())
+            .unwrap_or(text.len()),
+    );
+    let mut rest = text;
+    loop {
+        let (result, read) =
+            encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, true);
+        rest = &rest[read..];
+        match result {
+            EncoderResult::InputEmpty => return Ok(Cow::Owned(out)),
+            EncoderResult::OutputFull => out.reserve(rest.len().max(16)),
+            EncoderResult::Unmappable(_) if replace => out.push(b'?'),
+            EncoderResult::Unmappable(c) => return Err(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let latin1 = parse_encoding("latin1").unwrap();
+        assert_eq!(latin1.name(), "windows-1252");
+        assert!(matches!(encode("SELECT 1", latin1), Cow::Borrowed(_)));
+        assert_eq!(&*encode("'café'", latin1), b"'caf\xe9'");
+        assert_eq!(&*encode("'日本'", latin1), b"'??'");
+        assert_eq!(first_unmappable("'café 日本'", latin1), Some('日'));
+        assert_eq!(first_unmappable("'café'", latin1), None);
+
+        assert!(parse_encoding("utf-16le").is_err());
+        assert!(parse_encoding("klingon").is_err());
+    }
+}
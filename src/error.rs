@@ -0,0 +1,222 @@
+use crate::scanner::Unclosed;
+use crate::validate::Malformed;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// Errors produced while splitting a SQL file.
+#[derive(Debug)]
+pub enum SplitError {
+    Io(io::Error),
+    /// A single statement exceeded the configured `--max-statement-bytes`.
+    StatementTooLarge {
+        line: usize,
+        size: usize,
+        limit: usize,
+    },
+    /// The `--output-ext` value would escape the output directory.
+    InvalidOutputExt(String),
+    /// A `--pipe-to` command exited unsuccessfully for one output file.
+    CommandFailed {
+        command: String,
+        file: PathBuf,
+        status: ExitStatus,
+    },
+    /// `--strict` was set and the input ended with these constructs open.
+    Unclosed(Vec<Unclosed>),
+    /// `--trailing-statement-warn` and `--strict` were set and the input's
+    /// last statement, starting on `line`, had no delimiter.
+    UnterminatedLastStatement {
+        line: usize,
+    },
+    /// `--expand-go-count` was set and the batch starting on `line` was
+    /// ended by a `GO` asking for more than `limit` copies.
+    GoCountTooLarge {
+        line: usize,
+        count: u64,
+        limit: usize,
+    },
+    /// `--validate-sql` and `--strict` were set and these statements
+    /// looked malformed.
+    Malformed(Vec<Malformed>),
+    /// `--ascii error` was set and a statement contained this character.
+    NonAscii {
+        line: usize,
+        character: char,
+    },
+    /// `--on-unmappable error` was set and a statement contained this
+    /// character, which the `--encoding-out` encoding lacks.
+    Unmappable {
+        line: usize,
+        character: char,
+        encoding: &'static str,
+    },
+    /// `--fail-on-warnings` was set and the run produced these warnings.
+    Warnings(Vec<String>),
+    /// The `--from-csv` input could not be read as CSV.
+    Csv(csv::Error),
+    /// An included file (see [`crate::include`]) could not be read.
+    Include {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A file includes itself: the files from the input to the repeat.
+    IncludeCycle(Vec<PathBuf>),
+    /// `--keep-going` was set and these output files, by path, failed to
+    /// write; the others were written.
+    WritesFailed(Vec<(PathBuf, SplitError)>),
+    /// Output file `index` (1-based, in load order) at `path` took longer
+    /// than the `--write-timeout` to write.
+    WriteTimeout {
+        index: usize,
+        path: PathBuf,
+        timeout: Duration,
+    },
+    /// The output location could not be created or written to.
+    PermissionDenied {
+        path: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl SplitError {
+    /// Attributes an I/O error to `path`, singling out permission problems.
+    pub fn at_path(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> SplitError {
+        let path = path.as_ref().to_path_buf();
+        move |e| match e.kind() {
+            io::ErrorKind::PermissionDenied => SplitError::PermissionDenied { path, source: e },
+            _ => SplitError::Io(e),
+        }
+    }
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::Io(e) => write!(f, "{}", e),
+            SplitError::StatementTooLarge { line, size, limit } => write!(
+                f,
+                "statement starting on line {} is {} bytes, exceeding the limit of {} bytes",
+                line, size, limit
+            ),
+            SplitError::InvalidOutputExt(ext) => write!(
+                f,
+                "invalid output extension {:?}: it must not contain path separators",
+                ext
+            ),
+            SplitError::CommandFailed {
+                command,
+                file,
+                status,
+            } => write!(
+                f,
+                "`{}` failed for {} ({})",
+                command,
+                file.display(),
+                status
+            ),
+            SplitError::Unclosed(unclosed) => {
+                let unclosed: Vec<String> = unclosed.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "input ended with unclosed constructs (truncated?): {}",
+                    unclosed.join("; ")
+                )
+            }
+            SplitError::UnterminatedLastStatement { line } => write!(
+                f,
+                "the last statement, starting on line {}, has no delimiter (truncated input?)",
+                line
+            ),
+            SplitError::GoCountTooLarge { line, count, limit } => write!(
+                f,
+                "batch starting on line {} ends with GO {}, over the limit of {} copies (--expand-go-count)",
+                line, count, limit
+            ),
+            SplitError::Malformed(malformed) => {
+                let malformed: Vec<String> = malformed.iter().map(ToString::to_string).collect();
+                write!(f, "malformed statements: {}", malformed.join("; "))
+            }
+            SplitError::NonAscii { line, character } => write!(
+                f,
+                "statement starting on line {} contains non-ASCII character {:?} (U+{:04X})",
+                line, character, *character as u32
+            ),
+            SplitError::Unmappable {
+                line,
+                character,
+                encoding,
+            } => write!(
+                f,
+                "statement starting on line {} contains {:?} (U+{:04X}), which {} can't represent",
+                line, character, *character as u32, encoding
+            ),
+            SplitError::Warnings(warnings) => {
+                write!(f, "failing on warnings: {}", warnings.join("; "))
+            }
+            SplitError::Csv(e) => write!(f, "invalid CSV input: {}", e),
+            SplitError::Include { path, source } => {
+                write!(f, "cannot include {}: {}", path.display(), source)
+            }
+            SplitError::IncludeCycle(cycle) => {
+                let cycle: Vec<String> = cycle.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "include cycle: {}", cycle.join(" -> "))
+            }
+            SplitError::WritesFailed(failed) => {
+                let failed: Vec<String> = failed
+                    .iter()
+                    .map(|(path, e)| format!("{}: {}", path.display(), e))
+                    .collect();
+                write!(
+                    f,
+                    "{} output file(s) failed to write (rerun with --resume to retry them): {}",
+                    failed.len(),
+                    failed.join("; ")
+                )
+            }
+            SplitError::WriteTimeout {
+                index,
+                path,
+                timeout,
+            } => write!(
+                f,
+                "output file {} ({}) did not finish writing within {:?} (--write-timeout)",
+                index,
+                path.display(),
+                timeout
+            ),
+            SplitError::PermissionDenied { path, .. } => write!(
+                f,
+                "permission denied writing to {}: check that the current user can write \
+                 there (e.g. `chmod u+w` on the directory), or pick another --output-dir",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SplitError::Io(e) => Some(e),
+            SplitError::Csv(e) => Some(e),
+            SplitError::Include { source, .. } => Some(source),
+            SplitError::PermissionDenied { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<csv::Error> for SplitError {
+    fn from(e: csv::Error) -> Self {
+        SplitError::Csv(e)
+    }
+}
+
+impl From<io::Error> for SplitError {
+    fn from(e: io::Error) -> Self {
+        SplitError::Io(e)
+    }
+}
@@ -0,0 +1,141 @@
+use crate::dialect::Dialect;
+use crate::validate::{dollar_tag_len, quoted_len};
+use std::fmt;
+
+/// Why `--escape-validation` flagged a backslash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeProblem {
+    /// `\'` followed by `,`, `)`, `;` or the end of the line, where the
+    /// quote most likely meant to close the string the backslash kept open
+    /// (as in `'C:\dir\'`, from a dump that doesn't escape backslashes).
+    EscapedClosingQuote,
+    /// A backslash ending the statement, escaping nothing.
+    TrailingBackslash,
+}
+
+impl fmt::Display for EscapeProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscapeProblem::EscapedClosingQuote => {
+                write!(f, "escapes a quote that looks like it ends the string")
+            }
+            EscapeProblem::TrailingBackslash => write!(f, "ends the statement"),
+        }
+    }
+}
+
+/// A backslash `--escape-validation` flagged, by the input line it is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspiciousEscape {
+    pub line: usize,
+    pub problem: EscapeProblem,
+}
+
+impl fmt::Display for SuspiciousEscape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "backslash on line {} {}", self.line, self.problem)
+    }
+}
+
+/// The first suspicious backslash in `statement`, with the number of lines
+/// before it in the statement. String literals are read as the scanner
+/// reads them, a backslash escaping the next character, so a flagged `\'`
+/// is one that kept a string open where its author probably closed it and
+/// may have moved statement boundaries with it. A heuristic: a string
+/// that really holds `\', ` is flagged too. Backslashes in comments,
+/// quoted identifiers and dollar-quoted bodies are left alone.
+pub fn check_escapes(statement: &str, dialect: Dialect) -> Option<(usize, EscapeProblem)> {
+    let lines_before = |pos: usize| statement[..pos].matches('\n').count();
+    let mut pos = 0;
+    while let Some(c) = statement[pos..].chars().next() {
+        let rest = &statement[pos..];
+        let len = match c {
+            '-' if rest.starts_with("--") => rest.find('\n').map_or(rest.len(), |n| n + 1),
+            '#' if dialect == Dialect::Mysql => rest.find('\n').map_or(rest.len(), |n| n + 1),
+            '/' if rest.starts_with("/*") && !rest.starts_with("/*!") => {
+                rest[2..].find("*/").map_or(rest.len(), |n| n + 4)
+            }
+            '"' | '`' => quoted_len(rest, c).unwrap_or(rest.len()),
+            '$' if dialect == Dialect::Postgres => match dollar_tag_len(rest) {
+                Some(tag_len) => rest[tag_len..]
+                    .find(&rest[..tag_len])
+                    .map_or(rest.len(), |n| 2 * tag_len + n),
+                None => 1,
+            },
+            '\'' => {
+                let mut chars = rest.char_indices().skip(1);
+                let mut len = rest.len();
+                while let Some((i, c)) = chars.next() {
+                    if c == '\\' {
+                        match chars.next() {
+                            Some((_, '\'')) if looks_closed(&rest[i + 2..]) => {
+                                return Some((
+                                    lines_before(pos + i),
+                                    EscapeProblem::EscapedClosingQuote,
+                                ))
+                            }
+                            Some(_) => {}
+                            None => {
+                                return Some((
+                                    lines_before(pos + i),
+                                    EscapeProblem::TrailingBackslash,
+                                ))
+                            }
+                        }
+                    } else if c == '\'' {
+                        if rest[i + 1..].starts_with('\'') {
+                            chars.next();
+                            continue;
+                        }
+                        len = i + 1;
+                        break;
+                    }
+                }
+                len
+            }
+            '\\' if rest.trim_end().len() == 1 => {
+                return Some((lines_before(pos), EscapeProblem::TrailingBackslash))
+            }
+            c => c.len_utf8(),
+        };
+        pos += len;
+    }
+    None
+}
+
+/// Whether the text after an escaped quote reads like code after a
+/// closed string: a separator, or nothing more on the line.
+fn looks_closed(after: &str) -> bool {
+    let after = after.trim_start_matches([' ', '\t']);
+    after.is_empty() || after.starts_with([',', ')', ';', '\n', '\r'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_escapes() {
+        let flagged = |statement: &str| check_escapes(statement, Dialect::Mysql);
+        assert_eq!(
+            flagged("INSERT INTO t VALUES\n('C:\\dir\\', 2), ('b', 3)"),
+            Some((1, EscapeProblem::EscapedClosingQuote))
+        );
+        assert_eq!(
+            flagged("SELECT 'a\\"),
+            Some((0, EscapeProblem::TrailingBackslash))
+        );
+        assert_eq!(
+            flagged("SELECT 1 \\\n"),
+            Some((0, EscapeProblem::TrailingBackslash))
+        );
+        for statement in [
+            "SELECT 'it\\'s', 'a\\\\', '\\'', 'x\\n;'",
+            "SELECT `a\\`, 'b' -- 'c\\', d\n",
+            "SELECT 'it''s' /* '\\', */",
+        ] {
+            assert_eq!(flagged(statement), None, "{}", statement);
+        }
+        assert_eq!(check_escapes("SELECT $$a\\', b$$", Dialect::Postgres), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! C ABI for non-Rust callers (e.g. Python via `ctypes`).
+//!
+//! ```python
+//! lib = ctypes.CDLL("libsql_splitter.so")
+//! files = lib.sqlsplitter_split(b"dump.sql", b"out", 1000)
+//! ```
+
+use crate::{SplitError, SqlSplitter};
+use std::ffi::{c_char, c_int, CStr};
+
+/// A path argument was null or not valid UTF-8.
+pub const SQLSPLITTER_EINVAL: c_int = -1;
+/// Reading the input or writing the output failed.
+pub const SQLSPLITTER_EIO: c_int = -2;
+/// The output location is not writable.
+pub const SQLSPLITTER_EPERM: c_int = -3;
+/// A statement exceeded the configured size limit.
+pub const SQLSPLITTER_ETOOBIG: c_int = -4;
+
+const CONCURRENT_WRITES: usize = 4;
+
+/// Splits `input_path` into `output_dir` with files of at most
+/// `max_size_kb` kilobytes. Returns the number of files written, or one of
+/// the negative `SQLSPLITTER_E*` codes on failure.
+///
+/// # Safety
+///
+/// `input_path` and `output_dir` must be null or point to NUL-terminated
+/// strings that stay valid for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn sqlsplitter_split(
+    input_path: *const c_char,
+    output_dir: *const c_char,
+    max_size_kb: usize,
+) -> c_int {
+    let (Some(input_path), Some(output_dir)) = (path_arg(input_path), path_arg(output_dir)) else {
+        return SQLSPLITTER_EINVAL;
+    };
+    let Ok(runtime) = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    else {
+        return SQLSPLITTER_EIO;
+    };
+
+    let splitter = SqlSplitter::new(output_dir, max_size_kb, CONCURRENT_WRITES);
+    match runtime.block_on(splitter.split_file(input_path)) {
+        Ok(report) => c_int::try_from(report.files).unwrap_or(c_int::MAX),
+        Err(SplitError::PermissionDenied { .. }) => SQLSPLITTER_EPERM,
+        Err(SplitError::StatementTooLarge { .. }) => SQLSPLITTER_ETOOBIG,
+        Err(_) => SQLSPLITTER_EIO,
+    }
+}
+
+unsafe fn path_arg<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sqlsplitter_split() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let statement = format!("SELECT '{}';", "x".repeat(600));
+        std::fs::write(&input_path, statement.repeat(3))?;
+        let output_dir = temp_dir.path().join("out");
+
+        let input = CString::new(input_path.to_str().unwrap())?;
+        let output = CString::new(output_dir.to_str().unwrap())?;
+        let files = unsafe { sqlsplitter_split(input.as_ptr(), output.as_ptr(), 1) };
+        assert_eq!(files, 3);
+        assert!(output_dir.join("split_003.sql").exists());
+
+        let missing = CString::new(temp_dir.path().join("missing.sql").to_str().unwrap())?;
+        let code = unsafe { sqlsplitter_split(missing.as_ptr(), output.as_ptr(), 1) };
+        assert_eq!(code, SQLSPLITTER_EIO);
+        let code = unsafe { sqlsplitter_split(std::ptr::null(), output.as_ptr(), 1) };
+        assert_eq!(code, SQLSPLITTER_EINVAL);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::dialect::Dialect;
+use crate::error::SplitError;
+use crate::scanner::{Event, Scanner};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `content`, read from `file`, with each include directive (psql `\i`,
+/// `\ir`, `\include`, `\include_relative`; MySQL `source`) that opens a
+/// statement replaced by the content of the file it names, expanded the
+/// same way. Names are resolved against the directory of the file holding
+/// the directive. Directives inside strings and comments are left alone.
+///
+/// Line numbers in the result count lines of the expanded script, not of
+/// the file each statement came from. A file that includes itself, however
+/// indirectly, is a [`SplitError::IncludeCycle`].
+pub fn expand_includes(file: &Path, content: &str, dialect: Dialect) -> Result<String, SplitError> {
+    let canonical = fs::canonicalize(file).map_err(|source| SplitError::Include {
+        path: file.to_path_buf(),
+        source,
+    })?;
+    let mut expanded = String::with_capacity(content.len());
+    expand(&mut vec![canonical], content, dialect, &mut expanded)?;
+    Ok(expanded)
+}
+
+/// Appends `content` to `out` with its includes expanded; `stack` holds the
+/// canonical paths of the file `content` came from and those including it.
+fn expand(
+    stack: &mut Vec<PathBuf>,
+    content: &str,
+    dialect: Dialect,
+    out: &mut String,
+) -> Result<(), SplitError> {
+    let dir = stack
+        .last()
+        .and_then(|file| file.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let mut copied = 0;
+    for event in Scanner::new(content)
+        .dialect(dialect)
+        .include_directives(true)
+    {
+        let Event::Include { path, span, .. } = event else {
+            continue;
+        };
+        out.push_str(&content[copied..span.start]);
+        copied = span.end;
+
+        let path = dir.join(path);
+        let canonical = fs::canonicalize(&path).map_err(|source| SplitError::Include {
+            path: path.clone(),
+            source,
+        })?;
+        if stack.contains(&canonical) {
+            let mut cycle = stack.clone();
+            cycle.push(canonical);
+            return Err(SplitError::IncludeCycle(cycle));
+        }
+        let included = fs::read_to_string(&canonical)
+            .map_err(|source| SplitError::Include { path, source })?;
+        stack.push(canonical);
+        expand(stack, &included, dialect, out)?;
+        stack.pop();
+        // Keep a last statement without its newline from running into the next
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    out.push_str(&content[copied..]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_expand_includes() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        fs::create_dir(temp_dir.path().join("schema"))?;
+        fs::write(
+            temp_dir.path().join("schema/tables.sql"),
+            "CREATE TABLE t (id INT);\n\\ir seed.sql",
+        )?;
+        fs::write(
+            temp_dir.path().join("schema/seed.sql"),
+            "INSERT INTO t VALUES (1);",
+        )?;
+        let main = temp_dir.path().join("main.sql");
+        let content = "-- setup\n\\i schema/tables.sql\nSELECT '\\i nope.sql';\n";
+        fs::write(&main, content)?;
+
+        assert_eq!(
+            expand_includes(&main, content, Dialect::Postgres)?,
+            "-- setup\nCREATE TABLE t (id INT);\nINSERT INTO t VALUES (1);\nSELECT '\\i nope.sql';\n"
+        );
+
+        // MySQL's `source`, with its optional semicolon
+        match expand_includes(&main, "source main.sql;\n", Dialect::Mysql) {
+            Err(SplitError::IncludeCycle(cycle)) => assert_eq!(cycle.len(), 2),
+            other => panic!("expected IncludeCycle, got {:?}", other),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+pub mod ascii;
+pub mod batch;
+pub mod category;
+pub mod compression;
+pub mod csv_insert;
+pub mod definer;
+pub mod dialect;
+pub mod encoding;
+pub mod error;
+pub mod escapes;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod include;
+pub mod load_script;
+pub mod long_path;
+pub mod manifest;
+pub mod obfuscate;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod patterns;
+pub mod permissions;
+pub mod progress;
+pub mod references;
+pub mod report;
+pub mod rows;
+pub mod scanner;
+pub mod semicolons;
+pub mod size;
+pub mod splitter;
+pub mod table;
+pub mod validate;
+pub mod values;
+
+pub use ascii::AsciiMode;
+pub use batch::{batch_statements, Batch, BatchLimits};
+pub use dialect::Dialect;
+pub use error::SplitError;
+pub use report::{OutputFileStats, SplitReport, Warning, WarningKind};
+pub use scanner::{Event, LineEndings, LocatedStatement, Scanner};
+pub use splitter::{CommentStyle, Dedupe, SqlSplitter, TrailingDelimiter};
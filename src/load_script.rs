@@ -0,0 +1,65 @@
+use crate::compression::Compression;
+use std::path::Path;
+
+/// Renders a POSIX shell script loading each of `files`, in order, with
+/// `command` (e.g. `mysql mydb` or `psql -d mydb`). Each file is piped into
+/// the command's stdin, through the matching decompressor when the output is
+/// compressed; a command containing `{file}` is run with the quoted path in
+/// its place instead. The script runs under `set -e`, stopping at the first
+/// file that fails to load.
+pub fn load_script<P: AsRef<Path>>(files: &[P], command: &str, compression: Compression) -> String {
+    let mut script = String::from(
+        "#!/bin/sh\n# Generated by sql-split: loads each output file in order.\nset -e\n\n",
+    );
+    for file in files {
+        let quoted = shell_quote(&file.as_ref().to_string_lossy());
+        let line = if command.contains("{file}") {
+            command.replace("{file}", &quoted)
+        } else {
+            match compression.decompress_command() {
+                Some(decompress) => format!("{} {} | {}", decompress, quoted, command),
+                None => format!("{} < {}", command, quoted),
+            }
+        };
+        script.push_str(&line);
+        script.push('\n');
+    }
+    script
+}
+
+/// Single-quotes `s` for the shell, so spaces and metacharacters in paths
+/// are taken literally.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_lists_files_in_order() {
+        let files = [
+            "/out/split_001.sql",
+            "/out/split_002.sql",
+            "/out/it's/split_010.sql",
+        ];
+        let script = load_script(&files, "mysql shop", Compression::None);
+        assert_eq!(
+            script,
+            "#!/bin/sh\n\
+             # Generated by sql-split: loads each output file in order.\n\
+             set -e\n\
+             \n\
+             mysql shop < '/out/split_001.sql'\n\
+             mysql shop < '/out/split_002.sql'\n\
+             mysql shop < '/out/it'\\''s/split_010.sql'\n"
+        );
+
+        let script = load_script(&files[..1], "psql -d shop", Compression::Gzip);
+        assert!(script.ends_with("\ngzip -dc '/out/split_001.sql' | psql -d shop\n"));
+
+        let script = load_script(&files[..1], "psql -d shop -f {file}", Compression::Gzip);
+        assert!(script.ends_with("\npsql -d shop -f '/out/split_001.sql'\n"));
+    }
+}
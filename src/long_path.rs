@@ -0,0 +1,43 @@
+use std::borrow::Cow;
+use std::path::Path;
+#[cfg(windows)]
+use std::path::PathBuf;
+
+/// Longest absolute path, in UTF-16 units, Windows accepts without the
+/// `\\?\` prefix: `CreateDirectoryW`'s limit, which is below `MAX_PATH`
+/// (260, with the terminating NUL) to leave room for an 8.3 file name.
+#[cfg(windows)]
+const LIMIT: usize = 247;
+
+/// `path` in a form the file system accepts at any length. On Windows, a
+/// path whose absolute form is longer than the legacy limit comes back
+/// absolute with the `\\?\` (or, for a share, `\\?\UNC\`) extended-length
+/// prefix; other paths, and every path elsewhere, come back as they are.
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(prefixed) = prefixed(path) {
+        return Cow::Owned(prefixed);
+    }
+    Cow::Borrowed(path)
+}
+
+#[cfg(windows)]
+fn prefixed(path: &Path) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+    // `absolute` resolves `.`, `..` and `/`, which prefixed paths don't
+    let absolute = std::path::absolute(path).ok()?;
+    let wide: Vec<u16> = absolute.as_os_str().encode_wide().collect();
+    let text = String::from_utf16_lossy(&wide);
+    if wide.len() <= LIMIT || text.starts_with(r"\\?\") {
+        return None;
+    }
+    let (prefix, rest) = match text.strip_prefix(r"\\") {
+        Some(_) => (r"\\?\UNC\", &wide[2..]),
+        None => (r"\\?\", &wide[..]),
+    };
+    let mut prefixed: Vec<u16> = prefix.encode_utf16().collect();
+    prefixed.extend_from_slice(rest);
+    Some(OsString::from_wide(&prefixed).into())
+}
@@ -1,8 +1,23 @@
-use clap::Parser;
-use futures::StreamExt;
-use std::path::{Path, PathBuf};
-use tokio::fs::{self, File};
-use tokio::io::{AsyncWriteExt, BufWriter};
+use clap::{CommandFactory, Parser};
+use encoding_rs::Encoding;
+use regex::Regex;
+use sql_splitter::compression::Compression;
+use sql_splitter::encoding::{parse_encoding, OnUnmappable};
+use sql_splitter::load_script::load_script;
+use sql_splitter::manifest::{write_manifest, ManifestFormat};
+use sql_splitter::patterns::TablePatterns;
+#[cfg(unix)]
+use sql_splitter::permissions::parse_mode;
+use sql_splitter::size::parse_size;
+use sql_splitter::splitter::{DEFAULT_PREFIX_READ_BYTES, MAX_INDEX_WIDTH};
+use sql_splitter::{
+    AsciiMode, CommentStyle, Dedupe, Dialect, LineEndings, SplitReport, SqlSplitter,
+    TrailingDelimiter,
+};
+use std::path::PathBuf;
+
+/// Split file size limit when neither --max-size nor --max-size-kb is given.
+const DEFAULT_MAX_SIZE_KB: usize = 1000;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -12,215 +27,827 @@ use tokio::io::{AsyncWriteExt, BufWriter};
 )]
 struct Args {
     /// Input SQL file path
+    #[arg(short, long, required_unless_present_any = ["input_dir", "input_list"])]
+    input: Option<PathBuf>,
+
+    /// File listing input paths, one per line (# comments allowed), split in
+    /// order with continuous file numbering
+    #[arg(long, conflicts_with_all = ["input", "input_dir"])]
+    input_list: Option<PathBuf>,
+
+    /// Split every .sql file in this directory, each into its own output subdirectory
+    #[arg(long, conflicts_with = "input")]
+    input_dir: Option<PathBuf>,
+
+    /// Read --input as CSV whose header row names the columns, splitting
+    /// one INSERT per record into the single --table given
+    #[arg(
+        long,
+        requires_all = ["input", "tables"],
+        conflicts_with_all = ["preview", "analyze", "detect_dialect"]
+    )]
+    from_csv: bool,
+
+    /// Descend into subdirectories of --input-dir
+    #[arg(long, requires = "input_dir")]
+    recursive: bool,
+
+    /// Output directory for split files; give several (`-o a b`, or `-o`
+    /// more than once) to spread the files across them round-robin
+    #[arg(short, long, num_args = 1.., required = true)]
+    output_dir: Vec<PathBuf>,
+
+    /// Maximum size of each split file, e.g. 500KB, 50MB or 2GB (1 KB = 1024
+    /// bytes of UTF-8 output, not characters; a bare number is bytes)
+    #[arg(long, value_parser = parse_size, conflicts_with = "max_size_kb")]
+    max_size: Option<usize>,
+
+    /// Deprecated: use --max-size. Maximum size of each split file in kilobytes
     #[arg(short, long)]
-    input: PathBuf,
-
-    /// Output directory for split files
-    #[arg(short, long)]
-    output_dir: PathBuf,
-
-    /// Maximum size of each split file in kilobytes
-    #[arg(short, long, default_value = "1000")]
-    max_size_kb: usize,
+    max_size_kb: Option<usize>,
 
     /// Number of concurrent write operations
     #[arg(short, long, default_value = "4")]
     concurrent_writes: usize,
-}
 
-#[derive(Debug)]
-struct SqlSplitter {
-    max_size_kb: usize,
-    output_dir: PathBuf,
-    concurrent_writes: usize,
+    /// Number of inputs read and scanned at once with --input-list or
+    /// --input-dir, ahead of the one being written
+    #[arg(long, default_value = "1", value_name = "N")]
+    concurrent_reads: usize,
+
+    /// Maximum number of output files open at once, however many writes are in flight
+    #[arg(long)]
+    max_concurrent_open_files: Option<usize>,
+
+    /// File of table-name patterns whose statements are dropped
+    /// (defaults to `.sqlsplitterignore` in the current directory, if present)
+    #[arg(long)]
+    ignore_file: Option<PathBuf>,
+
+    /// Write a TSV mapping each output statement to its line in the input
+    #[arg(long)]
+    source_map: Option<PathBuf>,
+
+    /// Fail if any single statement is larger than this many bytes
+    #[arg(long)]
+    max_statement_bytes: Option<usize>,
+
+    /// Compress each output file with the given codec
+    #[arg(long, value_enum, default_value = "none")]
+    compress: Compression,
+
+    /// Codec-specific compression level (defaults to the codec's own default)
+    #[arg(long)]
+    compress_level: Option<i32>,
+
+    /// Keep statements byte-for-byte as in the input (no trimming or re-spacing)
+    #[arg(long)]
+    preserve_whitespace: bool,
+
+    /// Extension for output files, before any compression suffix
+    #[arg(long, default_value = "sql")]
+    output_ext: String,
+
+    /// Whether unterminated statements gain a `;` (default: source with --preserve-whitespace, else always)
+    #[arg(long, value_enum)]
+    trailing_delimiter: Option<TrailingDelimiter>,
+
+    /// Spread statements evenly across --num-files files (ignores --max-size-kb)
+    #[arg(long, requires = "num_files")]
+    balance: bool,
+
+    /// Look N statements past each cut to avoid leaving a file underfilled
+    #[arg(long, value_name = "N", conflicts_with = "balance")]
+    buffer_statements: Option<usize>,
+
+    /// Pack whole transactions (BEGIN..COMMIT) into files, never cutting
+    /// one across files; statements outside transactions pack as usual
+    #[arg(long, conflicts_with_all = ["balance", "buffer_statements"])]
+    group_transactions: bool,
+
+    /// Number of output files to produce in --balance mode
+    #[arg(long)]
+    num_files: Option<usize>,
+
+    /// Skip files already completed by an interrupted run into the same output dir
+    #[arg(long)]
+    resume: bool,
+
+    /// When an output file fails to write, keep writing the rest and report
+    /// every failure at the end instead of stopping at the first
+    #[arg(long)]
+    keep_going: bool,
+
+    /// Fail an output file that takes longer than SECS to write, as on a
+    /// hung network filesystem (with --keep-going, the rest are still written)
+    #[arg(
+        long,
+        value_name = "SECS",
+        visible_alias = "max-open-time",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    write_timeout: Option<u64>,
+
+    /// Number new files after the highest existing split_NNN file in the
+    /// output dir instead of overwriting from split_001
+    #[arg(long, conflicts_with = "resume")]
+    append: bool,
+
+    /// Scan the input on all cores, with the same result as a serial scan
+    /// (ignored with --limit, --boundary-regex or --no-semicolon-required)
+    #[cfg(feature = "parallel")]
+    #[arg(long)]
+    parallel_scan: bool,
+
+    /// SQL dialect whose scanning rules to apply
+    #[arg(long, value_enum, default_value = "generic")]
+    dialect: Dialect,
+
+    /// Detect MySQL DELIMITER directives or Postgres dollar quoting from the input
+    #[arg(long)]
+    auto_delimiter: bool,
+
+    /// Write DDL, DML and other statements into separate ddl/, dml/ and other/ subdirectories
+    #[arg(long)]
+    split_by_type: bool,
+
+    /// Write each schema's statements into its own subdirectory (unqualified ones go to default/)
+    #[arg(long)]
+    by_schema: bool,
+
+    /// Report statement counts and sizes for --input without writing any files
+    #[arg(long, requires = "input", conflicts_with = "preview")]
+    analyze: bool,
+
+    /// List the tables --input's statements target, with how many target
+    /// each, most first, without writing any files (with --analyze, after
+    /// its report)
+    #[arg(long, requires = "input", conflicts_with = "preview")]
+    report_tables: bool,
+
+    /// Print just the number of statements in --input and exit without
+    /// writing any files; --expand-includes and --expand-go-count apply as
+    /// in a split
+    #[arg(
+        long,
+        requires = "input",
+        conflicts_with_all = ["analyze", "report_tables", "preview", "detect_dialect", "from_csv"]
+    )]
+    count_only: bool,
+
+    /// Report the most likely dialect of --input and the evidence for it, without splitting
+    #[arg(long, requires = "input", conflicts_with_all = ["analyze", "preview"])]
+    detect_dialect: bool,
+
+    /// Print the first N parsed statements and exit without writing any files
+    #[arg(long, value_name = "N", requires = "input")]
+    preview: Option<usize>,
+
+    /// Plan the split without writing any output files: report (and with
+    /// --manifest, record) the files that would be written, with their
+    /// projected sizes and statement counts
+    #[arg(long, conflicts_with_all = ["pipe_to", "gen_load_script", "source_map"])]
+    dry_run: bool,
+
+    /// Regex matching statement terminators outside strings and comments, replacing `;` (slower)
+    #[arg(long, value_name = "REGEX")]
+    boundary_regex: Option<String>,
+
+    /// Repeat a batch ended by T-SQL `GO N` N times, each copy ended by `GO`.
+    /// Splits on `GO` lines unless --boundary-regex is given, which must then
+    /// match the count too
+    #[arg(
+        long,
+        visible_alias = "split-on-go-count",
+        conflicts_with_all = ["dedupe", "no_semicolon_required"]
+    )]
+    expand_go_count: bool,
+
+    /// Also end a statement at a newline when it looks complete and the next
+    /// line starts a new statement (best-effort, for dumps missing semicolons)
+    #[arg(long, conflicts_with = "boundary_regex")]
+    no_semicolon_required: bool,
+
+    /// Drop repeats of an earlier statement; `hash` remembers hashes only to save memory
+    #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, default_missing_value = "exact")]
+    dedupe: Option<Dedupe>,
+
+    /// Also print a table of the output files with their statement counts and sizes
+    #[arg(long)]
+    summary_table: bool,
+
+    /// Also print how long each output file took to write
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Show a progress bar with an ETA on stderr while writing
+    #[arg(long)]
+    progress: bool,
+
+    /// Print how many statements --dedupe dropped
+    #[arg(long, requires = "dedupe")]
+    dedupe_report: bool,
+
+    /// What to do with non-ASCII characters: fail, warn, or transliterate them to ASCII
+    #[arg(long, value_enum, value_name = "MODE")]
+    ascii: Option<AsciiMode>,
+
+    /// Write output in this encoding instead of UTF-8 (e.g. latin1,
+    /// windows-1250, shift_jis)
+    #[arg(long, value_name = "ENCODING", value_parser = parse_encoding)]
+    encoding_out: Option<&'static Encoding>,
+
+    /// What to do with characters --encoding-out can't represent: replace
+    /// them with `?`, or fail without writing output
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value = "replace",
+        requires = "encoding_out"
+    )]
+    on_unmappable: OnUnmappable,
+
+    /// Replay preceding SET @variable assignments at the top of every output file
+    #[arg(long)]
+    replay_variables: bool,
+
+    /// Convert CRLF line endings to LF outside string literals and COPY data
+    #[arg(long)]
+    normalize_line_endings: bool,
+
+    /// With --normalize-line-endings, also convert line endings inside string data
+    #[arg(long, requires = "normalize_line_endings")]
+    normalize_in_strings: bool,
+
+    /// Prefix output file names with the input file's stem (mydump_split_001.sql)
+    #[arg(long)]
+    prefix_from_input: bool,
+
+    /// Replace characters some filesystems reject in names taken from input
+    /// files (as with --prefix-from-input)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+    sanitize_filenames: bool,
+
+    /// Emit DROP TABLE IF EXISTS before every CREATE TABLE
+    #[arg(long)]
+    add_drop_table: bool,
+
+    /// Write DROP statements as comments (in the --comment-style), keeping
+    /// them in the output for review without letting an accidental run
+    /// destroy data
+    #[arg(long, conflicts_with = "add_drop_table")]
+    comment_out_drops: bool,
+
+    /// Reorder tables so those referenced by FOREIGN KEY/REFERENCES clauses are created first
+    #[arg(long)]
+    order_by_references: bool,
+
+    /// Write files one at a time, in order, for fully reproducible runs (overrides -c)
+    #[arg(long, conflicts_with = "concurrent_writes")]
+    sequential: bool,
+
+    /// Move the comment banner before the first statement into header.sql
+    #[arg(long)]
+    extract_header: bool,
+
+    /// Don't add a `;` after statements that already end with one
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+    trim_trailing_semicolons: bool,
+
+    /// End each output file with a newline after the last statement
+    #[arg(long, default_value = "no", value_name = "yes|no",
+          value_parser = clap::builder::BoolishValueParser::new(), action = clap::ArgAction::Set)]
+    final_newline: bool,
+
+    /// End each output file with a `-- sha256: <hex>` comment checksumming the rest of it
+    #[arg(long)]
+    embed_checksum: bool,
+
+    /// Remove MySQL DEFINER=user@host clauses from CREATE VIEW/PROCEDURE/
+    /// FUNCTION/TRIGGER/EVENT statements so they load on another server
+    #[arg(long)]
+    strip_definer: bool,
+
+    /// Rename tables starting with OLD to start with NEW instead, in every
+    /// statement that names them (string data is left alone)
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    replace_table_prefix: Option<Vec<String>>,
+
+    /// Collapse repeated semicolons (`;;`, `; ;`) left inside statements
+    /// ended by a DELIMITER or --boundary-regex, dropping any left empty
+    #[arg(long)]
+    normalize_semicolons: bool,
+
+    /// Mask the contents of string literals in data statements (INSERT,
+    /// UPDATE, COPY data...) with x's of the same length, for sharing a
+    /// dump's shape without its data
+    #[arg(long)]
+    obfuscate_strings: bool,
+
+    /// Mask the digits of numbers in data statements with 0's
+    #[arg(long)]
+    obfuscate_numbers: bool,
+
+    /// Inline the files named by psql `\i` and MySQL `source` directives
+    /// (resolved relative to the including file) before splitting
+    #[arg(long, visible_alias = "inline-includes", conflicts_with = "from_csv")]
+    expand_includes: bool,
+
+    /// Cut multi-row INSERT statements and COPY blocks larger than the size
+    /// limit into several, each with its own header, spreading their rows
+    /// across files
+    #[arg(long)]
+    split_rows: bool,
+
+    /// Cut multi-row INSERT statements and COPY blocks into pieces of at
+    /// most N rows
+    #[arg(long, value_name = "N")]
+    max_rows_per_insert: Option<usize>,
+
+    /// Flag statements that don't start with a SQL verb or have unbalanced
+    /// parentheses or quotes (a heuristic, not a parser; fatal with --strict)
+    #[arg(long)]
+    validate_sql: bool,
+
+    /// Flag backslashes that look like they desynced the scan, such as
+    /// `'C:\dir\'` where `\'` keeps the string open (a heuristic)
+    #[arg(long)]
+    escape_validation: bool,
+
+    /// How comments the splitter adds are written: dash (`--`), hash (`#`,
+    /// MySQL only) or block (`/* */`)
+    #[arg(long, value_enum, default_value = "dash")]
+    comment_style: CommentStyle,
+
+    /// Also pipe each output file's statements into this shell command (e.g. "psql mydb")
+    #[arg(long, value_name = "COMMAND")]
+    pipe_to: Option<String>,
+
+    /// After splitting, write a shell script here that loads the output files in order
+    #[arg(long, value_name = "PATH")]
+    gen_load_script: Option<PathBuf>,
+
+    /// Command the load script pipes each file into; `{file}` is replaced
+    /// with the file's path instead when present
+    #[arg(
+        long,
+        default_value = "mysql",
+        value_name = "COMMAND",
+        requires = "gen_load_script"
+    )]
+    load_command: String,
+
+    /// After splitting, write a listing of the output files with their
+    /// statement counts and sizes here
+    #[arg(long, visible_alias = "manifest", value_name = "PATH")]
+    output_manifest: Option<PathBuf>,
+
+    /// Format of the --output-manifest listing
+    #[arg(long, value_enum, default_value = "json", requires = "output_manifest")]
+    output_manifest_format: ManifestFormat,
+
+    /// Also rewrite the --output-manifest every N completed files, so an
+    /// interrupted run leaves a listing of the files it finished
+    #[arg(long, value_name = "N", requires = "output_manifest")]
+    flush_interval: Option<usize>,
+
+    /// Once the output files and manifest are written, create this empty
+    /// file in the output directory (`.done` if no name is given)
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = ".done")]
+    done_marker: Option<PathBuf>,
+
+    /// Permissions, in octal, for the directories the split creates
+    #[cfg(unix)]
+    #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+    dir_mode: Option<u32>,
+
+    /// Permissions, in octal, for the files written to the output directory
+    #[cfg(unix)]
+    #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+    file_mode: Option<u32>,
+
+    /// Warn when the last statement has no delimiter, which often means the
+    /// input was truncated (fatal with --strict)
+    #[arg(long)]
+    trailing_statement_warn: bool,
+
+    /// Exit with an error if the run produced any warnings (for CI)
+    #[arg(long)]
+    fail_on_warnings: bool,
+
+    /// Fail without writing output if the input ends inside a string, comment
+    /// or parentheses, if --validate-sql finds a malformed statement, or if
+    /// --trailing-statement-warn finds the last statement unterminated
+    #[arg(long)]
+    strict: bool,
+
+    /// Digits of zero-padding in output file names (split_001.sql has 3;
+    /// by default 3, or enough for the last file when there are more)
+    #[arg(long, value_name = "N",
+          value_parser = clap::value_parser!(u8).range(1..=MAX_INDEX_WIDTH as i64))]
+    index_width: Option<u8>,
+
+    /// Only split the first N statements of the input, stopping reading there
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Scan but discard the first N statements of the input (e.g. those
+    /// already loaded before an interruption) and split the rest
+    #[arg(long, value_name = "N")]
+    skip_statements: Option<usize>,
+
+    /// Only split the last N statements of the input (the whole input is
+    /// still read, keeping N statements in memory)
+    #[arg(long, value_name = "N", conflicts_with_all = ["limit", "skip_statements"])]
+    tail: Option<usize>,
+
+    /// Size in KB of the first read when only part of the input is scanned
+    /// (--limit, --preview); later reads double. Other runs read the whole
+    /// input at once and ignore it
+    #[arg(long, default_value_t = DEFAULT_PREFIX_READ_BYTES / 1024, value_name = "KB")]
+    prefix_read_kb: usize,
+
+    /// Only keep statements for this table (repeatable; globs allowed)
+    #[arg(long = "table", value_name = "NAME")]
+    tables: Vec<String>,
+
+    /// Only keep statements for tables matching a pattern in this file, one
+    /// per line in `.sqlsplitterignore` syntax (adds to any --table)
+    #[arg(long, value_name = "FILE")]
+    only_tables_from: Option<PathBuf>,
+
+    /// Only keep statements whose text matches this regex (e.g.
+    /// '^CREATE (TABLE|INDEX)'), checked after the table filters
+    #[arg(long, value_name = "REGEX")]
+    keep_matching: Option<String>,
+
+    /// Drop statements whose text matches this regex, checked after
+    /// --keep-matching
+    #[arg(long, value_name = "REGEX")]
+    drop_matching: Option<String>,
+
+    /// Match --keep-matching and --drop-matching against statements trimmed
+    /// and with whitespace runs collapsed to one space
+    #[arg(long)]
+    match_normalized: bool,
 }
 
-impl SqlSplitter {
-    pub fn new<P: AsRef<Path>>(
-        output_dir: P,
-        max_size_kb: usize,
-        concurrent_writes: usize,
-    ) -> Self {
-        SqlSplitter {
-            max_size_kb,
-            output_dir: output_dir.as_ref().to_path_buf(),
-            concurrent_writes,
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = Args::parse();
+    // Under --from-csv, --table names the table to insert into
+    let csv_table = match (args.from_csv, args.tables.len()) {
+        (false, _) => None,
+        (true, 1) => args.tables.pop(),
+        (true, _) => Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--from-csv takes exactly one --table to insert into",
+            )
+            .exit(),
+    };
+
+    let ignore_file = args.ignore_file.or_else(|| {
+        let default = PathBuf::from(".sqlsplitterignore");
+        default.is_file().then_some(default)
+    });
+    let max_size = match (args.max_size, args.max_size_kb) {
+        (Some(bytes), _) => bytes,
+        (None, Some(kb)) => {
+            eprintln!(
+                "warning: --max-size-kb is deprecated; use --max-size {}KB",
+                kb
+            );
+            kb * 1024
         }
+        (None, None) => DEFAULT_MAX_SIZE_KB * 1024,
+    };
+    let mut output_dirs = std::mem::take(&mut args.output_dir).into_iter();
+    let output_dir = output_dirs.next().expect("clap requires --output-dir");
+    let mut splitter = SqlSplitter::new(output_dir, DEFAULT_MAX_SIZE_KB, args.concurrent_writes)
+        .with_max_size(max_size)
+        .with_spread_dirs(output_dirs);
+    if let Some(path) = ignore_file {
+        splitter = splitter.with_ignore_patterns(TablePatterns::load(path).await?);
+    }
+    if let Some(path) = args.source_map {
+        splitter = splitter.with_source_map(path);
+    }
+    if let Some(policy) = args.trailing_delimiter {
+        splitter = splitter.with_trailing_delimiter(policy);
+    }
+    if args.normalize_line_endings {
+        splitter = splitter.with_line_endings(if args.normalize_in_strings {
+            LineEndings::UnixInStrings
+        } else {
+            LineEndings::Unix
+        });
+    }
+    if let Some(limit) = args.limit {
+        splitter = splitter.with_limit(limit);
+    }
+    if let Some(count) = args.skip_statements {
+        splitter = splitter.with_skip_statements(count);
+    }
+    if let Some(count) = args.tail {
+        splitter = splitter.with_tail(count);
+    }
+    if let Some(command) = args.pipe_to {
+        splitter = splitter.with_pipe_to(command);
+    }
+    if let Some(mode) = args.dedupe {
+        splitter = splitter.with_dedupe(mode);
+    }
+    if let Some(mode) = args.ascii {
+        splitter = splitter.with_ascii(mode);
+    }
+    if let Some(encoding) = args.encoding_out {
+        splitter = splitter
+            .with_output_encoding(encoding)
+            .with_on_unmappable(args.on_unmappable);
+    }
+    if let Some(pattern) = args.keep_matching {
+        splitter = splitter.with_keep_matching(Regex::new(&pattern)?);
+    }
+    if let Some(pattern) = args.drop_matching {
+        splitter = splitter.with_drop_matching(Regex::new(&pattern)?);
+    }
+    if let Some(boundary) = args.boundary_regex {
+        splitter = splitter.with_boundary_regex(Regex::new(&boundary)?);
+    }
+    if let (Some(path), Some(every)) = (&args.output_manifest, args.flush_interval) {
+        splitter = splitter.with_manifest_flush(path, args.output_manifest_format, every);
+    }
+    if let Some(rows) = args.max_rows_per_insert {
+        splitter = splitter.with_max_rows_per_insert(rows);
+    }
+    if let Some(width) = args.index_width {
+        splitter = splitter.with_index_width(width.into());
+    }
+    if let Some(limit) = args.max_concurrent_open_files {
+        splitter = splitter.with_max_open_files(limit);
+    }
+    if let Some(name) = &args.done_marker {
+        splitter = splitter.with_done_marker(name);
+    }
+    if let Some(limit) = args.max_statement_bytes {
+        splitter = splitter.with_max_statement_bytes(limit);
+    }
+    splitter = splitter
+        .with_output_ext(args.output_ext)
+        .with_compression(args.compress, args.compress_level)
+        .with_preserve_whitespace(args.preserve_whitespace)
+        .with_resume(args.resume)
+        .with_keep_going(args.keep_going)
+        .with_append(args.append)
+        .with_dialect(args.dialect)
+        .with_auto_delimiter(args.auto_delimiter)
+        .with_split_by_type(args.split_by_type)
+        .with_by_schema(args.by_schema)
+        .with_replay_variables(args.replay_variables)
+        .with_add_drop_table(args.add_drop_table)
+        .with_comment_out_drops(args.comment_out_drops)
+        .with_prefix_from_input(args.prefix_from_input)
+        .with_sanitize_filenames(args.sanitize_filenames)
+        .with_reference_order(args.order_by_references)
+        .with_sequential(args.sequential)
+        .with_concurrent_reads(args.concurrent_reads)
+        .with_newline_boundaries(args.no_semicolon_required)
+        .with_extract_header(args.extract_header)
+        .with_trim_trailing_semicolons(args.trim_trailing_semicolons)
+        .with_final_newline(args.final_newline)
+        .with_embed_checksum(args.embed_checksum)
+        .with_comment_style(args.comment_style)
+        .with_strip_definer(args.strip_definer)
+        .with_expand_includes(args.expand_includes)
+        .with_normalize_semicolons(args.normalize_semicolons)
+        .with_obfuscate_strings(args.obfuscate_strings)
+        .with_obfuscate_numbers(args.obfuscate_numbers)
+        .with_match_normalized(args.match_normalized)
+        .with_dry_run(args.dry_run)
+        .with_validate_sql(args.validate_sql)
+        .with_escape_validation(args.escape_validation)
+        .with_expand_go_count(args.expand_go_count)
+        .with_trailing_statement_warn(args.trailing_statement_warn)
+        .with_split_rows(args.split_rows)
+        .with_fail_on_warnings(args.fail_on_warnings)
+        .with_strict(args.strict)
+        .with_prefix_read_size(args.prefix_read_kb * 1024);
+    #[cfg(feature = "parallel")]
+    {
+        splitter = splitter.with_parallel_scan(args.parallel_scan);
+    }
+    if !args.tables.is_empty() || args.only_tables_from.is_some() {
+        let mut tables = args.tables.join("\n");
+        if let Some(path) = &args.only_tables_from {
+            tables.push('\n');
+            tables.push_str(&tokio::fs::read_to_string(path).await?);
+        }
+        splitter = splitter.with_table_filter(TablePatterns::parse(&tables)?);
+    }
+    #[cfg(unix)]
+    {
+        if let Some(mode) = args.dir_mode {
+            splitter = splitter.with_dir_mode(mode);
+        }
+        if let Some(mode) = args.file_mode {
+            splitter = splitter.with_file_mode(mode);
+        }
+    }
+    if let Some([old, new]) = args.replace_table_prefix.as_deref() {
+        splitter = splitter.with_replace_table_prefix(old, new);
+    }
+    if let Some(secs) = args.write_timeout {
+        splitter = splitter.with_write_timeout(std::time::Duration::from_secs(secs));
+    }
+    if args.group_transactions {
+        splitter = splitter.with_group_transactions(true);
+    }
+    if let Some(lookahead) = args.buffer_statements {
+        splitter = splitter.with_lookahead(lookahead);
+    }
+    if let (true, Some(num_files)) = (args.balance, args.num_files) {
+        splitter = splitter.with_balance(num_files);
     }
 
-    fn split_statements(content: &str) -> Vec<String> {
-        let mut statements = Vec::new();
-        let mut current_statement = String::new();
-        let mut in_string = false;
-        let mut escape_next = false;
-
-        for c in content.chars() {
-            match c {
-                '\\' if in_string => {
-                    current_statement.push(c);
-                    escape_next = !escape_next;
-                }
-                '\'' if !escape_next => {
-                    current_statement.push(c);
-                    in_string = !in_string;
-                }
-                ';' if !in_string => {
-                    current_statement = current_statement.trim().to_string();
-                    if !current_statement.is_empty() {
-                        statements.push(current_statement);
+    if let (true, Some(input)) = (args.detect_dialect, &args.input) {
+        match splitter.detect_dialect(input).await {
+            Ok(detection) => {
+                match detection.dialect {
+                    Some(dialect) => println!("Detected dialect: {}", dialect.name()),
+                    None => {
+                        println!("Detected dialect: unknown (no dialect-specific syntax found)")
                     }
-                    current_statement = String::new();
                 }
-                _ => {
-                    if c == '\'' {
-                        escape_next = false;
-                    }
-                    current_statement.push(c);
+                for signal in &detection.signals {
+                    println!(
+                        "  {} ({}): {}",
+                        signal.description,
+                        signal.dialect.name(),
+                        signal.count
+                    );
+                }
+                if let Some(dialect) = detection.dialect {
+                    let flag = match dialect.scanner_dialect() {
+                        Dialect::Mysql => "--dialect mysql",
+                        Dialect::Postgres => "--dialect postgres",
+                        Dialect::Generic => "--dialect generic (the default)",
+                    };
+                    println!("Suggested: {}", flag);
                 }
             }
+            Err(e) => {
+                eprintln!("Error detecting dialect: {}", e);
+                std::process::exit(1);
+            }
         }
-
-        // Add the last statement if it doesn't end with a semicolon
-        let final_statement = current_statement.trim().to_string();
-        if !final_statement.is_empty() {
-            statements.push(final_statement);
-        }
-
-        statements
+        return Ok(());
     }
 
-    async fn write_sql_file(
-        statements: Vec<String>,
-        output_path: PathBuf,
-    ) -> Result<(), std::io::Error> {
-        let file = File::create(output_path).await?;
-        let mut writer = BufWriter::new(file);
-
-        for (i, statement) in statements.iter().enumerate() {
-            if i > 0 {
-                writer.write_all(b"\n\n").await?;
+    if let (true, Some(input)) = (args.count_only, &args.input) {
+        match splitter.count_statements(input).await {
+            Ok(count) => println!("{}", count),
+            Err(e) => {
+                eprintln!("Error counting statements: {}", e);
+                std::process::exit(1);
             }
-            writer.write_all(statement.as_bytes()).await?;
-            writer.write_all(b";").await?;
         }
-        writer.flush().await?;
-        Ok(())
+        return Ok(());
     }
 
-    async fn split_file(&self, input_file: impl AsRef<Path>) -> Result<usize, std::io::Error> {
-        // Create output directory if it doesn't exist
-        fs::create_dir_all(&self.output_dir).await?;
-
-        // Read the entire file content
-        let content = fs::read_to_string(input_file).await?;
-        let statements = Self::split_statements(&content);
-        let max_size = self.max_size_kb * 1024;
-
-        let mut batches = Vec::new();
-        let mut current_batch = Vec::new();
-        let mut current_size = 0;
-
-        for statement in statements {
-            let statement = statement.trim().to_string();
-            let statement_size = statement.as_bytes().len() + 1; // +1 for semicolon
-
-            if current_size + statement_size > max_size && !current_batch.is_empty() {
-                batches.push(current_batch);
-                current_batch = Vec::new();
-                current_size = 0;
+    if let (true, Some(input)) = (args.analyze || args.report_tables, &args.input) {
+        match splitter.analyze(input).await {
+            Ok(report) => {
+                if args.analyze {
+                    println!("Statements: {}", report.statements);
+                    println!("String literals: {}", report.string_literals);
+                    println!("Comments: {}", report.comments);
+                    println!(
+                        "Largest statement: {} bytes starting on line {}",
+                        report.largest_statement_bytes, report.largest_statement_line
+                    );
+                    println!("{}", report.histogram());
+                }
+                if args.report_tables {
+                    println!("{}", report.table_report());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error analyzing file: {}", e);
+                std::process::exit(1);
             }
-
-            current_batch.push(statement);
-            current_size += statement_size;
-        }
-
-        if !current_batch.is_empty() {
-            batches.push(current_batch);
         }
+        return Ok(());
+    }
 
-        // Process batches concurrently with limited parallelism
-        let mut futures = futures::stream::iter(
-            batches
-                .into_iter()
-                .enumerate()
-                .map(|(i, batch)| {
-                    let output_path = self.output_dir.join(format!("split_{:03}.sql", i + 1));
-                    Self::write_sql_file(batch, output_path)
-                })
-                .collect::<Vec<_>>(),
-        )
-        .buffer_unordered(self.concurrent_writes);
-
-        let mut file_count = 0;
-        while let Some(result) = futures.next().await {
-            result?;
-            file_count += 1;
+    if let (Some(limit), Some(input)) = (args.preview, &args.input) {
+        match splitter.preview(input, limit).await {
+            Ok(statements) => {
+                for (i, statement) in statements.iter().enumerate() {
+                    println!("-- statement {} (line {}) --", i + 1, statement.line);
+                    println!("{}", statement.text);
+                }
+                println!("-- end of preview: {} statement(s) --", statements.len());
+            }
+            Err(e) => {
+                eprintln!("Error previewing file: {}", e);
+                std::process::exit(1);
+            }
         }
-
-        Ok(file_count)
+        return Ok(());
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-
-    let splitter = SqlSplitter::new(args.output_dir, args.max_size_kb, args.concurrent_writes);
+    if args.progress {
+        splitter = splitter.with_progress(|progress| {
+            eprint!("\r{}", progress);
+            if progress.files_done == progress.files {
+                eprintln!();
+            }
+        });
+    }
 
     println!("Starting to split SQL file...");
     let start = std::time::Instant::now();
 
-    match splitter.split_file(args.input).await {
-        Ok(num_files) => {
+    let result =
+        match (args.input, args.input_dir, args.input_list) {
+            (Some(input), _, _) => match &csv_table {
+                Some(table) => splitter.split_csv(input, table).await,
+                None => splitter.split_file(input).await,
+            },
+            (None, _, Some(list)) => splitter.split_list(list).await,
+            (None, Some(input_dir), None) => splitter
+                .split_dir(input_dir, args.recursive)
+                .await
+                .map(|reports| {
+                    let mut total = SplitReport::default();
+                    for (_, report) in &reports {
+                        total.merge(report);
+                    }
+                    total
+                }),
+            (None, None, None) => {
+                unreachable!("clap requires --input, --input-dir or --input-list")
+            }
+        };
+
+    match result {
+        Ok(report) => {
             let duration = start.elapsed();
-            println!("Successfully split SQL file into {} files", num_files);
-            println!("Time taken: {:.2?}", duration);
+            if args.summary_table {
+                println!("{}", report.summary_table());
+            }
+            if args.dry_run {
+                println!(
+                    "Dry run: {} file(s) planned, {} bytes projected; nothing written",
+                    report.files,
+                    report
+                        .output_file_stats
+                        .iter()
+                        .map(|stats| stats.bytes)
+                        .sum::<u64>()
+                );
+            } else {
+                println!("{}", report.summary(duration));
+            }
+            if args.verbose {
+                println!("{}", report.write_timings());
+            }
+            for warning in &report.warnings {
+                eprintln!("warning: {}", warning);
+            }
+            if args.dedupe_report {
+                println!(
+                    "Dropped {} duplicate statement(s)",
+                    report.duplicate_statements
+                );
+            }
+            if let Some(path) = &args.output_manifest {
+                write_manifest(path, &report, args.output_manifest_format).await?;
+                println!("Wrote manifest to {}", path.display());
+            }
+            if let Some(path) = &args.gen_load_script {
+                let files = report
+                    .output_files
+                    .iter()
+                    .map(std::fs::canonicalize)
+                    .collect::<Result<Vec<_>, _>>()?;
+                std::fs::write(path, load_script(&files, &args.load_command, args.compress))?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+                }
+                println!("Wrote load script to {}", path.display());
+            }
+            splitter.mark_done().await?;
+        }
+        Err(e) => {
+            eprintln!("Error splitting file: {}", e);
+            std::process::exit(1);
         }
-        Err(e) => eprintln!("Error splitting file: {}", e),
     }
 
     Ok(())
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
-
-    #[tokio::test]
-    async fn test_split_statements() {
-        let input = "SELECT * FROM table1; INSERT INTO table2 VALUES ('test;test'); UPDATE table3 SET col = 1;";
-        let statements = SqlSplitter::split_statements(input);
-        assert_eq!(statements.len(), 3);
-        assert_eq!(
-            statements[1].trim(),
-            "INSERT INTO table2 VALUES ('test;test')"
-        );
-    }
-
-    #[tokio::test]
-    async fn test_split_statements_with_escaped_quotes() {
-        let input = "SELECT 'it\\'s working'; INSERT INTO table2 VALUES ('test');";
-        let statements = SqlSplitter::split_statements(input);
-        assert_eq!(statements.len(), 2);
-        assert_eq!(statements[0].trim(), "SELECT 'it\\'s working'");
-    }
-
-    #[tokio::test]
-    async fn test_file_splitting() -> Result<(), Box<dyn std::error::Error>> {
-        let temp_dir = tempdir()?;
-        let splitter = SqlSplitter::new(temp_dir.path(), 1, 2);
-
-        // Create a test input file
-        let input_path = temp_dir.path().join("input.sql");
-        let mut input_file = File::create(&input_path).await?;
-        input_file
-            .write_all(b"SELECT 1; SELECT 2; SELECT 3;")
-            .await?;
-
-        let num_files = splitter.split_file(input_path).await?;
-        assert!(num_files > 1);
-
-        Ok(())
-    }
-}
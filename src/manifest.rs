@@ -0,0 +1,160 @@
+use crate::report::SplitReport;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// File format of the `--output-manifest` listing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManifestFormat {
+    /// `{"files": [{"path": ..., "statements": ..., "bytes": ...}, ...]}`.
+    #[default]
+    Json,
+    /// A `path,statements,bytes` header row, then one row per file.
+    Csv,
+    /// A `files:` sequence of mappings with the same keys as JSON.
+    Yaml,
+}
+
+/// Renders the output files of `report`, in load order, with their
+/// statement counts and sizes in bytes.
+pub fn manifest(report: &SplitReport, format: ManifestFormat) -> String {
+    let files = report
+        .output_files
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .zip(&report.output_file_stats);
+    let mut out = String::new();
+    match format {
+        ManifestFormat::Json => {
+            out.push_str("{\n  \"files\": [");
+            for (i, (path, stats)) in files.enumerate() {
+                out.push_str(if i == 0 { "\n" } else { ",\n" });
+                out.push_str(&format!(
+                    "    {{\"path\": {}, \"statements\": {}, \"bytes\": {}}}",
+                    quote(&path),
+                    stats.statements,
+                    stats.bytes
+                ));
+            }
+            out.push_str(if report.output_files.is_empty() {
+                "]\n}\n"
+            } else {
+                "\n  ]\n}\n"
+            });
+        }
+        ManifestFormat::Csv => {
+            out.push_str("path,statements,bytes\n");
+            for (path, stats) in files {
+                let path = if path.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", path.replace('"', "\"\""))
+                } else {
+                    path.into_owned()
+                };
+                out.push_str(&format!("{},{},{}\n", path, stats.statements, stats.bytes));
+            }
+        }
+        ManifestFormat::Yaml => {
+            out.push_str("files:");
+            if report.output_files.is_empty() {
+                out.push_str(" []");
+            }
+            out.push('\n');
+            for (path, stats) in files {
+                out.push_str(&format!(
+                    "  - path: {}\n    statements: {}\n    bytes: {}\n",
+                    quote(&path),
+                    stats.statements,
+                    stats.bytes
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Writes the manifest of `report` to `path` through a `.partial` file
+/// renamed into place, so readers see either the previous listing or the
+/// new one in full.
+pub async fn write_manifest(
+    path: &Path,
+    report: &SplitReport,
+    format: ManifestFormat,
+) -> std::io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    fs::write(&partial, manifest(report, format)).await?;
+    fs::rename(&partial, path).await
+}
+
+/// Double-quotes `s` with JSON escapes, which YAML reads the same way.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::OutputFileStats;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_manifest_formats() {
+        let report = SplitReport {
+            output_files: vec![
+                PathBuf::from("out/split_001.sql"),
+                PathBuf::from("out/a \"b\",c.sql"),
+            ],
+            output_file_stats: vec![
+                OutputFileStats {
+                    statements: 10,
+                    bytes: 1000,
+                },
+                OutputFileStats {
+                    statements: 2,
+                    bytes: 234,
+                },
+            ],
+            ..SplitReport::default()
+        };
+        assert_eq!(
+            manifest(&report, ManifestFormat::Json),
+            "{\n  \"files\": [\n    \
+             {\"path\": \"out/split_001.sql\", \"statements\": 10, \"bytes\": 1000},\n    \
+             {\"path\": \"out/a \\\"b\\\",c.sql\", \"statements\": 2, \"bytes\": 234}\n  \
+             ]\n}\n"
+        );
+        assert_eq!(
+            manifest(&report, ManifestFormat::Csv),
+            "path,statements,bytes\n\
+             out/split_001.sql,10,1000\n\
+             \"out/a \"\"b\"\",c.sql\",2,234\n"
+        );
+        assert_eq!(
+            manifest(&report, ManifestFormat::Yaml),
+            "files:\n  \
+             - path: \"out/split_001.sql\"\n    statements: 10\n    bytes: 1000\n  \
+             - path: \"out/a \\\"b\\\",c.sql\"\n    statements: 2\n    bytes: 234\n"
+        );
+
+        let empty = SplitReport::default();
+        assert_eq!(
+            manifest(&empty, ManifestFormat::Json),
+            "{\n  \"files\": []\n}\n"
+        );
+        assert_eq!(manifest(&empty, ManifestFormat::Yaml), "files: []\n");
+    }
+}
@@ -0,0 +1,200 @@
+use crate::dialect::Dialect;
+
+/// Which literals [`obfuscate`] masks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mask {
+    /// Replace every character inside single-quoted strings with `x` (`0`
+    /// in `X'..'` and `B'..'` literals), keeping their length.
+    pub strings: bool,
+    /// Replace the digits of numeric literals with `0`.
+    pub numbers: bool,
+}
+
+impl Mask {
+    /// Whether nothing is masked.
+    pub fn is_empty(self) -> bool {
+        !self.strings && !self.numbers
+    }
+}
+
+/// `text` with the literals `mask` selects masked, leaving keywords,
+/// identifiers (quoted or not), comments and Postgres dollar-quoted bodies
+/// as they are, so the statement keeps its shape and still parses.
+/// MySQL executable comments (`/*!40101 ... */`) are code and masked too.
+pub fn obfuscate(text: &str, dialect: Dialect, mask: Mask) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    while let Some(c) = text[pos..].chars().next() {
+        let rest = &text[pos..];
+        let start = pos;
+        pos += c.len_utf8();
+        match c {
+            '-' if rest.starts_with("--") => {
+                pos = rest.find('\n').map_or(text.len(), |i| start + i + 1);
+            }
+            '#' if dialect == Dialect::Mysql => {
+                pos = rest.find('\n').map_or(text.len(), |i| start + i + 1);
+            }
+            '/' if rest.starts_with("/*") && !rest.starts_with("/*!") => {
+                pos = rest[2..]
+                    .find("*/")
+                    .map_or(text.len(), |i| start + 2 + i + 2);
+            }
+            '"' | '`' => pos = closing_quote(text, pos, c, false).unwrap_or(text.len()),
+            '$' if dialect == Dialect::Postgres && !follows_word(&out) => {
+                if let Some(tag_len) = rest[1..].find('$') {
+                    let tag = &rest[..tag_len + 2];
+                    if tag[1..tag.len() - 1]
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_')
+                    {
+                        pos = rest[tag.len()..]
+                            .find(tag)
+                            .map_or(text.len(), |i| start + 2 * tag.len() + i);
+                    }
+                }
+            }
+            '\'' if mask.strings => {
+                // A letter right before the quote, not ending a word, is a prefix
+                let prefix = out
+                    .chars()
+                    .next_back()
+                    .filter(|p| !follows_word(&out[..out.len() - p.len_utf8()]));
+                let escapes = dialect == Dialect::Mysql || matches!(prefix, Some('E' | 'e'));
+                let fill = match prefix {
+                    Some('X' | 'x' | 'B' | 'b') => '0',
+                    _ => 'x',
+                };
+                let (inner_end, end) = match closing_quote(text, pos, '\'', escapes) {
+                    Some(end) => (end - 1, end),
+                    None => (text.len(), text.len()),
+                };
+                out.push('\'');
+                out.extend(text[pos..inner_end].chars().map(|_| fill));
+                out.push_str(&text[inner_end..end]);
+                pos = end;
+                continue;
+            }
+            '\'' => {
+                pos =
+                    closing_quote(text, pos, '\'', dialect == Dialect::Mysql).unwrap_or(text.len())
+            }
+            c if c.is_ascii_digit() && mask.numbers && !follows_word(&out) => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                    .unwrap_or(rest.len());
+                let number = &rest[..len];
+                // Only digits change: the letters of `0x1F` and `2e5` stay
+                out.extend(
+                    number
+                        .chars()
+                        .map(|c| if c.is_ascii_digit() { '0' } else { c }),
+                );
+                pos = start + len;
+                continue;
+            }
+            _ => {}
+        }
+        out.push_str(&text[start..pos]);
+    }
+    out
+}
+
+/// `data`, a `COPY ... FROM stdin` block (the rest of the `COPY` line, its
+/// rows, then `\.`), with the fields of each row masked: numeric fields
+/// under `mask.numbers`, the rest under `mask.strings`. `\N` stays `NULL`.
+pub fn obfuscate_copy_data(data: &str, mask: Mask) -> String {
+    let mut lines = data.split_inclusive('\n');
+    let mut out: String = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        let row = line.trim_end_matches(['\r', '\n']);
+        if row == "\\." {
+            out.push_str(line);
+            continue;
+        }
+        let fields: Vec<String> = row
+            .split('\t')
+            .map(|field| {
+                let numeric = field.parse::<f64>().is_ok();
+                match field {
+                    "\\N" => field.to_string(),
+                    _ if numeric && mask.numbers => field
+                        .chars()
+                        .map(|c| if c.is_ascii_digit() { '0' } else { c })
+                        .collect(),
+                    _ if !numeric && mask.strings => field.chars().map(|_| 'x').collect(),
+                    _ => field.to_string(),
+                }
+            })
+            .collect();
+        out.push_str(&fields.join("\t"));
+        out.push_str(&line[row.len()..]);
+    }
+    out
+}
+
+/// Whether the text so far ends in a word character, so what follows
+/// continues an identifier (`t1`, `$1`) rather than starting a literal.
+fn follows_word(out: &str) -> bool {
+    out.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// The byte offset just past the `quote` closing a quoted run whose
+/// content starts at `from` (a doubled quote continues it, as does a
+/// backslash-escaped one with `escapes`), or `None` if it runs to the end.
+fn closing_quote(text: &str, from: usize, quote: char, escapes: bool) -> Option<usize> {
+    let mut chars = text[from..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && escapes {
+            chars.next();
+        } else if c == quote {
+            if text[from + i + 1..].starts_with(quote) {
+                chars.next();
+            } else {
+                return Some(from + i + 1);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_obfuscate() {
+        let strings = Mask {
+            strings: true,
+            numbers: false,
+        };
+        let both = Mask {
+            strings: true,
+            numbers: true,
+        };
+        assert_eq!(
+            obfuscate(
+                "INSERT INTO `t1` (\"a\", b) VALUES (12, 'O''Neil', 'a\\'b', X'1F', -3.5e2) -- 'c'",
+                Dialect::Mysql,
+                both
+            ),
+            "INSERT INTO `t1` (\"a\", b) VALUES (00, 'xxxxxxx', 'xxxx', X'00', -0.0e0) -- 'c'"
+        );
+        assert_eq!(
+            obfuscate(
+                "UPDATE t2 SET v = E'a\\'b', w = $1 WHERE id = 7",
+                Dialect::Postgres,
+                strings
+            ),
+            "UPDATE t2 SET v = E'xxxx', w = $1 WHERE id = 7"
+        );
+        assert_eq!(
+            obfuscate("SELECT $$it's$$, 'it''s'", Dialect::Postgres, strings),
+            "SELECT $$it's$$, 'xxxxx'"
+        );
+        assert_eq!(
+            obfuscate_copy_data("\n1\tAda\t\\N\n2\tBob\t3.5\n\\.", both),
+            "\n0\txxx\t\\N\n0\txxx\t0.0\n\\."
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Multi-core scanning (`--features parallel`).
+//!
+//! The input is cut into large segments just after a `;` ending a line,
+//! each segment is scanned on its own in parallel, and the results are
+//! stitched back together in order. A cut found by the quick search can land
+//! inside a string, comment, `COPY` block or `DELIMITER` section; the scan of
+//! the segment before it then doesn't [end cleanly](Scanner::ended_cleanly),
+//! and the two segments are merged and rescanned. The statements and stats
+//! are therefore the same as a serial scan's.
+
+use crate::scanner::{LocatedStatement, ScanStats, Scanner, Unclosed};
+use rayon::prelude::*;
+
+/// Smallest segment worth scanning on its own thread.
+pub const MIN_SEGMENT_BYTES: usize = 1024 * 1024;
+
+/// One scanned segment: `content[start..end]`, its statements and stats
+/// with lines relative to the segment, and how many lines it spans.
+struct Segment {
+    start: usize,
+    end: usize,
+    statements: Vec<LocatedStatement>,
+    stats: ScanStats,
+    clean: bool,
+    lines: usize,
+}
+
+/// Scans `content` in parallel segments of roughly `segment_bytes`, each
+/// with a scanner from `scanner`, returning the same statements and stats
+/// as scanning it whole with one such scanner.
+pub fn scan<F>(
+    content: &str,
+    segment_bytes: usize,
+    scanner: F,
+) -> (Vec<LocatedStatement>, ScanStats)
+where
+    F: Fn(&str) -> Scanner + Sync,
+{
+    let bounds = cut_points(content, segment_bytes.max(1));
+    let segments: Vec<Segment> = bounds
+        .par_windows(2)
+        .map(|w| scan_segment(content, w[0], w[1], &scanner))
+        .collect();
+
+    // Stitch: a segment that ended mid-construct swallows the next one
+    let mut stitched = Vec::with_capacity(segments.len());
+    let mut segments = segments.into_iter();
+    let mut current = segments.next().expect("at least one segment");
+    for next in segments {
+        if current.clean {
+            stitched.push(std::mem::replace(&mut current, next));
+        } else {
+            current = scan_segment(content, current.start, next.end, &scanner);
+        }
+    }
+    stitched.push(current);
+
+    let mut statements = Vec::new();
+    let mut stats = ScanStats::default();
+    let mut line_offset = 0;
+    for segment in stitched {
+        statements.extend(segment.statements.into_iter().map(|mut statement| {
+            statement.line += line_offset;
+            statement
+        }));
+        stats.empty_segments += segment.stats.empty_segments;
+        stats.comment_only_segments += segment.stats.comment_only_segments;
+        stats.unterminated_statements += segment.stats.unterminated_statements;
+        stats.string_literals += segment.stats.string_literals;
+        stats.comments += segment.stats.comments;
+        stats.unclosed.extend(
+            segment
+                .stats
+                .unclosed
+                .into_iter()
+                .map(|unclosed| offset_unclosed(unclosed, line_offset)),
+        );
+        line_offset += segment.lines;
+    }
+    (statements, stats)
+}
+
+fn scan_segment<F>(content: &str, start: usize, end: usize, scanner: &F) -> Segment
+where
+    F: Fn(&str) -> Scanner,
+{
+    let text = &content[start..end];
+    let mut scanner = scanner(text);
+    let statements = scanner.statements().collect();
+    Segment {
+        start,
+        end,
+        statements,
+        stats: scanner.stats(),
+        clean: scanner.ended_cleanly(),
+        lines: text.matches('\n').count(),
+    }
+}
+
+/// Byte offsets bounding the segments, from 0 to `content.len()`. Each
+/// inner cut falls just after the first `;\n` at least `segment_bytes`
+/// past the previous cut, so it is on a character boundary.
+fn cut_points(content: &str, segment_bytes: usize) -> Vec<usize> {
+    let mut bounds = vec![0];
+    let mut from = segment_bytes;
+    while from < content.len() {
+        let Some(found) = content.as_bytes()[from..]
+            .windows(2)
+            .position(|pair| pair == b";\n")
+        else {
+            break;
+        };
+        // Cut after the `;`: the newline starts the next segment, which is
+        // where a serial scan would keep it (in a preserved statement's text)
+        let cut = from + found + 1;
+        bounds.push(cut);
+        from = cut + segment_bytes;
+    }
+    bounds.push(content.len());
+    bounds
+}
+
+fn offset_unclosed(unclosed: Unclosed, offset: usize) -> Unclosed {
+    match unclosed {
+        Unclosed::String { line } => Unclosed::String {
+            line: line + offset,
+        },
+        Unclosed::Identifier { line } => Unclosed::Identifier {
+            line: line + offset,
+        },
+        Unclosed::Comment { line } => Unclosed::Comment {
+            line: line + offset,
+        },
+        Unclosed::Parentheses { depth, line } => Unclosed::Parentheses {
+            depth,
+            line: line + offset,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::Dialect;
+
+    /// A dump mixing every construct a cut can land inside.
+    fn generated_input() -> String {
+        let mut input = String::new();
+        for i in 0..5_000 {
+            match i % 8 {
+                0 => input.push_str(&format!("INSERT INTO t VALUES ({}, 'a;\nb');\n", i)),
+                1 => input.push_str(&format!("-- note {};\nSELECT {};\n", i, i)),
+                2 => input.push_str(&format!("/* block;\n{} */ UPDATE t SET v = {};\n", i, i)),
+                3 => input.push_str(&format!(
+                    "COPY t (id, v) FROM stdin;\n{}\tx;\n{}\ty;\n\\.\n",
+                    i,
+                    i + 1
+                )),
+                4 => input.push_str(&format!(
+                    "DELIMITER $$\nCREATE PROCEDURE p{}()\nBEGIN\n  SELECT 1;\nEND$$\nDELIMITER ;\n",
+                    i
+                )),
+                5 => input.push_str(&format!("/*!40101 SET @a = {};\n SET @b = 2 */;\n", i)),
+                6 => input.push_str(";\n\n"),
+                _ => input.push_str(&format!("SELECT (\n  {}\n);\n", i)),
+            }
+        }
+        input.push_str("SELECT 'unclosed;\n");
+        input
+    }
+
+    #[test]
+    fn test_parallel_scan_matches_serial() {
+        let input = generated_input();
+        for preserve_whitespace in [false, true] {
+            for dialect in [Dialect::Generic, Dialect::Mysql] {
+                let make = |text: &str| {
+                    Scanner::new(text)
+                        .preserve_whitespace(preserve_whitespace)
+                        .dialect(dialect)
+                };
+                let mut serial = make(&input);
+                let expected: Vec<LocatedStatement> = serial.statements().collect();
+                let expected_stats = serial.stats();
+                assert!(expected.len() > 2_500);
+
+                // Small segments put plenty of cuts inside strings and blocks
+                for segment_bytes in [1, 997, 64 * 1024] {
+                    let (statements, stats) = scan(&input, segment_bytes, make);
+                    assert_eq!(statements, expected, "{} byte segments", segment_bytes);
+                    assert_eq!(stats, expected_stats, "{} byte segments", segment_bytes);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use regex::Regex;
+use std::io;
+use std::path::Path;
+
+/// A list of table-name patterns, as read from a `.sqlsplitterignore` file.
+///
+/// Each non-empty line is a glob (`*` and `?` wildcards) unless it starts
+/// with `re:`, in which case the remainder is a regular expression. Lines
+/// starting with `#` are comments. Matching is ASCII case-insensitive, and a
+/// qualified name such as `public.audit_log` matches a pattern written
+/// against either the full name or the bare table name.
+#[derive(Debug, Clone, Default)]
+pub struct TablePatterns {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl TablePatterns {
+    pub fn parse(contents: &str) -> Result<Self, regex::Error> {
+        let mut patterns = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let pattern = match line.strip_prefix("re:") {
+                Some(expr) => Pattern::Regex(Regex::new(&format!("(?i){}", expr.trim()))?),
+                None => Pattern::Glob(line.to_ascii_lowercase()),
+            };
+            patterns.push(pattern);
+        }
+        Ok(TablePatterns { patterns })
+    }
+
+    pub async fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = tokio::fs::read_to_string(path).await?;
+        Self::parse(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, table: &str) -> bool {
+        let bare = table.rsplit('.').next().unwrap_or(table);
+        self.patterns.iter().any(|pattern| match pattern {
+            Pattern::Glob(glob) => {
+                let table = table.to_ascii_lowercase();
+                let bare = bare.to_ascii_lowercase();
+                glob_match(glob.as_bytes(), table.as_bytes())
+                    || glob_match(glob.as_bytes(), bare.as_bytes())
+            }
+            Pattern::Regex(regex) => regex.is_match(table) || regex.is_match(bare),
+        })
+    }
+}
+
+/// Matches `text` against a glob supporting `*` (any run) and `?` (any byte).
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_and_regex_patterns() {
+        let patterns = TablePatterns::parse("# audit tables\naudit_*\n\nre:^tmp_\\d+$\n").unwrap();
+        assert!(patterns.matches("audit_log"));
+        assert!(patterns.matches("public.AUDIT_events"));
+        assert!(patterns.matches("tmp_42"));
+        assert!(!patterns.matches("tmp_x"));
+        assert!(!patterns.matches("users"));
+        assert!(!patterns.matches("my_audit_log"));
+    }
+}
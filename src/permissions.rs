@@ -0,0 +1,68 @@
+use crate::long_path;
+use std::io;
+use std::path::Path;
+use tokio::fs;
+
+/// Parses octal permission bits such as `750` or `0o640`.
+pub fn parse_mode(input: &str) -> Result<u32, String> {
+    let digits = input.trim();
+    let digits = digits.strip_prefix("0o").unwrap_or(digits);
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!("invalid octal mode `{}`", input)),
+    }
+}
+
+/// Creates `dir` and any missing parents, as [`fs::create_dir_all`] does,
+/// then on unix gives each directory it created `mode` (octal permission
+/// bits such as `0o750`), regardless of the umask. Directories that
+/// already existed keep theirs.
+pub async fn create_dir_all(dir: &Path, mode: Option<u32>) -> io::Result<()> {
+    let mut created = Vec::new();
+    if mode.is_some() {
+        for ancestor in dir.ancestors() {
+            if ancestor.as_os_str().is_empty() || fs::try_exists(ancestor).await? {
+                break;
+            }
+            created.push(ancestor);
+        }
+    }
+    fs::create_dir_all(long_path::extended(dir)).await?;
+    // Deepest first, as a mode without the search bit locks out the paths below
+    for path in created {
+        set_mode(path, mode).await?;
+    }
+    Ok(())
+}
+
+/// On unix, gives the existing file or directory at `path` `mode` (octal
+/// permission bits); does nothing without a mode, or on other platforms.
+pub async fn set_mode(path: &Path, mode: Option<u32>) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(
+            long_path::extended(path),
+            std::fs::Permissions::from_mode(mode),
+        )
+        .await?;
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("750"), Ok(0o750));
+        assert_eq!(parse_mode("0640"), Ok(0o640));
+        assert_eq!(parse_mode("0o2775"), Ok(0o2775));
+        assert!(parse_mode("rw-r--r--").is_err());
+        assert!(parse_mode("800").is_err());
+        assert!(parse_mode("17777").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Weight of the newest throughput sample in the moving average; lower
+/// values steady the estimate but make it slower to follow a real change.
+const SMOOTHING: f64 = 0.3;
+
+/// Samples closer together than this are merged into the next one, as a
+/// rate over a near-zero interval says nothing.
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How far a split has got, passed to the
+/// [`SqlSplitter::with_progress`](crate::SqlSplitter::with_progress)
+/// callback each time an output file is complete.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Output files written so far, those a resumed run skipped included.
+    pub files_done: usize,
+    /// Output files the input is split into.
+    pub files: usize,
+    /// Bytes of statements in the files written so far.
+    pub bytes_done: u64,
+    /// Bytes of statements across all the files.
+    pub bytes: u64,
+    /// Time since writing started.
+    pub elapsed: Duration,
+    /// Estimated time until the last file is written, once there is a
+    /// throughput to go by.
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    /// A one-line text bar `width` characters wide inside its brackets,
+    /// with the percentage, file count and ETA after it.
+    pub fn bar(&self, width: usize) -> String {
+        let fraction = if self.bytes == 0 {
+            1.0
+        } else {
+            self.bytes_done as f64 / self.bytes as f64
+        };
+        let filled = ((fraction * width as f64) as usize).min(width);
+        let eta = match self.eta {
+            Some(eta) => format_duration(eta),
+            None => "--:--".to_string(),
+        };
+        format!(
+            "[{}{}] {:>3}%  {}/{} files  ETA {}",
+            "#".repeat(filled),
+            "-".repeat(width - filled),
+            (fraction * 100.0) as u32,
+            self.files_done,
+            self.files,
+            eta
+        )
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.bar(30))
+    }
+}
+
+/// Estimates the time left to process `total` bytes by extrapolating the
+/// throughput so far, smoothed with an exponential moving average so one
+/// slow or fast file doesn't swing the estimate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EtaEstimator {
+    total: u64,
+    /// Bytes done as of the latest sample, used or not.
+    done: u64,
+    /// Smoothed bytes per second, once a sample has given one.
+    rate: Option<f64>,
+    /// Time and bytes done at the last sample used.
+    last: (Duration, u64),
+}
+
+impl EtaEstimator {
+    /// An estimator for `total` bytes, of which `done` (say, files a
+    /// resumed run skips) are already processed at the start.
+    pub fn new(total: u64, done: u64) -> Self {
+        EtaEstimator {
+            total,
+            done,
+            rate: None,
+            last: (Duration::ZERO, done),
+        }
+    }
+
+    /// Records that `done` bytes in all are processed `elapsed` after the
+    /// start.
+    pub fn record(&mut self, elapsed: Duration, done: u64) {
+        self.done = done;
+        let (last_elapsed, last_done) = self.last;
+        let interval = elapsed.saturating_sub(last_elapsed);
+        if interval < MIN_SAMPLE_INTERVAL {
+            return;
+        }
+        let sample = done.saturating_sub(last_done) as f64 / interval.as_secs_f64();
+        self.rate = Some(match self.rate {
+            Some(rate) => SMOOTHING * sample + (1.0 - SMOOTHING) * rate,
+            None => sample,
+        });
+        self.last = (elapsed, done);
+    }
+
+    /// The bytes to process in all.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The smoothed throughput in bytes per second, if known.
+    pub fn rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// Time left at the smoothed rate for the bytes not yet done: zero
+    /// once all are done, `None` before there is a rate (or while it is
+    /// zero).
+    pub fn eta(&self) -> Option<Duration> {
+        let remaining = self.total.saturating_sub(self.done);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate.filter(|&rate| rate > 0.0)?;
+        Some(Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+/// `h:mm:ss`, or `m:ss` under an hour.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_smooths_throughput() {
+        let secs = Duration::from_secs;
+        let mut eta = EtaEstimator::new(1000, 0);
+        assert_eq!(eta.eta(), None);
+
+        // 100 B/s to start: 900 bytes left
+        eta.record(secs(1), 100);
+        assert_eq!(eta.rate(), Some(100.0));
+        assert_eq!(eta.eta(), Some(secs(9)));
+
+        // One burst at 400 B/s only moves the average part of the way
+        eta.record(secs(2), 500);
+        assert!((eta.rate().unwrap() - 190.0).abs() < 1e-9);
+        let left = eta.eta().unwrap().as_secs_f64();
+        assert!((left - 500.0 / 190.0).abs() < 1e-6);
+
+        // A stall pulls it down, but not to zero
+        eta.record(secs(3), 500);
+        assert!((eta.rate().unwrap() - 133.0).abs() < 1e-9);
+
+        // Samples too close together wait to be merged into the next
+        eta.record(secs(3) + Duration::from_millis(1), 600);
+        assert!((eta.rate().unwrap() - 133.0).abs() < 1e-9);
+
+        eta.record(secs(4), 1000);
+        assert_eq!(eta.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_eta_counts_from_resumed_bytes() {
+        let mut eta = EtaEstimator::new(1000, 600);
+        eta.record(Duration::from_secs(2), 800);
+        assert_eq!(eta.rate(), Some(100.0));
+        assert_eq!(eta.eta(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        let progress = Progress {
+            files_done: 2,
+            files: 8,
+            bytes_done: 250,
+            bytes: 1000,
+            elapsed: Duration::from_secs(5),
+            eta: Some(Duration::from_secs(75)),
+        };
+        assert_eq!(progress.bar(8), "[##------]  25%  2/8 files  ETA 1:15");
+        let progress = Progress {
+            eta: None,
+            ..progress
+        };
+        assert_eq!(progress.bar(4), "[#---]  25%  2/8 files  ETA --:--");
+        assert_eq!(format_duration(Duration::from_secs(3725)), "1:02:05");
+    }
+}
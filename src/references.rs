@@ -0,0 +1,155 @@
+use crate::scanner::LocatedStatement;
+use crate::table;
+use std::collections::HashMap;
+
+/// Reorders `statements` so each `CREATE TABLE` comes after the creates of
+/// the tables its `REFERENCES` clauses name, for loading with foreign keys
+/// enforced. Best-effort, on [`table::referenced_tables`] heuristics.
+///
+/// Every statement on a created table (its `DROP`, inserts, ...) moves with
+/// that table, along with the table-less statements following it; those
+/// before the first one stay first. Input whose creates are already in
+/// order is returned unchanged. Reference cycles are broken arbitrarily;
+/// the second value counts the tables placed before a table they reference.
+pub fn order_by_references(statements: Vec<LocatedStatement>) -> (Vec<LocatedStatement>, usize) {
+    let mut keys: Vec<String> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut references: Vec<Vec<String>> = Vec::new();
+    for statement in &statements {
+        if table::created_table(&statement.text).is_none() {
+            continue;
+        }
+        let Some(name) = table::table_name(&statement.text) else {
+            continue;
+        };
+        let key = name.to_ascii_lowercase();
+        if !index.contains_key(&key) {
+            index.insert(key.clone(), keys.len());
+            keys.push(key);
+            references.push(table::referenced_tables(&statement.text));
+        }
+    }
+
+    // A referenced name may leave out the schema its table was created in
+    let resolve = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        index.get(&name).copied().or_else(|| {
+            keys.iter()
+                .position(|key| key.rsplit('.').next() == Some(name.as_str()))
+        })
+    };
+    let parents: Vec<Vec<usize>> = references
+        .iter()
+        .enumerate()
+        .map(|(i, names)| {
+            names
+                .iter()
+                .filter_map(|name| resolve(name))
+                .filter(|&parent| parent != i)
+                .collect()
+        })
+        .collect();
+
+    let mut placed = vec![false; keys.len()];
+    let mut order = Vec::with_capacity(keys.len());
+    let mut cycles = 0;
+    while order.len() < keys.len() {
+        let ready = (0..keys.len())
+            .find(|&i| !placed[i] && parents[i].iter().all(|&parent| placed[parent]));
+        let next = ready.unwrap_or_else(|| {
+            // Every table left waits on another; follow unplaced parents
+            // until one repeats, which puts it on a cycle, and place that
+            cycles += 1;
+            let mut seen = vec![false; keys.len()];
+            let mut i = (0..keys.len())
+                .find(|&i| !placed[i])
+                .expect("a table is left to place");
+            while !seen[i] {
+                seen[i] = true;
+                i = parents[i]
+                    .iter()
+                    .copied()
+                    .find(|&parent| !placed[parent])
+                    .expect("a waiting table has an unplaced parent");
+            }
+            i
+        });
+        placed[next] = true;
+        order.push(next);
+    }
+    if order.iter().enumerate().all(|(position, &i)| position == i) {
+        return (statements, cycles);
+    }
+
+    // Block 0 holds whatever precedes the first statement on a created table
+    let mut blocks: Vec<Vec<LocatedStatement>> = vec![Vec::new(); keys.len() + 1];
+    let mut current = 0;
+    for statement in statements {
+        let table = table::table_name(&statement.text)
+            .and_then(|name| index.get(&name.to_ascii_lowercase()).copied());
+        if let Some(i) = table {
+            current = i + 1;
+        }
+        blocks[current].push(statement);
+    }
+    let mut ordered = std::mem::take(&mut blocks[0]);
+    for i in order {
+        ordered.append(&mut blocks[i + 1]);
+    }
+    (ordered, cycles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::split_statements_located;
+
+    fn texts(statements: &[LocatedStatement]) -> Vec<&str> {
+        statements.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_referenced_table_moves_first() {
+        let input = "SET NAMES utf8;\n\
+                     DROP TABLE IF EXISTS orders;\n\
+                     CREATE TABLE orders (id INT, customer_id INT REFERENCES customers (id));\n\
+                     INSERT INTO orders VALUES (1, 1);\n\
+                     UNLOCK TABLES;\n\
+                     DROP TABLE IF EXISTS customers;\n\
+                     CREATE TABLE customers (id INT PRIMARY KEY);\n\
+                     INSERT INTO customers VALUES (1);\n";
+        let (ordered, cycles) = order_by_references(split_statements_located(input, false));
+        assert_eq!(
+            texts(&ordered),
+            vec![
+                "SET NAMES utf8",
+                "DROP TABLE IF EXISTS customers",
+                "CREATE TABLE customers (id INT PRIMARY KEY)",
+                "INSERT INTO customers VALUES (1)",
+                "DROP TABLE IF EXISTS orders",
+                "CREATE TABLE orders (id INT, customer_id INT REFERENCES customers (id))",
+                "INSERT INTO orders VALUES (1, 1)",
+                "UNLOCK TABLES",
+            ]
+        );
+        assert_eq!(cycles, 0);
+
+        // Already in order: nothing moves
+        let (unchanged, _) = order_by_references(ordered.clone());
+        assert_eq!(unchanged, ordered);
+    }
+
+    #[test]
+    fn test_cycle_is_broken() {
+        let input = "CREATE TABLE c (id INT, a_id INT REFERENCES a (id));\n\
+                     CREATE TABLE a (id INT, b_id INT REFERENCES b (id));\n\
+                     CREATE TABLE b (id INT, a_id INT REFERENCES a (id));\n";
+        let (ordered, cycles) = order_by_references(split_statements_located(input, false));
+        let names: Vec<_> = ordered
+            .iter()
+            .filter_map(|s| table::table_name(&s.text))
+            .collect();
+        assert_eq!(names, vec!["a", "c", "b"]);
+        assert_eq!(cycles, 1);
+    }
+}
@@ -0,0 +1,525 @@
+use crate::escapes::SuspiciousEscape;
+use crate::scanner::{LocatedStatement, ScanStats, Unclosed};
+use crate::validate::Malformed;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Upper bounds (exclusive) of the statement size histogram buckets; the
+/// last bucket holds everything larger.
+pub const SIZE_BUCKETS: [(usize, &str); 3] = [
+    (1024, "<1KB"),
+    (10 * 1024, "1-10KB"),
+    (100 * 1024, "10-100KB"),
+];
+
+/// Statement count and size of one output file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputFileStats {
+    /// Statements written to the file, not counting replayed variables.
+    pub statements: usize,
+    /// Size of the file on disk, after any compression.
+    pub bytes: u64,
+}
+
+/// What a [`Warning`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// A statement larger than the file size limit, written to a file of
+    /// its own that exceeds the limit.
+    OversizedStatement,
+    /// Delimiters with nothing before them, skipped.
+    EmptyStatements,
+    /// Segments holding only comments, dropped.
+    CommentOnlySegments,
+    /// Statements that ran to the end of input without a delimiter.
+    UnterminatedStatements,
+    /// A last statement `--trailing-statement-warn` found without a
+    /// delimiter, as if the input were cut off.
+    UnterminatedLastStatement,
+    /// A construct left open at the end of the input.
+    Unclosed,
+    /// A statement `--ascii warn` found non-ASCII characters in.
+    NonAscii,
+    /// Tables `--order-by-references` ordered against a reference to
+    /// break a foreign key cycle.
+    ReferenceCycle,
+    /// A statement `--validate-sql` found suspicious.
+    Malformed,
+    /// A backslash `--escape-validation` found suspicious.
+    SuspiciousEscape,
+    /// A `DEFINER` clause `--strip-definer` removed.
+    StrippedDefiner,
+}
+
+/// Something a run tolerated or changed that its user may want to know
+/// about, for embedders to inspect instead of parsing the CLI's output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// A description for people, complete on its own.
+    pub message: String,
+    /// Input line the warning concerns, for those about one statement or
+    /// construct.
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Summary of a completed split.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SplitReport {
+    /// Number of output files in the split set.
+    pub files: usize,
+    /// Number of statements written across all files.
+    pub statements: usize,
+    /// Delimiters with nothing before them (e.g. `;;`).
+    pub empty_segments: usize,
+    /// Segments holding only comments, which were dropped.
+    pub comment_only_segments: usize,
+    /// Statements that ran to the end of input without a delimiter.
+    pub unterminated_statements: usize,
+    /// Constructs left open at the end of the input, e.g. by truncation.
+    pub unclosed: Vec<Unclosed>,
+    /// Statements `--validate-sql` found suspicious.
+    pub malformed: Vec<Malformed>,
+    /// Backslashes `--escape-validation` found suspicious, at most one per
+    /// statement.
+    pub suspicious_escapes: Vec<SuspiciousEscape>,
+    /// String literals in the input, dollar-quoted ones included.
+    pub string_literals: usize,
+    /// `--` and `/* */` comments in the input (not executable `/*! */` ones).
+    pub comments: usize,
+    /// Statements larger than the file size limit, each written to a file
+    /// of its own that exceeds the limit.
+    pub oversized_statements: usize,
+    /// Statements dropped by `--dedupe` as repeats of an earlier one.
+    pub duplicate_statements: usize,
+    /// Statements `--ascii warn` found non-ASCII characters in.
+    pub non_ascii_statements: usize,
+    /// Tables `--order-by-references` placed before a table they reference
+    /// to break a foreign key cycle.
+    pub reference_cycles: usize,
+    /// Statements per table they target, by the name
+    /// [`table_name`](crate::table::table_name) finds; filled in by
+    /// [`SqlSplitter::analyze`](crate::SqlSplitter::analyze).
+    pub table_statements: BTreeMap<String, usize>,
+    /// Bytes of input read.
+    pub input_bytes: usize,
+    /// Output size of the largest statement written, terminator included.
+    pub largest_statement_bytes: usize,
+    /// Input line the largest statement starts on (0 if none was written).
+    pub largest_statement_line: usize,
+    /// Statement counts per [`SIZE_BUCKETS`] bucket, then one for `>100KB`.
+    pub size_histogram: [usize; SIZE_BUCKETS.len() + 1],
+    /// Most output files open at once while writing.
+    pub peak_open_files: usize,
+    /// Paths of the output files, in the order they should be loaded.
+    pub output_files: Vec<PathBuf>,
+    /// Stats for each of [`output_files`](Self::output_files), in the same order.
+    pub output_file_stats: Vec<OutputFileStats>,
+    /// How long writing each of [`output_files`](Self::output_files) took,
+    /// in the same order: from getting an open-file slot until the file was
+    /// complete under its final name (zero for files a resumed run skipped,
+    /// and in dry runs).
+    pub write_times: Vec<Duration>,
+    /// Each warning of the run, by kind in the order they were found.
+    pub warnings: Vec<Warning>,
+}
+
+impl SplitReport {
+    /// Adds the counts of `other`, e.g. to total a multi-input run.
+    pub fn merge(&mut self, other: &SplitReport) {
+        self.files += other.files;
+        self.statements += other.statements;
+        self.empty_segments += other.empty_segments;
+        self.comment_only_segments += other.comment_only_segments;
+        self.unterminated_statements += other.unterminated_statements;
+        self.unclosed.extend_from_slice(&other.unclosed);
+        self.malformed.extend_from_slice(&other.malformed);
+        self.suspicious_escapes
+            .extend_from_slice(&other.suspicious_escapes);
+        self.string_literals += other.string_literals;
+        self.comments += other.comments;
+        self.oversized_statements += other.oversized_statements;
+        self.duplicate_statements += other.duplicate_statements;
+        self.non_ascii_statements += other.non_ascii_statements;
+        self.reference_cycles += other.reference_cycles;
+        for (table, count) in &other.table_statements {
+            *self.table_statements.entry(table.clone()).or_default() += count;
+        }
+        self.input_bytes += other.input_bytes;
+        self.peak_open_files = self.peak_open_files.max(other.peak_open_files);
+        self.output_files.extend_from_slice(&other.output_files);
+        self.output_file_stats
+            .extend_from_slice(&other.output_file_stats);
+        self.write_times.extend_from_slice(&other.write_times);
+        self.warnings.extend_from_slice(&other.warnings);
+        for (count, other) in self.size_histogram.iter_mut().zip(other.size_histogram) {
+            *count += other;
+        }
+        if other.largest_statement_bytes > self.largest_statement_bytes {
+            self.largest_statement_bytes = other.largest_statement_bytes;
+            self.largest_statement_line = other.largest_statement_line;
+        }
+    }
+
+    /// Describes each kind of problem the run tolerated, one line per kind
+    /// rather than per [`Warning`], for `--fail-on-warnings`. Empty when the
+    /// input was clean; changes the run was asked to make (such as
+    /// stripped definers) don't count.
+    pub fn warning_summary(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.oversized_statements > 0 {
+            warnings.push(format!(
+                "{} statement(s) exceeded the file size limit",
+                self.oversized_statements
+            ));
+        }
+        if self.empty_segments > 0 {
+            warnings.push(format!(
+                "{} empty statement(s) skipped",
+                self.empty_segments
+            ));
+        }
+        if self.comment_only_segments > 0 {
+            warnings.push(format!(
+                "{} comment-only segment(s) dropped",
+                self.comment_only_segments
+            ));
+        }
+        if self.unterminated_statements > 0 {
+            warnings.push(format!(
+                "{} statement(s) lacked a trailing delimiter",
+                self.unterminated_statements
+            ));
+        }
+        if self.non_ascii_statements > 0 {
+            warnings.push(format!(
+                "{} statement(s) contained non-ASCII characters",
+                self.non_ascii_statements
+            ));
+        }
+        if self.reference_cycles > 0 {
+            warnings.push(format!(
+                "{} table(s) in a foreign key cycle were created before a table they reference",
+                self.reference_cycles
+            ));
+        }
+        for unclosed in &self.unclosed {
+            warnings.push(format!("input ended inside a {}", unclosed));
+        }
+        for malformed in &self.malformed {
+            warnings.push(format!("{} (--validate-sql)", malformed));
+        }
+        for escape in &self.suspicious_escapes {
+            warnings.push(format!("{} (--escape-validation)", escape));
+        }
+        warnings
+    }
+
+    /// Adds a [`Warning`].
+    pub(crate) fn warn(&mut self, kind: WarningKind, line: Option<usize>, message: String) {
+        self.warnings.push(Warning {
+            kind,
+            message,
+            line,
+        });
+    }
+
+    /// Records `statement` as written, keeping the largest seen.
+    pub(crate) fn record_statement(&mut self, statement: &LocatedStatement) {
+        let size = statement.output_len();
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|&(limit, _)| size < limit)
+            .unwrap_or(SIZE_BUCKETS.len());
+        self.size_histogram[bucket] += 1;
+        if size > self.largest_statement_bytes {
+            self.largest_statement_bytes = size;
+            self.largest_statement_line = statement.line;
+        }
+    }
+
+    /// Input throughput in MB/s (1 MB = 1024 KB) over `elapsed`.
+    pub fn throughput(&self, elapsed: Duration) -> f64 {
+        let secs = elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.input_bytes as f64 / (1024.0 * 1024.0) / secs
+    }
+
+    /// Renders [`size_histogram`](Self::size_histogram) as labelled text
+    /// bars, the longest 40 characters wide.
+    pub fn histogram(&self) -> String {
+        const WIDTH: usize = 40;
+        let labels = SIZE_BUCKETS
+            .iter()
+            .map(|&(_, label)| label)
+            .chain(std::iter::once(">100KB"));
+        let max = self
+            .size_histogram
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut out = String::from("Statement sizes:");
+        for (label, &count) in labels.zip(&self.size_histogram) {
+            let bar = "#".repeat((count * WIDTH).div_ceil(max));
+            let line = format!("\n  {:<9} {:>8} {}", label, count, bar);
+            out.push_str(line.trim_end());
+        }
+        out
+    }
+
+    /// The end-of-run summary printed by the CLI.
+    pub fn summary(&self, elapsed: Duration) -> String {
+        format!(
+            "Successfully split SQL file into {} files\n\
+             Skipped {} empty and {} comment-only segments; {} statement(s) lacked a trailing delimiter\n\
+             Largest statement: {} bytes starting on line {}\n\
+             Time taken: {:.2?}\n\
+             Throughput: {:.2} MB/s",
+            self.files,
+            self.empty_segments,
+            self.comment_only_segments,
+            self.unterminated_statements,
+            self.largest_statement_bytes,
+            self.largest_statement_line,
+            elapsed,
+            self.throughput(elapsed)
+        )
+    }
+
+    /// Renders a table with a row per output file (its statement count and
+    /// size in bytes) and a closing total, columns aligned for a terminal.
+    pub fn summary_table(&self) -> String {
+        let rows: Vec<(String, String, String)> = self
+            .output_files
+            .iter()
+            .zip(&self.output_file_stats)
+            .map(|(path, stats)| {
+                (
+                    path.display().to_string(),
+                    stats.statements.to_string(),
+                    stats.bytes.to_string(),
+                )
+            })
+            .collect();
+        let total = (
+            "Total".to_string(),
+            self.statements.to_string(),
+            self.output_file_stats
+                .iter()
+                .map(|stats| stats.bytes)
+                .sum::<u64>()
+                .to_string(),
+        );
+        let header = ("File", "Statements", "Bytes");
+        let width = |column: fn(&(String, String, String)) -> &String, title: &str| {
+            rows.iter()
+                .chain(std::iter::once(&total))
+                .map(|row| column(row).len())
+                .fold(title.len(), usize::max)
+        };
+        let widths = (
+            width(|row| &row.0, header.0),
+            width(|row| &row.1, header.1),
+            width(|row| &row.2, header.2),
+        );
+        let rule = format!(
+            "{}  {}  {}",
+            "-".repeat(widths.0),
+            "-".repeat(widths.1),
+            "-".repeat(widths.2)
+        );
+        let line = |file: &str, statements: &str, bytes: &str| {
+            format!(
+                "{:<w0$}  {:>w1$}  {:>w2$}",
+                file,
+                statements,
+                bytes,
+                w0 = widths.0,
+                w1 = widths.1,
+                w2 = widths.2
+            )
+        };
+        let mut out = vec![line(header.0, header.1, header.2), rule.clone()];
+        out.extend(rows.iter().map(|row| line(&row.0, &row.1, &row.2)));
+        out.push(rule);
+        out.push(line(&total.0, &total.1, &total.2));
+        out.join("\n")
+    }
+
+    /// [`table_statements`](Self::table_statements) from the most targeted
+    /// table down, ties by name.
+    pub fn tables_by_count(&self) -> Vec<(&str, usize)> {
+        let mut tables: Vec<(&str, usize)> = self
+            .table_statements
+            .iter()
+            .map(|(table, &count)| (table.as_str(), count))
+            .collect();
+        tables.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        tables
+    }
+
+    /// Renders [`tables_by_count`](Self::tables_by_count) as aligned
+    /// `table  count` rows under a heading, for `--report-tables`.
+    pub fn table_report(&self) -> String {
+        let tables = self.tables_by_count();
+        let width = tables
+            .iter()
+            .map(|(table, _)| table.len())
+            .fold("Table".len(), usize::max);
+        let mut out = format!("{:<width$}  Statements", "Table");
+        for (table, count) in tables {
+            out.push_str(&format!("\n{:<width$}  {:>10}", table, count));
+        }
+        out
+    }
+
+    /// Lists how long each output file took to write, for `--verbose`,
+    /// then the slowest and the total.
+    pub fn write_timings(&self) -> String {
+        let mut out = String::from("Write times:");
+        for (path, elapsed) in self.output_files.iter().zip(&self.write_times) {
+            out.push_str(&format!("\n  {}: {:.2?}", path.display(), elapsed));
+        }
+        let slowest = self
+            .output_files
+            .iter()
+            .zip(&self.write_times)
+            .max_by_key(|(_, elapsed)| **elapsed);
+        if let Some((path, elapsed)) = slowest {
+            out.push_str(&format!("\nSlowest: {} ({:.2?})", path.display(), elapsed));
+        }
+        let total: Duration = self.write_times.iter().sum();
+        out.push_str(&format!("\nTotal: {:.2?}", total));
+        out
+    }
+
+    pub(crate) fn record_scan(&mut self, stats: &ScanStats) {
+        self.empty_segments += stats.empty_segments;
+        self.comment_only_segments += stats.comment_only_segments;
+        self.unterminated_statements += stats.unterminated_statements;
+        self.unclosed.extend_from_slice(&stats.unclosed);
+        self.string_literals += stats.string_literals;
+        self.comments += stats.comments;
+
+        let counted = [
+            (
+                WarningKind::EmptyStatements,
+                stats.empty_segments,
+                "empty statement(s) skipped",
+            ),
+            (
+                WarningKind::CommentOnlySegments,
+                stats.comment_only_segments,
+                "comment-only segment(s) dropped",
+            ),
+            (
+                WarningKind::UnterminatedStatements,
+                stats.unterminated_statements,
+                "statement(s) lacked a trailing delimiter",
+            ),
+        ];
+        for (kind, count, what) in counted {
+            if count > 0 {
+                self.warn(kind, None, format!("{} {}", count, what));
+            }
+        }
+        for unclosed in &stats.unclosed {
+            self.warn(
+                WarningKind::Unclosed,
+                Some(unclosed.line()),
+                format!("input ended inside a {}", unclosed),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_reports_throughput() {
+        let report = SplitReport {
+            files: 2,
+            input_bytes: 3 * 1024 * 1024,
+            ..SplitReport::default()
+        };
+        let summary = report.summary(Duration::from_millis(1500));
+        assert!(summary.starts_with("Successfully split SQL file into 2 files\n"));
+        let throughput = summary
+            .lines()
+            .find_map(|line| line.strip_prefix("Throughput: "))
+            .and_then(|rest| rest.strip_suffix(" MB/s"))
+            .and_then(|value| value.parse::<f64>().ok())
+            .expect("throughput line");
+        assert!((throughput - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_summary_table_rows() {
+        let report = SplitReport {
+            files: 2,
+            statements: 12,
+            output_files: vec![
+                PathBuf::from("out/split_001.sql"),
+                PathBuf::from("out/split_002.sql"),
+            ],
+            output_file_stats: vec![
+                OutputFileStats {
+                    statements: 10,
+                    bytes: 1000,
+                },
+                OutputFileStats {
+                    statements: 2,
+                    bytes: 234,
+                },
+            ],
+            ..SplitReport::default()
+        };
+        assert_eq!(
+            report.summary_table(),
+            "File               Statements  Bytes\n\
+             -----------------  ----------  -----\n\
+             out/split_001.sql          10   1000\n\
+             out/split_002.sql           2    234\n\
+             -----------------  ----------  -----\n\
+             Total                      12   1234"
+        );
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut report = SplitReport::default();
+        for size in [10, 1023, 1024, 5000, 10 * 1024, 200 * 1024] {
+            report.record_statement(&LocatedStatement {
+                text: "x".repeat(size - 1),
+                line: 1,
+                data: None,
+                delimiter: None,
+                boundary: None,
+                terminated: true,
+            });
+        }
+        assert_eq!(report.size_histogram, [2, 2, 1, 1]);
+        assert_eq!(
+            report.histogram(),
+            "Statement sizes:\n  \
+             <1KB             2 ########################################\n  \
+             1-10KB           2 ########################################\n  \
+             10-100KB         1 ####################\n  \
+             >100KB           1 ####################"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::scanner::LocatedStatement;
+use crate::values::parse_values;
+
+/// Cuts a multi-row `INSERT ... VALUES` statement or `COPY ... FROM stdin`
+/// block into several of the same kind, each holding at most `max_rows`
+/// rows and, where a single row allows, at most `max_bytes` of output.
+/// Every piece repeats the statement's header (and an `INSERT`'s tail,
+/// such as `ON DUPLICATE KEY UPDATE ...`), so each loads on its own.
+///
+/// Returns `None` for other statements, an unterminated `COPY` block, and
+/// statements already within both limits.
+pub fn chunk_rows(
+    statement: &LocatedStatement,
+    max_rows: Option<usize>,
+    max_bytes: Option<usize>,
+) -> Option<Vec<LocatedStatement>> {
+    let fits = |rows: usize, bytes: usize| {
+        max_rows.is_none_or(|max| rows <= max) && max_bytes.is_none_or(|max| bytes <= max)
+    };
+    let piece = |text: String, data: Option<String>, last: bool| LocatedStatement {
+        text,
+        line: statement.line,
+        data,
+        delimiter: statement.delimiter.clone(),
+        boundary: statement.boundary.clone(),
+        terminated: statement.terminated || !last,
+    };
+    let terminator = statement.output_len()
+        - statement.text.len()
+        - statement.data.as_ref().map_or(0, String::len);
+
+    match &statement.data {
+        None => {
+            let values = parse_values(&statement.text)?;
+            // `<head> <row>,<row>...<tail>`
+            let overhead = values.head.len() + 1 + values.tail.len() + terminator;
+            let chunks = group(&values.rows, overhead, 1, fits);
+            if chunks.len() < 2 {
+                return None;
+            }
+            let count = chunks.len();
+            Some(
+                chunks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, rows)| {
+                        let text = format!("{} {}{}", values.head, rows.join(","), values.tail);
+                        piece(text, None, i + 1 == count)
+                    })
+                    .collect(),
+            )
+        }
+        Some(data) => {
+            // The rest of the COPY line, one line per row, then `\.`
+            let lead_end = data.find('\n')? + 1;
+            let (lead, body) = data.split_at(lead_end);
+            let mut lines: Vec<&str> = body.split_inclusive('\n').collect();
+            let end_marker = lines.pop()?;
+            if end_marker.trim_end_matches(['\r', '\n']) != "\\." {
+                return None;
+            }
+            let overhead = statement.text.len() + terminator + lead.len() + end_marker.len();
+            let chunks = group(&lines, overhead, 0, fits);
+            if chunks.len() < 2 {
+                return None;
+            }
+            let count = chunks.len();
+            Some(
+                chunks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, rows)| {
+                        let data = format!("{}{}{}", lead, rows.concat(), end_marker);
+                        piece(statement.text.clone(), Some(data), i + 1 == count)
+                    })
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Packs `rows` greedily into groups that `fits` (given a group's row count
+/// and output size), at least one row per group. Each group costs
+/// `overhead` bytes plus its rows, with `separator` bytes between rows.
+fn group<'a>(
+    rows: &[&'a str],
+    overhead: usize,
+    separator: usize,
+    fits: impl Fn(usize, usize) -> bool,
+) -> Vec<Vec<&'a str>> {
+    let mut groups = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut size = overhead;
+    for &row in rows {
+        let added = if current.is_empty() {
+            row.len()
+        } else {
+            separator + row.len()
+        };
+        if !current.is_empty() && !fits(current.len() + 1, size + added) {
+            groups.push(std::mem::take(&mut current));
+            size = overhead + row.len();
+        } else {
+            size += added;
+        }
+        current.push(row);
+    }
+    if !current.is_empty() {
+        groups.push(current);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(text: &str, data: Option<&str>) -> LocatedStatement {
+        LocatedStatement {
+            text: text.to_string(),
+            line: 7,
+            data: data.map(str::to_string),
+            delimiter: None,
+            boundary: None,
+            terminated: true,
+        }
+    }
+
+    #[test]
+    fn test_chunks_insert_rows() {
+        let insert = statement(
+            "INSERT INTO t VALUES (1,'a'),\n(2,'b,c'),(3,'d') ON DUPLICATE KEY UPDATE v = 1",
+            None,
+        );
+        let pieces = chunk_rows(&insert, Some(2), None).unwrap();
+        let texts: Vec<&str> = pieces.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "INSERT INTO t VALUES (1,'a'),(2,'b,c') ON DUPLICATE KEY UPDATE v = 1",
+                "INSERT INTO t VALUES (3,'d') ON DUPLICATE KEY UPDATE v = 1",
+            ]
+        );
+        assert!(pieces.iter().all(|p| p.line == 7 && p.terminated));
+
+        // By size: each piece within the limit, terminator included
+        let pieces = chunk_rows(&insert, None, Some(62)).unwrap();
+        assert_eq!(pieces.len(), 3);
+        assert!(pieces.iter().all(|p| p.output_len() <= 62));
+
+        assert_eq!(chunk_rows(&insert, Some(3), Some(1000)), None);
+        assert_eq!(
+            chunk_rows(&statement("UPDATE t SET v = 1", None), Some(1), None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_chunks_copy_rows() {
+        let copy = statement("COPY t (id, v) FROM stdin", Some("\n1\ta\n2\tb\n3\tc\n\\."));
+        let pieces = chunk_rows(&copy, Some(2), None).unwrap();
+        let data: Vec<&str> = pieces.iter().map(|p| p.data.as_deref().unwrap()).collect();
+        assert_eq!(data, vec!["\n1\ta\n2\tb\n\\.", "\n3\tc\n\\."]);
+        assert!(pieces.iter().all(|p| p.text == "COPY t (id, v) FROM stdin"));
+
+        // A row too big for the limit still gets a piece of its own
+        let pieces = chunk_rows(&copy, None, Some(1)).unwrap();
+        assert_eq!(pieces.len(), 3);
+
+        let unterminated = statement("COPY t FROM stdin", Some("\n1\ta\n2\tb"));
+        assert_eq!(chunk_rows(&unterminated, Some(1), None), None);
+    }
+}
@@ -0,0 +1,1511 @@
+use crate::dialect::Dialect;
+use crate::table::Cursor;
+use regex::Regex;
+use regex_automata::{meta, Anchored, Input};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+
+/// A statement together with the input line it starts on (1-based).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedStatement {
+    pub text: String,
+    pub line: usize,
+    /// Inline `COPY ... FROM stdin` data: everything after the statement's
+    /// `;` up to and including the `\.` terminator line, kept verbatim.
+    pub data: Option<String>,
+    /// The custom MySQL `DELIMITER` that ended this statement, if not `;`.
+    pub delimiter: Option<String>,
+    /// The text a custom boundary regex matched to end this statement.
+    pub boundary: Option<String>,
+    /// False when the end of input rather than a delimiter ended it.
+    pub terminated: bool,
+}
+
+impl LocatedStatement {
+    /// Size in bytes of the statement as written, including its terminator
+    /// and data. An unterminated statement is counted as if a `;` were
+    /// appended, so this is an upper bound under every trailing-delimiter
+    /// policy.
+    pub fn output_len(&self) -> usize {
+        let terminator = match (&self.delimiter, &self.boundary) {
+            // DELIMITER <d>\n<text><d>\nDELIMITER ;
+            (Some(d), _) => "DELIMITER \n".len() + 2 * d.len() + "\nDELIMITER ;".len(),
+            (None, Some(boundary)) => boundary.len(),
+            (None, None) => 1,
+        };
+        self.text.len() + terminator + self.data.as_ref().map_or(0, String::len)
+    }
+}
+
+/// Segments the scanner discarded or flagged, for the end-of-run summary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    /// Delimiters with nothing but whitespace before them (e.g. `;;`).
+    pub empty_segments: usize,
+    /// Delimited segments containing only comments; these are dropped.
+    pub comment_only_segments: usize,
+    /// Statements ended by the end of input rather than a delimiter.
+    pub unterminated_statements: usize,
+    /// Constructs the input left open at its end, as in a truncated dump
+    /// or after a stray trailing `'`. Only the last statement can hold any.
+    pub unclosed: Vec<Unclosed>,
+    /// String literals, dollar-quoted ones included, when counted (see
+    /// [`Scanner::count_literals`]).
+    pub string_literals: usize,
+    /// `--` and `/* */` comments, but not executable ones, when counted.
+    pub comments: usize,
+}
+
+/// A construct still open at the end of input, with the line it opened on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unclosed {
+    /// A string literal, or a Postgres dollar-quoted string.
+    String { line: usize },
+    /// A MySQL backtick-quoted identifier.
+    Identifier { line: usize },
+    /// A `/* */` comment, or a MySQL `/*! */` executable comment.
+    Comment { line: usize },
+    /// `depth` parentheses, the outermost opened on `line`.
+    Parentheses { depth: usize, line: usize },
+}
+
+impl Unclosed {
+    /// The line the construct opened on.
+    pub fn line(&self) -> usize {
+        match *self {
+            Unclosed::String { line }
+            | Unclosed::Identifier { line }
+            | Unclosed::Comment { line }
+            | Unclosed::Parentheses { line, .. } => line,
+        }
+    }
+}
+
+impl fmt::Display for Unclosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unclosed::String { line } => write!(f, "string literal opened on line {}", line),
+            Unclosed::Identifier { line } => {
+                write!(f, "quoted identifier opened on line {}", line)
+            }
+            Unclosed::Comment { line } => write!(f, "comment opened on line {}", line),
+            Unclosed::Parentheses { depth, line } => write!(
+                f,
+                "{} parenthesis(es), the outermost opened on line {}",
+                depth, line
+            ),
+        }
+    }
+}
+
+/// How the scanner treats `\r\n` line endings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEndings {
+    /// Leaves line endings as they are.
+    #[default]
+    Keep,
+    /// Rewrites `\r\n` to `\n` outside string literals and `COPY` data,
+    /// leaving multi-line values untouched.
+    Unix,
+    /// Rewrites `\r\n` to `\n` everywhere, data included.
+    UnixInStrings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `-- ...` (or, for MySQL, `# ...`) running to the end of the line.
+    Line,
+    /// `/* ... */`.
+    Block,
+}
+
+/// Tokenization events produced by [`Scanner::next_event`]. Lines are 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    EnteredString {
+        line: usize,
+    },
+    ExitedString {
+        line: usize,
+    },
+    EnteredComment {
+        line: usize,
+        kind: CommentKind,
+    },
+    ExitedComment {
+        line: usize,
+    },
+    /// A statement boundary, carrying the completed (non-empty) statement.
+    Statement(LocatedStatement),
+    /// A psql `\i` or MySQL `source` directive naming the file `path`,
+    /// spanning the input bytes `span` (its newline included); only with
+    /// [`Scanner::include_directives`].
+    Include {
+        line: usize,
+        path: String,
+        span: Range<usize>,
+    },
+}
+
+/// What [`Scanner::count_literals`] tallies.
+#[derive(Debug, Clone, Copy)]
+enum Literal {
+    String,
+    Comment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Normal,
+    InString,
+    /// Inside a Postgres dollar-quoted string; the tag is in `dollar_tag`.
+    InDollarQuote,
+    /// Inside a MySQL backtick-quoted identifier.
+    InBacktick,
+    InComment(CommentKind),
+}
+
+/// The statement-splitting state machine.
+///
+/// `;` ends a statement unless it appears inside a single-quoted string or a
+/// comment. MySQL executable comments (`/*!40101 ... */`, `/*M!...*/`) are
+/// code rather than comments: a `;` inside one ends the statement, and each
+/// piece is re-wrapped in the comment's opener and a closing `*/` so it runs
+/// on its own. `COPY ... FROM stdin` data blocks are attached verbatim to their
+/// statement. Under [`Dialect::Mysql`] a `DELIMITER` directive changes the
+/// terminator and backtick-quoted identifiers are opaque like strings; under [`Dialect::Postgres`] dollar-quoted bodies are strings.
+/// A [`boundary`](Self::boundary) regex replaces `;` altogether, and
+/// [`newline_boundaries`](Self::newline_boundaries) lets newlines end statements too.
+#[derive(Debug)]
+pub struct Scanner {
+    input: String,
+    pos: usize,
+    state: State,
+    escape_next: bool,
+    current: String,
+    line: usize,
+    start_line: Option<usize>,
+    has_code: bool,
+    /// Whether the current segment's text so far ends in a `--` comment.
+    ends_in_line_comment: bool,
+    stats: ScanStats,
+    pending: VecDeque<Event>,
+    done: bool,
+    preserve_whitespace: bool,
+    dialect: Dialect,
+    delimiter: String,
+    dollar_tag: String,
+    /// The opener (e.g. `/*!40101`) of the executable comment being scanned.
+    executable_comment: Option<String>,
+    /// Line the executable comment opened on.
+    executable_line: usize,
+    /// Line the current string, identifier or comment opened on.
+    open_line: usize,
+    /// Parentheses open in the current statement, outside strings and
+    /// comments, and the line the outermost opened on.
+    paren_depth: usize,
+    paren_line: usize,
+    boundary: Option<meta::Regex>,
+    line_endings: LineEndings,
+    /// Whether a newline can end a statement that lacks its `;`.
+    newline_boundaries: bool,
+    count_literals: bool,
+    /// Whether [`take_run`](Self::take_run) may move runs of ordinary
+    /// characters at once; off only in tests, as the reference it must match.
+    runs: bool,
+    /// Whether include directives are reported rather than scanned as code.
+    include_directives: bool,
+    /// Whether a `COPY` data block ran to the end of input without its `\.`.
+    copy_open: bool,
+    /// Whether the input ended where a fresh scanner would start: outside
+    /// any statement, string, comment or data, with `;` the delimiter.
+    clean_end: bool,
+    /// Whether statements are only counted, into `counted`, rather than
+    /// emitted with their text.
+    count_only: bool,
+    counted: usize,
+}
+
+impl Scanner {
+    pub fn new(input: impl Into<String>) -> Self {
+        Scanner {
+            input: input.into(),
+            pos: 0,
+            state: State::Normal,
+            escape_next: false,
+            current: String::new(),
+            line: 1,
+            start_line: None,
+            has_code: false,
+            ends_in_line_comment: false,
+            stats: ScanStats::default(),
+            pending: VecDeque::new(),
+            done: false,
+            preserve_whitespace: false,
+            dialect: Dialect::Generic,
+            delimiter: ";".to_string(),
+            dollar_tag: String::new(),
+            executable_comment: None,
+            executable_line: 0,
+            open_line: 0,
+            paren_depth: 0,
+            paren_line: 0,
+            boundary: None,
+            line_endings: LineEndings::Keep,
+            newline_boundaries: false,
+            count_literals: false,
+            runs: true,
+            include_directives: false,
+            copy_open: false,
+            clean_end: false,
+            count_only: false,
+            counted: 0,
+        }
+    }
+
+    /// Normalizes `\r\n` line endings in statement text as it is scanned.
+    pub fn line_endings(mut self, line_endings: LineEndings) -> Self {
+        self.line_endings = line_endings;
+        self
+    }
+
+    /// Ends statements wherever `boundary` matches outside strings and
+    /// comments, instead of at `;`. `COPY` data is not recognized in this
+    /// mode.
+    ///
+    /// The regex is tried, anchored, at every character position outside
+    /// strings and comments, so scanning is noticeably slower than with the
+    /// built-in delimiter handling; keep the pattern simple. It sees the
+    /// input around that position, so `\A` matches only at the start of the
+    /// input and `(?m)^` only at the start of a line.
+    pub fn boundary(mut self, boundary: &Regex) -> Self {
+        let boundary =
+            meta::Regex::new(boundary.as_str()).expect("a valid regex is a valid meta regex");
+        self.boundary = Some(boundary);
+        self
+    }
+
+    /// Also ends a statement at a newline when, heuristically, it looks
+    /// complete and the next line starts another one: see
+    /// [`newline_ends_statement`](Self::newline_ends_statement). For inputs
+    /// whose semicolons are missing or inconsistent; best-effort, since
+    /// without delimiters a statement split across lines can't always be
+    /// told apart from two statements.
+    pub fn newline_boundaries(mut self, enabled: bool) -> Self {
+        self.newline_boundaries = enabled;
+        self
+    }
+
+    /// Tallies string literals and comments in [`ScanStats`].
+    pub fn count_literals(mut self, count: bool) -> Self {
+        self.count_literals = count;
+        self
+    }
+
+    /// Reports psql `\i`, `\ir`, `\include` and `\include_relative` lines
+    /// and MySQL `source` lines that open a statement, outside strings and
+    /// comments, as [`Event::Include`] instead of scanning them as code.
+    pub fn include_directives(mut self, enabled: bool) -> Self {
+        self.include_directives = enabled;
+        self
+    }
+
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Counts of dropped and unterminated segments seen so far, and once
+    /// the input is exhausted, the constructs it left open.
+    pub fn stats(&self) -> ScanStats {
+        self.stats.clone()
+    }
+
+    /// Whether the exhausted input ended in the state a new scanner starts
+    /// in, so a scan of whatever would follow it, on its own, gives the same
+    /// statements as continuing this one. False until the input is exhausted.
+    pub fn ended_cleanly(&self) -> bool {
+        self.clean_end
+    }
+
+    /// Yields only the statements from the remaining events.
+    pub fn statements(&mut self) -> impl Iterator<Item = LocatedStatement> + '_ {
+        self.by_ref().filter_map(|event| match event {
+            Event::Statement(statement) => Some(statement),
+            _ => None,
+        })
+    }
+
+    /// Scans the rest of the input and returns the number of statements in
+    /// it, as [`statements`](Self::statements) would yield, without copying
+    /// out their text or `COPY` data.
+    pub fn count_statements(&mut self) -> usize {
+        self.count_only = true;
+        self.by_ref().for_each(drop);
+        self.count_only = false;
+        std::mem::take(&mut self.counted)
+    }
+
+    /// Keeps each statement's exact source text between delimiters instead
+    /// of trimming surrounding whitespace.
+    pub fn preserve_whitespace(mut self, preserve: bool) -> Self {
+        self.preserve_whitespace = preserve;
+        self
+    }
+
+    /// Returns the next event, or `None` once the input is exhausted.
+    pub fn next_event(&mut self) -> Option<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let Some(c) = self.input[self.pos..].chars().next() else {
+                if self.done {
+                    return None;
+                }
+                // Emit the last statement even if it lacks a terminator,
+                // keeping any unclosed quote as part of its text
+                self.done = true;
+                self.clean_end = self.state == State::Normal
+                    && self.current.is_empty()
+                    && self.delimiter == ";"
+                    && !self.copy_open;
+                self.record_unclosed();
+                self.end_statement_with(None, None, None, false);
+                continue;
+            };
+            self.pos += c.len_utf8();
+            self.step(c);
+            if self.runs {
+                self.take_run();
+            }
+        }
+    }
+
+    /// Moves the run of characters ahead that [`step`](Self::step) would
+    /// only append to the current segment, changing nothing else, into it
+    /// as one slice: the scan's fast path through string bodies, comments
+    /// and plain words. The run stops at any byte that could matter (a
+    /// quote, newline, `\r`, comment or delimiter character), so it always
+    /// ends on a character boundary.
+    fn take_run(&mut self) {
+        let ordinary: fn(&u8) -> bool = match self.state {
+            State::InString if !self.escape_next => |b| !matches!(b, b'\'' | b'\\' | b'\n' | b'\r'),
+            State::InBacktick => |b| !matches!(b, b'`' | b'\n' | b'\r'),
+            State::InDollarQuote => |b| !matches!(b, b'$' | b'\n' | b'\r'),
+            State::InComment(CommentKind::Line) => |b| !matches!(b, b'\n' | b'\r'),
+            State::InComment(CommentKind::Block) => |b| !matches!(b, b'*' | b'\n' | b'\r'),
+            // Mid-statement, where a word or space can't start a directive
+            // or end the statement
+            State::Normal
+                if self.has_code
+                    && self.start_line.is_some()
+                    && self.boundary.is_none()
+                    && self.delimiter == ";" =>
+            {
+                |b| {
+                    b.is_ascii_alphanumeric()
+                        || matches!(b, b'_' | b' ' | b'\t' | b',' | b'.' | b'=')
+                }
+            }
+            _ => return,
+        };
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .bytes()
+            .position(|b| !ordinary(&b))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return;
+        }
+        let run = &rest[..len];
+        if self.state == State::Normal && run.bytes().any(|b| !matches!(b, b' ' | b'\t')) {
+            self.ends_in_line_comment = false;
+        }
+        self.current.push_str(run);
+        self.pos += len;
+    }
+
+    /// Records the constructs open at the end of input in the stats.
+    fn record_unclosed(&mut self) {
+        let line = self.open_line;
+        let open = match self.state {
+            State::InString | State::InDollarQuote => Some(Unclosed::String { line }),
+            State::InBacktick => Some(Unclosed::Identifier { line }),
+            State::InComment(CommentKind::Block) => Some(Unclosed::Comment { line }),
+            State::InComment(CommentKind::Line) | State::Normal => None,
+        };
+        self.stats.unclosed.extend(open);
+        if self.executable_comment.is_some() {
+            self.stats.unclosed.push(Unclosed::Comment {
+                line: self.executable_line,
+            });
+        }
+        if self.paren_depth > 0 {
+            self.stats.unclosed.push(Unclosed::Parentheses {
+                depth: self.paren_depth,
+                line: self.paren_line,
+            });
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    /// Consumes the next character into the current statement.
+    fn advance(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+            self.current.push(c);
+        }
+    }
+
+    fn step(&mut self, c: char) {
+        let line = self.line;
+        if c == '\n' {
+            self.line += 1;
+        }
+        if c == '\r' && self.peek() == Some('\n') {
+            let in_string = matches!(self.state, State::InString | State::InDollarQuote);
+            match self.line_endings {
+                LineEndings::Unix if !in_string => return,
+                LineEndings::UnixInStrings => return,
+                _ => {}
+            }
+        }
+
+        match self.state {
+            State::Normal => {
+                if let Some(boundary) = &self.boundary {
+                    let at = self.pos - c.len_utf8();
+                    let input = Input::new(&self.input).range(at..).anchored(Anchored::Yes);
+                    if let Some(m) = boundary.find(input).filter(|m| !m.is_empty()) {
+                        let matched = self.input[m.range()].to_string();
+                        self.pos = at + matched.len();
+                        self.line = line + matched.matches('\n').count();
+                        self.end_statement_with(None, None, Some(matched), true);
+                        return;
+                    }
+                    if c == ';' {
+                        // An ordinary character once a boundary regex is set
+                        self.start_line.get_or_insert(line);
+                        self.has_code = true;
+                        self.ends_in_line_comment = false;
+                        self.current.push(c);
+                        return;
+                    }
+                }
+                if self.dialect == Dialect::Mysql
+                    && self.start_line.is_none()
+                    && self.delimiter_directive(c)
+                {
+                    return;
+                }
+                if self.include_directives
+                    && !self.has_code
+                    && self.executable_comment.is_none()
+                    && self.include_directive(c)
+                {
+                    return;
+                }
+                if self.start_line.is_none() && !c.is_whitespace() && c != ';' {
+                    self.start_line = Some(line);
+                }
+                if self.delimiter != ";" {
+                    let at = self.pos - c.len_utf8();
+                    if self.input[at..].starts_with(self.delimiter.as_str()) {
+                        self.pos = at + self.delimiter.len();
+                        let delimiter = self.delimiter.clone();
+                        self.end_statement_with(None, Some(delimiter), None, true);
+                        return;
+                    }
+                }
+                if c.is_whitespace()
+                    && self.start_line.is_none()
+                    && self.executable_comment.is_some()
+                    && !self.preserve_whitespace
+                {
+                    // Leading whitespace after a re-wrapping opener would
+                    // survive trimming
+                    return;
+                }
+                if c == '/' && self.peek() == Some('*') {
+                    if let Some(opener) = self.executable_comment_opener() {
+                        self.pos += opener.len() - 1;
+                        self.current.push_str(&opener);
+                        self.has_code = true;
+                        self.ends_in_line_comment = false;
+                        self.executable_comment = Some(opener);
+                        self.executable_line = line;
+                        return;
+                    }
+                }
+                if c == '*' && self.peek() == Some('/') && self.executable_comment.is_some() {
+                    self.executable_comment = None;
+                    if !self.has_code {
+                        // Only the opener re-wrapping the rest of a comment
+                        // split at `; */`; nothing is left to run
+                        self.current.clear();
+                        self.start_line = None;
+                        self.pos += 1;
+                        return;
+                    }
+                    self.current.push(c);
+                    self.advance();
+                    self.ends_in_line_comment = false;
+                    return;
+                }
+                let starts_comment = (c == '-' && self.peek() == Some('-'))
+                    || (c == '/' && self.peek() == Some('*'))
+                    || (c == '#' && self.dialect == Dialect::Mysql);
+                if !c.is_whitespace() && (c != ';' || self.delimiter != ";") && !starts_comment {
+                    self.has_code = true;
+                }
+                match c {
+                    ';' if self.delimiter == ";" => {
+                        let data = if is_copy_from_stdin(&self.current) {
+                            let (data, complete) = copy_data(&self.input[self.pos..]);
+                            let (len, lines) = (data.len(), data.matches('\n').count());
+                            let data = if self.count_only {
+                                None
+                            } else if self.line_endings == LineEndings::UnixInStrings {
+                                Some(data.replace("\r\n", "\n"))
+                            } else {
+                                Some(data.to_string())
+                            };
+                            self.copy_open = !complete;
+                            self.pos += len;
+                            self.line += lines;
+                            data
+                        } else {
+                            None
+                        };
+                        self.end_statement(data);
+                        return;
+                    }
+                    '\'' => {
+                        self.state = State::InString;
+                        self.open_line = line;
+                        self.pending.push_back(Event::EnteredString { line });
+                        self.count_literal(Literal::String);
+                    }
+                    '`' if self.dialect == Dialect::Mysql => {
+                        self.state = State::InBacktick;
+                        self.open_line = line;
+                    }
+                    '(' => {
+                        if self.paren_depth == 0 {
+                            self.paren_line = line;
+                        }
+                        self.paren_depth += 1;
+                    }
+                    ')' => self.paren_depth = self.paren_depth.saturating_sub(1),
+                    '$' if self.dialect == Dialect::Postgres => {
+                        if let Some(tag) = self.dollar_quote_tag() {
+                            self.current.push(c);
+                            self.current.push_str(&tag);
+                            self.current.push('$');
+                            self.pos += tag.len() + 1;
+                            self.dollar_tag = tag;
+                            self.ends_in_line_comment = false;
+                            self.state = State::InDollarQuote;
+                            self.open_line = line;
+                            self.pending.push_back(Event::EnteredString { line });
+                            self.count_literal(Literal::String);
+                            return;
+                        }
+                    }
+                    '-' if self.peek() == Some('-') => {
+                        self.enter_comment(c, CommentKind::Line, line);
+                        return;
+                    }
+                    '/' if self.peek() == Some('*') => {
+                        self.enter_comment(c, CommentKind::Block, line);
+                        return;
+                    }
+                    '#' if self.dialect == Dialect::Mysql => {
+                        self.enter_comment(c, CommentKind::Line, line);
+                        return;
+                    }
+                    _ => {}
+                }
+                if !c.is_whitespace() {
+                    self.ends_in_line_comment = false;
+                }
+                if c == '\n' && self.newline_ends_statement() {
+                    self.end_statement(None);
+                }
+                self.current.push(c);
+            }
+            State::InString => {
+                self.current.push(c);
+                // A backslash escapes exactly the next character, whatever it
+                // is, so `\\` is a literal backslash that leaves the next
+                // quote unescaped and `\n` leaves nothing pending
+                let escaped = std::mem::take(&mut self.escape_next);
+                match c {
+                    '\\' => self.escape_next = !escaped,
+                    '\'' if !escaped => {
+                        self.state = State::Normal;
+                        self.pending.push_back(Event::ExitedString { line });
+                    }
+                    _ => {}
+                }
+            }
+            State::InBacktick => {
+                self.current.push(c);
+                if c == '`' {
+                    // A doubled backtick is an escaped one
+                    if self.peek() == Some('`') {
+                        self.advance();
+                    } else {
+                        self.state = State::Normal;
+                    }
+                }
+            }
+            State::InDollarQuote => {
+                self.current.push(c);
+                let rest = &self.input[self.pos..];
+                if c == '$'
+                    && rest.starts_with(self.dollar_tag.as_str())
+                    && rest[self.dollar_tag.len()..].starts_with('$')
+                {
+                    let closing = &rest[..self.dollar_tag.len() + 1];
+                    self.current.push_str(closing);
+                    self.pos += closing.len();
+                    self.state = State::Normal;
+                    self.pending.push_back(Event::ExitedString { line });
+                }
+            }
+            State::InComment(kind) => {
+                self.current.push(c);
+                let closed = match kind {
+                    CommentKind::Line => c == '\n',
+                    CommentKind::Block => c == '*' && self.peek() == Some('/'),
+                };
+                if closed {
+                    if kind == CommentKind::Block {
+                        self.advance();
+                    }
+                    self.state = State::Normal;
+                    self.pending.push_back(Event::ExitedComment { line });
+                    if kind == CommentKind::Line && self.newline_ends_statement() {
+                        self.end_statement(None);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Under [`newline_boundaries`](Self::newline_boundaries), whether the
+    /// newline just read ends the current statement: it has code, its
+    /// parentheses are balanced, it doesn't end in a comma, operator or
+    /// keyword that needs more after it, and the next line starts with a
+    /// statement keyword that doesn't continue it (`SET` after `UPDATE`, a
+    /// `SELECT` feeding an `INSERT`).
+    fn newline_ends_statement(&self) -> bool {
+        if !self.newline_boundaries
+            || !self.has_code
+            || self.paren_depth > 0
+            || self.delimiter != ";"
+            || self.boundary.is_some()
+            || self.executable_comment.is_some()
+        {
+            return false;
+        }
+        let Some(next) = Cursor::new(&self.input[self.pos..]).word() else {
+            return false;
+        };
+        let next = next.to_ascii_uppercase();
+        if !STATEMENT_KEYWORDS.contains(&next.as_str()) {
+            return false;
+        }
+        if !self.ends_in_line_comment && ends_in_continuation(&self.current) {
+            return false;
+        }
+        let first = Cursor::new(&self.current)
+            .word()
+            .map(str::to_ascii_uppercase)
+            .unwrap_or_default();
+        match (first.as_str(), next.as_str()) {
+            ("UPDATE", "SET") => false,
+            ("INSERT" | "REPLACE", "SELECT" | "WITH") => {
+                self.current.to_ascii_uppercase().contains("VALUES")
+            }
+            _ => true,
+        }
+    }
+
+    fn count_literal(&mut self, literal: Literal) {
+        if !self.count_literals {
+            return;
+        }
+        match literal {
+            Literal::String => self.stats.string_literals += 1,
+            Literal::Comment => self.stats.comments += 1,
+        }
+    }
+
+    fn enter_comment(&mut self, c: char, kind: CommentKind, line: usize) {
+        self.ends_in_line_comment = kind == CommentKind::Line;
+        self.open_line = line;
+        self.current.push(c);
+        // `--` and `/*` take two characters, MySQL's `#` just the one
+        if c != '#' {
+            self.advance();
+        }
+        self.state = State::InComment(kind);
+        self.pending.push_back(Event::EnteredComment { line, kind });
+        self.count_literal(Literal::Comment);
+    }
+
+    /// Handles a MySQL `DELIMITER <token>` line starting at `c`, returning
+    /// whether one was consumed. The directive itself is not a statement.
+    fn delimiter_directive(&mut self, c: char) -> bool {
+        const DIRECTIVE: &str = "DELIMITER";
+        let at = self.pos - c.len_utf8();
+        let rest = &self.input[at..];
+        let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let line = &rest[..line_end];
+        let is_directive = line.len() > DIRECTIVE.len()
+            && line.is_char_boundary(DIRECTIVE.len())
+            && line[..DIRECTIVE.len()].eq_ignore_ascii_case(DIRECTIVE)
+            && line[DIRECTIVE.len()..].starts_with([' ', '\t']);
+        let Some(token) = is_directive
+            .then(|| line[DIRECTIVE.len()..].split_whitespace().next())
+            .flatten()
+        else {
+            return false;
+        };
+        self.delimiter = token.to_string();
+        self.pos = at + line_end;
+        self.line += line.matches('\n').count();
+        true
+    }
+
+    /// Handles an include directive line starting at `c`, returning whether
+    /// one was consumed. The directive itself is not part of a statement.
+    fn include_directive(&mut self, c: char) -> bool {
+        let at = self.pos - c.len_utf8();
+        let rest = &self.input[at..];
+        let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+        let line = &rest[..line_end];
+        let Some((command, path)) = line.split_once([' ', '\t']) else {
+            return false;
+        };
+        let is_directive = matches!(command, "\\i" | "\\ir" | "\\include" | "\\include_relative")
+            || command.eq_ignore_ascii_case("source");
+        // mysql accepts a trailing `;`, psql a quoted name
+        let path = path.trim().trim_end_matches(';').trim_end();
+        let path = path
+            .strip_prefix('\'')
+            .and_then(|p| p.strip_suffix('\''))
+            .unwrap_or(path);
+        if !is_directive || path.is_empty() {
+            return false;
+        }
+        self.pending.push_back(Event::Include {
+            line: self.line,
+            path: path.to_string(),
+            span: at..at + line_end,
+        });
+        self.pos = at + line_end;
+        self.line += line.matches('\n').count();
+        true
+    }
+
+    /// The opener of an executable comment starting at the `/` just
+    /// consumed, version number included: `/*!`, `/*!40101` or `/*M!100101`.
+    fn executable_comment_opener(&self) -> Option<String> {
+        let rest = &self.input[self.pos..];
+        let marker = ["*!", "*M!"]
+            .into_iter()
+            .find(|marker| rest.starts_with(marker))?;
+        let version = rest[marker.len()..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len() - marker.len());
+        Some(format!("/{}", &rest[..marker.len() + version]))
+    }
+
+    /// The tag of a dollar quote opening at the `$` just consumed, if any.
+    fn dollar_quote_tag(&self) -> Option<String> {
+        let follows_word = self
+            .current
+            .chars()
+            .next_back()
+            .is_some_and(|p| p.is_alphanumeric() || p == '_' || p == '$');
+        if follows_word {
+            return None;
+        }
+        let rest = &self.input[self.pos..];
+        let end = rest.find('$')?;
+        let tag = &rest[..end];
+        let valid = tag.chars().enumerate().all(|(i, ch)| {
+            ch == '_'
+                || if i == 0 {
+                    ch.is_alphabetic()
+                } else {
+                    ch.is_alphanumeric()
+                }
+        });
+        valid.then(|| tag.to_string())
+    }
+
+    fn end_statement(&mut self, data: Option<String>) {
+        self.end_statement_with(data, None, None, true);
+    }
+
+    /// Emits the current segment as a statement; `terminated` is false when
+    /// the end of input rather than a delimiter ended it.
+    fn end_statement_with(
+        &mut self,
+        data: Option<String>,
+        delimiter: Option<String>,
+        boundary: Option<String>,
+        terminated: bool,
+    ) {
+        let has_code = std::mem::take(&mut self.has_code);
+        self.paren_depth = 0;
+        let ends_in_line_comment = std::mem::take(&mut self.ends_in_line_comment);
+        match self.start_line {
+            None if terminated => self.stats.empty_segments += 1,
+            Some(_) if !has_code => {
+                self.stats.comment_only_segments += 1;
+                self.start_line = None;
+            }
+            Some(_) if !terminated => self.stats.unterminated_statements += 1,
+            _ => {}
+        }
+        if self.count_only {
+            self.counted += usize::from(self.start_line.take().is_some());
+        } else if let Some(line) = self.start_line.take() {
+            let body = if self.preserve_whitespace {
+                self.current.as_str()
+            } else {
+                self.current.trim()
+            };
+            // Copied out at its final size, room for the suffixes below
+            // included, so `current` keeps its capacity for the next segment
+            let mut text = String::with_capacity(body.len() + 3);
+            text.push_str(body);
+            if ends_in_line_comment && !self.preserve_whitespace {
+                // Keep the comment's newline so a terminator written after
+                // the text isn't commented out
+                text.push('\n');
+            }
+            if self.executable_comment.is_some() && terminated {
+                // Close the comment here; the opener starts the next segment
+                if ends_in_line_comment {
+                    text.push_str("*/");
+                } else {
+                    text.truncate(text.trim_end().len());
+                    text.push_str(" */");
+                }
+            }
+            self.pending.push_back(Event::Statement(LocatedStatement {
+                text,
+                line,
+                data,
+                delimiter,
+                boundary,
+                terminated,
+            }));
+        }
+        self.current.clear();
+        if let (Some(opener), true) = (&self.executable_comment, terminated) {
+            self.current.push_str(opener);
+            self.current.push(' ');
+        }
+    }
+}
+
+impl Iterator for Scanner {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.next_event()
+    }
+}
+
+/// Splits `content` into statements with their starting lines.
+pub fn split_statements_located(content: &str, preserve_whitespace: bool) -> Vec<LocatedStatement> {
+    let mut scanner = Scanner::new(content).preserve_whitespace(preserve_whitespace);
+    scanner.statements().collect()
+}
+
+/// Whether `text`, scanned on its own, ends inside a `--` (or MySQL `#`)
+/// comment, which would swallow anything appended to it on the same line.
+pub(crate) fn ends_in_line_comment(text: &str, dialect: Dialect) -> bool {
+    let mut scanner = Scanner::new(text).dialect(dialect);
+    scanner.by_ref().for_each(drop);
+    scanner.state == State::InComment(CommentKind::Line)
+}
+
+/// Whether `text` ends with a `;` that isn't part of a trailing `--` (or
+/// MySQL `#`) comment.
+pub(crate) fn ends_with_semicolon(text: &str, dialect: Dialect) -> bool {
+    text.ends_with(';') && !ends_in_line_comment(text, dialect)
+}
+
+/// Splits `content` into trimmed statement texts.
+pub fn split_statements(content: &str) -> Vec<String> {
+    split_statements_located(content, false)
+        .into_iter()
+        .map(|statement| statement.text)
+        .collect()
+}
+
+/// Leading keywords of statements, which the line after a complete-looking
+/// one must start with for a newline to end it.
+const STATEMENT_KEYWORDS: &[&str] = &[
+    "ALTER", "ANALYZE", "BEGIN", "CALL", "COMMIT", "COPY", "CREATE", "DELETE", "DESCRIBE", "DROP",
+    "EXEC", "EXECUTE", "EXPLAIN", "GRANT", "INSERT", "LOCK", "MERGE", "PRAGMA", "RENAME",
+    "REPLACE", "REVOKE", "ROLLBACK", "SELECT", "SET", "SHOW", "START", "TRUNCATE", "UNLOCK",
+    "UPDATE", "USE", "VACUUM", "WITH",
+];
+
+/// Keywords a statement can't end with, since more must follow them.
+const CONTINUATION_KEYWORDS: &[&str] = &[
+    "ALL", "AND", "AS", "BETWEEN", "BY", "CASE", "CROSS", "DISTINCT", "ELSE", "EXISTS", "FROM",
+    "FULL", "GROUP", "HAVING", "IN", "INNER", "INTO", "IS", "JOIN", "LEFT", "LIKE", "LIMIT", "NOT",
+    "OFFSET", "ON", "OR", "ORDER", "OUTER", "RIGHT", "SELECT", "SET", "TABLE", "THEN", "UNION",
+    "USING", "VALUES", "WHEN", "WHERE", "WITH",
+];
+
+/// Whether `text` ends in a comma, operator or keyword that needs more after it.
+fn ends_in_continuation(text: &str) -> bool {
+    let text = text.trim_end();
+    if text.ends_with("*/") {
+        // A closing block comment, not a division
+        return false;
+    }
+    if text.ends_with([
+        ',', '(', '.', '+', '-', '*', '/', '=', '<', '>', '|', '&', '%',
+    ]) {
+        return true;
+    }
+    let word = text
+        .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or("")
+        .to_ascii_uppercase();
+    CONTINUATION_KEYWORDS.contains(&word.as_str())
+}
+
+/// Whether `statement` is a `COPY ... FROM stdin` whose data follows inline.
+fn is_copy_from_stdin(statement: &str) -> bool {
+    let mut words = statement
+        .lines()
+        .skip_while(|l| l.trim().is_empty() || l.trim_start().starts_with("--"))
+        .flat_map(str::split_whitespace);
+    if !words.next().is_some_and(|w| w.eq_ignore_ascii_case("COPY")) {
+        return false;
+    }
+    let mut after_from = false;
+    words.any(|w| {
+        let stdin = after_from && w.eq_ignore_ascii_case("STDIN");
+        after_from = w.eq_ignore_ascii_case("FROM");
+        stdin
+    })
+}
+
+/// Returns the prefix of `rest` (the input after a `COPY ... FROM stdin;`)
+/// holding the data rows through the `\.` end-of-data marker, and whether
+/// the marker was found. An unterminated block runs to the end of input.
+fn copy_data(rest: &str) -> (&str, bool) {
+    let mut end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+    while end < rest.len() {
+        let line_end = rest[end..].find('\n').map_or(rest.len(), |i| end + i);
+        if rest[end..line_end].trim_end_matches('\r') == "\\." {
+            return (&rest[..line_end], true);
+        }
+        end = line_end + 1;
+    }
+    (rest, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_literals() {
+        let input = "-- header\nINSERT INTO t VALUES ('a', 'it''s', '-- no', '/* no */');\n\
+                     /* block ' */ SELECT $$x'y$$, $tag$z$tag$ -- trailing\n;\n\
+                     /*!40101 SET @a = 'v' */;\nSELECT 1 /* one */ /* two */;";
+        let mut scanner = Scanner::new(input)
+            .dialect(Dialect::Postgres)
+            .count_literals(true);
+        scanner.statements().count();
+        // `'it''s'` is two literals back to back, as the scanner sees it;
+        // the executable comment is code, not a comment
+        assert_eq!(
+            (scanner.stats().string_literals, scanner.stats().comments),
+            (8, 5)
+        );
+
+        let mut scanner = Scanner::new(input);
+        scanner.statements().count();
+        assert_eq!(
+            (scanner.stats().string_literals, scanner.stats().comments),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_event_sequence() {
+        let input = "SELECT 'a;b'; -- note;\n/* x; */ SELECT 2;";
+        let events: Vec<Event> = Scanner::new(input).collect();
+        assert_eq!(
+            events,
+            vec![
+                Event::EnteredString { line: 1 },
+                Event::ExitedString { line: 1 },
+                Event::Statement(LocatedStatement {
+                    text: "SELECT 'a;b'".to_string(),
+                    line: 1,
+                    data: None,
+                    delimiter: None,
+                    boundary: None,
+                    terminated: true,
+                }),
+                Event::EnteredComment {
+                    line: 1,
+                    kind: CommentKind::Line,
+                },
+                Event::ExitedComment { line: 1 },
+                Event::EnteredComment {
+                    line: 2,
+                    kind: CommentKind::Block,
+                },
+                Event::ExitedComment { line: 2 },
+                Event::Statement(LocatedStatement {
+                    text: "-- note;\n/* x; */ SELECT 2".to_string(),
+                    line: 1,
+                    data: None,
+                    delimiter: None,
+                    boundary: None,
+                    terminated: true,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semicolons_in_comments_do_not_split() {
+        let statements = split_statements("SELECT 1 /* ; */; -- a;b\nSELECT 2;");
+        assert_eq!(statements, vec!["SELECT 1 /* ; */", "-- a;b\nSELECT 2"]);
+    }
+
+    #[test]
+    fn test_mysql_hash_comments() {
+        let input = "SELECT 1 # a;b\n;\n#\nSELECT '#;';\n# trailer;";
+        let statements: Vec<String> = Scanner::new(input)
+            .dialect(Dialect::Mysql)
+            .statements()
+            .map(|statement| statement.text)
+            .collect();
+        assert_eq!(statements, vec!["SELECT 1 # a;b\n", "#\nSELECT '#;'"]);
+
+        // Elsewhere `#` is ordinary code (a Postgres operator, say)
+        assert_eq!(split_statements("SELECT 1 # 2;"), vec!["SELECT 1 # 2"]);
+    }
+
+    #[test]
+    fn test_newline_boundaries_without_semicolons() {
+        let scan = |input: &str| -> Vec<(String, usize)> {
+            Scanner::new(input)
+                .newline_boundaries(true)
+                .statements()
+                .map(|s| (s.text, s.line))
+                .collect()
+        };
+        assert_eq!(
+            scan("CREATE TABLE t (id INT)\nINSERT INTO t VALUES (1)\nSELECT * FROM t;\nSELECT 2"),
+            vec![
+                ("CREATE TABLE t (id INT)".to_string(), 1),
+                ("INSERT INTO t VALUES (1)".to_string(), 2),
+                ("SELECT * FROM t".to_string(), 3),
+                ("SELECT 2".to_string(), 4),
+            ]
+        );
+        // Statements spanning lines stay whole
+        assert_eq!(
+            scan("SELECT a,\n  b\nFROM t\nWHERE a = 1 AND\nSELECT_FLAG = 2\nUPDATE t\nSET a = 1"),
+            vec![
+                (
+                    "SELECT a,\n  b\nFROM t\nWHERE a = 1 AND\nSELECT_FLAG = 2".to_string(),
+                    1
+                ),
+                ("UPDATE t\nSET a = 1".to_string(), 6),
+            ]
+        );
+        assert_eq!(
+            scan("INSERT INTO t (a)\nSELECT a FROM s\nCREATE TABLE u (\n  id INT\n)\nDROP TABLE v"),
+            vec![
+                ("INSERT INTO t (a)\nSELECT a FROM s".to_string(), 1),
+                ("CREATE TABLE u (\n  id INT\n)".to_string(), 3),
+                ("DROP TABLE v".to_string(), 6),
+            ]
+        );
+        // Newlines in strings and comments don't count
+        assert_eq!(
+            scan("SELECT 'a\nSELECT b' -- note\nSELECT /* x\nSELECT */ 2"),
+            vec![
+                ("SELECT 'a\nSELECT b' -- note\n".to_string(), 1),
+                ("SELECT /* x\nSELECT */ 2".to_string(), 3),
+            ]
+        );
+        // Off by default
+        assert_eq!(
+            split_statements("SELECT 1\nSELECT 2"),
+            vec!["SELECT 1\nSELECT 2"]
+        );
+    }
+
+    #[test]
+    fn test_mysql_delimiter_directive() {
+        let input =
+            "DELIMITER $$\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND$$\nDELIMITER ;\nSELECT 2;";
+        let statements: Vec<_> = Scanner::new(input)
+            .dialect(Dialect::Mysql)
+            .statements()
+            .collect();
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0].text,
+            "CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND"
+        );
+        assert_eq!(statements[0].line, 2);
+        assert_eq!(statements[0].delimiter.as_deref(), Some("$$"));
+        assert_eq!(statements[1].text, "SELECT 2");
+        assert_eq!(statements[1].line, 7);
+        assert_eq!(statements[1].delimiter, None);
+    }
+
+    #[test]
+    fn test_postgres_dollar_quotes() {
+        let input = "CREATE FUNCTION f() RETURNS int AS $fn$\nBEGIN RETURN 1; END;\n$fn$ LANGUAGE plpgsql;\nSELECT $$a;b$$;";
+        let mut scanner = Scanner::new(input).dialect(Dialect::Postgres);
+        let statements: Vec<_> = scanner.statements().map(|s| s.text).collect();
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE FUNCTION f() RETURNS int AS $fn$\nBEGIN RETURN 1; END;\n$fn$ LANGUAGE plpgsql",
+                "SELECT $$a;b$$",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_executable_comments_split_but_block_comments_do_not() {
+        let input = "/*!40000 INSERT INTO t VALUES (1); INSERT INTO t VALUES ('2;')*/;\n\
+                     /* a; b */ SELECT 1;\n\
+                     /*!40101 SET NAMES utf8 */;\n\
+                     /*M!100101 SET @x = 1; */;\n\
+                     /* only; a comment */;";
+        let mut scanner = Scanner::new(input);
+        let statements: Vec<_> = scanner.statements().map(|s| (s.text, s.line)).collect();
+        assert_eq!(
+            statements,
+            vec![
+                ("/*!40000 INSERT INTO t VALUES (1) */".to_string(), 1),
+                ("/*!40000 INSERT INTO t VALUES ('2;')*/".to_string(), 1),
+                ("/* a; b */ SELECT 1".to_string(), 2),
+                ("/*!40101 SET NAMES utf8 */".to_string(), 3),
+                ("/*M!100101 SET @x = 1 */".to_string(), 4),
+            ]
+        );
+        assert_eq!(scanner.stats().comment_only_segments, 1);
+    }
+
+    #[test]
+    fn test_unclosed_constructs_at_end_of_input() {
+        let unclosed = |input: &str, dialect: Dialect| {
+            let mut scanner = Scanner::new(input).dialect(dialect);
+            scanner.statements().for_each(drop);
+            scanner.stats().unclosed
+        };
+        let generic = Dialect::Generic;
+        assert_eq!(
+            unclosed("SELECT 1;\nINSERT INTO t VALUES (1, (2", generic),
+            vec![Unclosed::Parentheses { depth: 2, line: 2 }]
+        );
+        assert_eq!(
+            unclosed(
+                "CREATE TABLE t (\n  a INT, -- note\n  b TEXT DEFAULT 'x",
+                generic
+            ),
+            vec![
+                Unclosed::String { line: 3 },
+                Unclosed::Parentheses { depth: 1, line: 1 }
+            ]
+        );
+        assert_eq!(
+            unclosed("SELECT 1;\n/* truncated\n", generic),
+            vec![Unclosed::Comment { line: 2 }]
+        );
+        assert_eq!(
+            unclosed("SELECT 1;\n/*!40101 SET NAMES utf8", generic),
+            vec![Unclosed::Comment { line: 2 }]
+        );
+        assert_eq!(
+            unclosed("SELECT `a\n", Dialect::Mysql),
+            vec![Unclosed::Identifier { line: 1 }]
+        );
+        assert_eq!(
+            unclosed("SELECT $$ (", Dialect::Postgres),
+            vec![Unclosed::String { line: 1 }]
+        );
+
+        // Balanced input, parentheses in strings and comments, a finished
+        // line comment and a `;` inside parentheses report nothing
+        for input in [
+            "SELECT (1) -- trailing note",
+            "SELECT '(' /* ( */;",
+            "CREATE TABLE t (a INT;\nSELECT 1;",
+        ] {
+            assert_eq!(unclosed(input, generic), vec![], "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_mysql_backtick_identifiers_hide_semicolons() {
+        let input = "CREATE TABLE `weird;name` (`a``;b` INT, c TEXT DEFAULT 'x`;');\nSELECT 1;";
+        let statements: Vec<_> = Scanner::new(input)
+            .dialect(Dialect::Mysql)
+            .statements()
+            .map(|s| s.text)
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE TABLE `weird;name` (`a``;b` INT, c TEXT DEFAULT 'x`;')",
+                "SELECT 1"
+            ]
+        );
+
+        // Other dialects leave backticks alone
+        assert_eq!(Scanner::new(input).statements().count(), 4);
+    }
+
+    #[test]
+    fn test_stats_count_dropped_and_unterminated_segments() {
+        let mut scanner = Scanner::new("SELECT 1;;\n ; -- only a comment\n; /* x */;\nSELECT 2");
+        let statements: Vec<_> = scanner
+            .statements()
+            .map(|s| (s.text, s.terminated))
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                ("SELECT 1".to_string(), true),
+                ("SELECT 2".to_string(), false)
+            ]
+        );
+        assert_eq!(
+            scanner.stats(),
+            ScanStats {
+                empty_segments: 2,
+                comment_only_segments: 2,
+                unterminated_statements: 1,
+                unclosed: Vec::new(),
+                string_literals: 0,
+                comments: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_statements_without_whitespace_between() {
+        for preserve in [false, true] {
+            let statements = split_statements_located("SELECT 1;SELECT ';';;;SELECT 3", preserve);
+            let texts: Vec<_> = statements
+                .iter()
+                .map(|s| (s.text.as_str(), s.terminated))
+                .collect();
+            assert_eq!(
+                texts,
+                vec![
+                    ("SELECT 1", true),
+                    ("SELECT ';'", true),
+                    ("SELECT 3", false)
+                ],
+                "preserve: {}",
+                preserve
+            );
+        }
+
+        assert!(split_statements(";;;").is_empty());
+        assert!(ends_in_line_comment("SELECT 1 -- x", Dialect::Generic));
+        assert!(!ends_in_line_comment("SELECT 1 -- x\n", Dialect::Generic));
+        assert!(!ends_in_line_comment("SELECT '--'", Dialect::Generic));
+    }
+
+    #[test]
+    fn test_runs_match_character_steps() {
+        let input = "CREATE TABLE `a;b` (id INT, name VARCHAR(20) DEFAULT 'x;y') ;\n\
+             INSERT INTO t VALUES (1,'it''s; fine'),(2,'back\\\\slash\\'; still'), (3, \"dq;\");\n\
+             -- line; comment\n/* block; comment */ SELECT 1 ;;\n\
+             /*!40101 SET @OLD=@@X; SET NAMES utf8 */;\n\
+             SELECT $tag$ body; $tag$, $$ x; $$ ;\n\
+             COPY t (a, b) FROM stdin;\n1\ta;b\n\\.\n\
+             SELECT 'multi\r\nline; string' , x -- trailing\r\n;\r\n\
+             # hash; comment\n   UPDATE   t SET a = 1   WHERE b = 2\nSELECT 2";
+        for dialect in [Dialect::Generic, Dialect::Mysql, Dialect::Postgres] {
+            for preserve in [false, true] {
+                for line_endings in [
+                    LineEndings::Keep,
+                    LineEndings::Unix,
+                    LineEndings::UnixInStrings,
+                ] {
+                    for newlines in [false, true] {
+                        let scan = |runs: bool| {
+                            let mut scanner = Scanner::new(input)
+                                .dialect(dialect)
+                                .preserve_whitespace(preserve)
+                                .line_endings(line_endings)
+                                .newline_boundaries(newlines)
+                                .count_literals(true);
+                            scanner.runs = runs;
+                            let events: Vec<Event> = scanner.by_ref().collect();
+                            (events, scanner.stats(), scanner.ended_cleanly())
+                        };
+                        assert_eq!(
+                            scan(true),
+                            scan(false),
+                            "{:?} preserve={} {:?} newlines={}",
+                            dialect,
+                            preserve,
+                            line_endings,
+                            newlines
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_input_ending_in_quote_backslash_or_delimiter() {
+        // A stray quote opens a string that runs to the end of input
+        let mut scanner = Scanner::new("SELECT 1;\n'");
+        let statements: Vec<_> = scanner
+            .statements()
+            .map(|s| (s.text, s.terminated))
+            .collect();
+        assert_eq!(
+            statements,
+            vec![("SELECT 1".to_string(), true), ("'".to_string(), false)]
+        );
+        assert_eq!(scanner.stats().unclosed, vec![Unclosed::String { line: 2 }]);
+
+        // A trailing backslash escapes nothing, inside a string or not
+        let mut scanner = Scanner::new("SELECT 'a\\");
+        let statements: Vec<_> = scanner.statements().map(|s| s.text).collect();
+        assert_eq!(statements, vec!["SELECT 'a\\"]);
+        assert_eq!(scanner.stats().unclosed, vec![Unclosed::String { line: 1 }]);
+        let mut scanner = Scanner::new("SELECT 1 \\");
+        let statements: Vec<_> = scanner.statements().map(|s| s.text).collect();
+        assert_eq!(statements, vec!["SELECT 1 \\"]);
+        assert!(scanner.stats().unclosed.is_empty());
+
+        // A final delimiter leaves nothing behind: no empty or unterminated
+        // segment after it
+        let mut scanner = Scanner::new("SELECT 'a;';");
+        let statements: Vec<_> = scanner
+            .statements()
+            .map(|s| (s.text, s.terminated))
+            .collect();
+        assert_eq!(statements, vec![("SELECT 'a;'".to_string(), true)]);
+        assert_eq!(scanner.stats(), ScanStats::default());
+    }
+
+    #[test]
+    fn test_escaped_backslash_before_closing_quote() {
+        // `'\\'` is a string holding one backslash; the quote still closes it
+        assert_eq!(
+            split_statements("SELECT '\\\\'; SELECT 2;"),
+            vec!["SELECT '\\\\'", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("SELECT '\\\\\\\\'; SELECT 2;"),
+            vec!["SELECT '\\\\\\\\'", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("INSERT INTO t VALUES ('a\\\\'); SELECT 2; SELECT 3;"),
+            vec!["INSERT INTO t VALUES ('a\\\\')", "SELECT 2", "SELECT 3"]
+        );
+        // An odd run still escapes the quote
+        assert_eq!(
+            split_statements("SELECT 'a\\\\\\'; b'; SELECT 2;"),
+            vec!["SELECT 'a\\\\\\'; b'", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn test_escape_covers_only_next_character() {
+        // An escaped letter leaves no escape pending for the closing quote
+        assert_eq!(
+            split_statements("SELECT '\\n'; SELECT 2;"),
+            vec!["SELECT '\\n'", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("SELECT '\\\\'; SELECT 2;"),
+            vec!["SELECT '\\\\'", "SELECT 2"]
+        );
+        // The `;` after an escape sequence is still inside the string
+        assert_eq!(
+            split_statements("SELECT '\\t;'; SELECT 2;"),
+            vec!["SELECT '\\t;'", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("SELECT '\\\\\\n;'; SELECT 2;"),
+            vec!["SELECT '\\\\\\n;'", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn test_line_endings_outside_strings() {
+        let input = "INSERT INTO t VALUES ('one\r\ntwo');\r\n-- note\r\nSELECT\r\n  1;";
+        let scan = |line_endings| -> Vec<String> {
+            Scanner::new(input)
+                .line_endings(line_endings)
+                .statements()
+                .map(|s| s.text)
+                .collect()
+        };
+        assert_eq!(
+            scan(LineEndings::Unix),
+            vec![
+                "INSERT INTO t VALUES ('one\r\ntwo')",
+                "-- note\nSELECT\n  1"
+            ]
+        );
+        assert_eq!(
+            scan(LineEndings::UnixInStrings),
+            vec!["INSERT INTO t VALUES ('one\ntwo')", "-- note\nSELECT\n  1"]
+        );
+        assert_eq!(
+            scan(LineEndings::Keep),
+            vec![
+                "INSERT INTO t VALUES ('one\r\ntwo')",
+                "-- note\r\nSELECT\r\n  1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_trailing_line_comment_keeps_newline() {
+        assert_eq!(
+            split_statements("SELECT 1 -- one;\n;\nSELECT 2 /* two */;\nSELECT 3 -- three"),
+            vec![
+                "SELECT 1 -- one;\n",
+                "SELECT 2 /* two */",
+                "SELECT 3 -- three\n"
+            ]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::dialect::Dialect;
+use crate::validate::{dollar_tag_len, quoted_len};
+
+/// `statement` with each run of `;`s separated only by whitespace (`;;`,
+/// `; ;`) collapsed into one `;`, or `None` if it has no such run. Runs in
+/// string literals, quoted identifiers and comments are left alone.
+///
+/// With the default delimiter the scanner already drops the empty
+/// statements between repeated `;`s; a run survives in the text of a
+/// statement ended by a `DELIMITER` or boundary regex instead.
+pub fn collapse_semicolons(statement: &str, dialect: Dialect) -> Option<String> {
+    let mut collapsed = String::with_capacity(statement.len());
+    let mut changed = false;
+    let mut pos = 0;
+    while let Some(c) = statement[pos..].chars().next() {
+        let rest = &statement[pos..];
+        let len = match c {
+            '-' if rest.starts_with("--") => rest.find('\n').map_or(rest.len(), |n| n + 1),
+            '#' if dialect == Dialect::Mysql => rest.find('\n').map_or(rest.len(), |n| n + 1),
+            '/' if rest.starts_with("/*") && !rest.starts_with("/*!") => {
+                rest[2..].find("*/").map_or(rest.len(), |n| n + 4)
+            }
+            '\'' | '"' | '`' => quoted_len(rest, c).unwrap_or(rest.len()),
+            '$' if dialect == Dialect::Postgres => match dollar_tag_len(rest) {
+                Some(tag_len) => rest[tag_len..]
+                    .find(&rest[..tag_len])
+                    .map_or(rest.len(), |n| 2 * tag_len + n),
+                None => 1,
+            },
+            ';' => {
+                let run = rest
+                    .find(|c: char| !(c.is_whitespace() || c == ';'))
+                    .unwrap_or(rest.len());
+                // Keep the whitespace after the run's last `;`
+                let last = rest[..run].rfind(';').unwrap_or(0);
+                changed |= last > 0;
+                collapsed.push(';');
+                pos += last + 1;
+                continue;
+            }
+            c => c.len_utf8(),
+        };
+        collapsed.push_str(&rest[..len]);
+        pos += len;
+    }
+    changed.then_some(collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_semicolons() {
+        assert_eq!(
+            collapse_semicolons("BEGIN SELECT 1;; SELECT 2; ;\n END", Dialect::Mysql).as_deref(),
+            Some("BEGIN SELECT 1; SELECT 2;\n END")
+        );
+        assert_eq!(
+            collapse_semicolons("SELECT ';;', `a;;b` -- ;;\n; /* ; ; */;", Dialect::Mysql),
+            None
+        );
+        assert_eq!(
+            collapse_semicolons("DO $$ BEGIN NULL;; END $$;;", Dialect::Postgres).as_deref(),
+            Some("DO $$ BEGIN NULL;; END $$;")
+        );
+        assert_eq!(
+            collapse_semicolons("SELECT 1; SELECT 2;", Dialect::Generic),
+            None
+        );
+    }
+}
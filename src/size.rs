@@ -0,0 +1,49 @@
+/// Parses a human-friendly size such as `50MB`, `2GB` or `512KB` into bytes.
+///
+/// Units are 1024-based and case-insensitive, with optional whitespace
+/// before them; a bare number (or one ending in `B`) is a count of bytes.
+pub fn parse_size(input: &str) -> Result<usize, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    let multiplier: usize = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" => 1024,
+        "MB" => 1024 * 1024,
+        "GB" => 1024 * 1024 * 1024,
+        other => {
+            return Err(format!(
+                "unknown size unit {:?} in {:?} (expected B, KB, MB or GB)",
+                other, input
+            ))
+        }
+    };
+    let value: usize = digits
+        .parse()
+        .map_err(|_| format!("invalid size {:?}: expected e.g. 500KB or 50MB", input))?;
+    value
+        .checked_mul(multiplier)
+        .filter(|&bytes| bytes > 0)
+        .ok_or_else(|| format!("size {:?} must be positive and fit in memory", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("50MB"), Ok(50 * 1024 * 1024));
+        assert_eq!(parse_size("2GB"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("512 kb"), Ok(512 * 1024));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("10B"), Ok(10));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("1.5GB").is_err());
+        assert!(parse_size("10TB").is_err());
+        assert!(parse_size("0KB").is_err());
+    }
+}
@@ -0,0 +1,5935 @@
+use crate::ascii::{self, AsciiMode};
+use crate::batch::{batch_statements, BatchLimits, STATEMENT_SEPARATOR};
+use crate::category::{self, Category};
+use crate::compression::Compression;
+use crate::csv_insert;
+use crate::definer;
+use crate::dialect::{self, Detection, Dialect};
+use crate::encoding::{self, OnUnmappable};
+use crate::error::SplitError;
+use crate::escapes::{self, SuspiciousEscape};
+use crate::include;
+use crate::long_path;
+use crate::manifest::{self, ManifestFormat};
+use crate::obfuscate::{self, Mask};
+#[cfg(feature = "parallel")]
+use crate::parallel;
+use crate::patterns::TablePatterns;
+use crate::permissions;
+use crate::progress::{EtaEstimator, Progress};
+use crate::references;
+use crate::report::{OutputFileStats, SplitReport, WarningKind};
+use crate::rows;
+use crate::scanner::{self, LineEndings, LocatedStatement, ScanStats, Scanner};
+use crate::semicolons;
+use crate::table::{self, Cursor};
+use crate::validate::{self, Malformed};
+use encoding_rs::Encoding;
+use futures::{Stream, StreamExt};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::process::Command;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Name of the file in the output directory recording completed batches.
+pub const STATE_FILE_NAME: &str = ".sql-splitter-state";
+
+/// Starts the text of the comment [`SqlSplitter::with_embed_checksum`] ends
+/// each file with; the hex digest follows.
+pub const CHECKSUM_LABEL: &str = "sha256: ";
+
+/// Zero-padding of file indices unless a run writes more than 999 files or
+/// [`SqlSplitter::with_index_width`] sets it.
+pub const DEFAULT_INDEX_WIDTH: usize = 3;
+
+/// Widest zero-padding accepted by [`SqlSplitter::with_index_width`].
+pub const MAX_INDEX_WIDTH: usize = 10;
+
+/// Subdirectory `--by-schema` routes statements without a schema-qualified
+/// table name to.
+pub const DEFAULT_SCHEMA_DIR: &str = "default";
+
+/// Statement boundary [`SqlSplitter::with_expand_go_count`] splits on
+/// unless a boundary regex is set: a T-SQL `GO` on a line of its own,
+/// optionally with a repeat count.
+pub const GO_BOUNDARY: &str = r"(?imR)(?:\A|\n)[ \t]*GO(?:[ \t]+[0-9]+)?[ \t]*$";
+
+/// Most times [`SqlSplitter::with_expand_go_count`] repeats one batch; a
+/// larger `GO N` fails the split rather than exhaust memory on copies.
+pub const MAX_GO_COUNT: usize = 10_000;
+
+/// Bytes of input `detect_dialect` samples.
+const DETECT_SAMPLE_BYTES: u64 = 1024 * 1024;
+
+/// Default size of the first read when scanning only a prefix of the input
+/// (for `preview` and `with_limit`); later reads double.
+pub const DEFAULT_PREFIX_READ_BYTES: usize = 256 * 1024;
+
+/// When to append `;` to a statement the input left unterminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TrailingDelimiter {
+    /// Terminate every statement, ending each output file with `;`.
+    Always,
+    /// Only emit `;` where the input had one.
+    Source,
+}
+
+/// How the comments the splitter writes itself (such as checksums) are
+/// delimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CommentStyle {
+    /// `-- text`, standard SQL.
+    #[default]
+    Dash,
+    /// `# text`, which only MySQL reads as a comment (re-split such files
+    /// with `--dialect mysql`).
+    Hash,
+    /// `/* text */`.
+    Block,
+}
+
+impl CommentStyle {
+    /// `text` as a comment in this style, without a trailing newline.
+    pub fn comment(self, text: &str) -> String {
+        match self {
+            CommentStyle::Dash => format!("-- {}", text.replace('\n', " ")),
+            CommentStyle::Hash => format!("# {}", text.replace('\n', " ")),
+            // A `*/` inside would close the comment early
+            CommentStyle::Block => format!("/* {} */", text.replace("*/", "* /")),
+        }
+    }
+
+    /// `statement` commented out in this style, keeping its lines: each
+    /// line that isn't blank behind `-- ` or `# `, or the whole of it in
+    /// one `/* */`.
+    pub fn comment_out(self, statement: &str) -> String {
+        let prefix = match self {
+            CommentStyle::Dash => "-- ",
+            CommentStyle::Hash => "# ",
+            // Postgres nests block comments, so a `/*` inside matters too
+            CommentStyle::Block => {
+                let escaped = statement.replace("*/", "* /").replace("/*", "/ *");
+                return format!("/* {} */", escaped);
+            }
+        };
+        statement
+            .split_inclusive('\n')
+            .map(|line| {
+                if line.trim().is_empty() {
+                    line.to_string()
+                } else {
+                    format!("{}{}", prefix, line)
+                }
+            })
+            .collect()
+    }
+}
+
+/// How `--dedupe` remembers the statements it has already kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dedupe {
+    /// Keeps every distinct normalized statement in memory.
+    Exact,
+    /// Keeps only a 64-bit hash per statement, bounding memory on huge
+    /// inputs at a vanishing risk of dropping a colliding statement.
+    Hash,
+}
+
+/// Statements already kept, as tracked under a [`Dedupe`] mode.
+enum Seen {
+    Exact(HashSet<String>),
+    Hash(HashSet<u64>),
+}
+
+impl Seen {
+    fn new(mode: Dedupe) -> Self {
+        match mode {
+            Dedupe::Exact => Seen::Exact(HashSet::new()),
+            Dedupe::Hash => Seen::Hash(HashSet::new()),
+        }
+    }
+
+    /// Records `statement`, returning false if it was seen before.
+    fn insert(&mut self, statement: &LocatedStatement) -> bool {
+        let mut key = normalize(&statement.text);
+        if let Some(data) = &statement.data {
+            key.push_str(data);
+        }
+        match self {
+            Seen::Exact(seen) => seen.insert(key),
+            Seen::Hash(seen) => {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                seen.insert(hasher.finish())
+            }
+        }
+    }
+}
+
+/// Limits and counts the output files a run has open at once.
+struct OpenFiles {
+    permits: Semaphore,
+    open: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl OpenFiles {
+    fn new(limit: Option<usize>) -> Self {
+        OpenFiles {
+            permits: Semaphore::new(limit.unwrap_or(Semaphore::MAX_PERMITS)),
+            open: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits until another file may be opened; it counts as open until the
+    /// returned guard is dropped.
+    async fn acquire(&self) -> OpenFile<'_> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+        let open = self.open.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(open, Ordering::SeqCst);
+        OpenFile {
+            files: self,
+            _permit: permit,
+        }
+    }
+}
+
+struct OpenFile<'a> {
+    files: &'a OpenFiles,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl Drop for OpenFile<'_> {
+    fn drop(&mut self) {
+        self.files.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A writer that discards its input, counting the bytes; clones share
+/// the count.
+#[derive(Debug, Clone, Default)]
+struct ByteCounter(Arc<AtomicU64>);
+
+impl AsyncWrite for ByteCounter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.0.fetch_add(buf.len() as u64, Ordering::SeqCst);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Where and how often [`SqlSplitter::with_manifest_flush`] rewrites the
+/// manifest during a run.
+#[derive(Debug, Clone)]
+struct ManifestFlush {
+    path: PathBuf,
+    format: ManifestFormat,
+    every: usize,
+    /// Files written for earlier inputs of the same run, listed first.
+    earlier: SplitReport,
+}
+
+/// The [`SqlSplitter::with_progress`] callback.
+#[derive(Clone)]
+struct ProgressHook(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook(..)")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SqlSplitter {
+    max_size: usize,
+    output_dir: PathBuf,
+    /// Directories sharing the output files with `output_dir`, round-robin.
+    spread_dirs: Vec<PathBuf>,
+    concurrent_writes: usize,
+    concurrent_reads: usize,
+    max_open_files: Option<usize>,
+    ignore: TablePatterns,
+    only_tables: Option<TablePatterns>,
+    keep_matching: Option<Regex>,
+    drop_matching: Option<Regex>,
+    match_normalized: bool,
+    source_map: Option<PathBuf>,
+    manifest_flush: Option<ManifestFlush>,
+    max_statement_bytes: Option<usize>,
+    compression: Compression,
+    compress_level: Option<i32>,
+    preserve_whitespace: bool,
+    trailing_delimiter: Option<TrailingDelimiter>,
+    split_by_type: bool,
+    by_schema: bool,
+    dedupe: Option<Dedupe>,
+    ascii: Option<AsciiMode>,
+    output_encoding: Option<&'static Encoding>,
+    on_unmappable: OnUnmappable,
+    strip_definer: bool,
+    table_prefix: Option<(String, String)>,
+    expand_includes: bool,
+    mask: Mask,
+    normalize_semicolons: bool,
+    keep_going: bool,
+    write_timeout: Option<Duration>,
+    progress: Option<ProgressHook>,
+    dry_run: bool,
+    done_marker: Option<PathBuf>,
+    dir_mode: Option<u32>,
+    file_mode: Option<u32>,
+    validate_sql: bool,
+    escape_validation: bool,
+    trailing_statement_warn: bool,
+    split_rows: bool,
+    max_rows_per_insert: Option<usize>,
+    replay_variables: bool,
+    add_drop_table: bool,
+    comment_out_drops: bool,
+    order_by_references: bool,
+    extract_header: bool,
+    trim_trailing_semicolons: bool,
+    final_newline: bool,
+    embed_checksum: bool,
+    comment_style: CommentStyle,
+    pipe_to: Option<String>,
+    fail_on_warnings: bool,
+    strict: bool,
+    index_width: Option<usize>,
+    limit: Option<usize>,
+    skip_statements: usize,
+    tail: Option<usize>,
+    prefix_read_bytes: usize,
+    /// Index of the first file written, above 1 when numbering continues
+    /// from an earlier input.
+    first_index: usize,
+    prefix_from_input: bool,
+    sanitize_filenames: bool,
+    line_endings: LineEndings,
+    boundary: Option<Regex>,
+    expand_go_count: bool,
+    newline_boundaries: bool,
+    output_ext: String,
+    balance_files: Option<usize>,
+    lookahead: usize,
+    group_transactions: bool,
+    resume: bool,
+    append: bool,
+    dialect: Dialect,
+    auto_dialect: bool,
+    #[cfg(feature = "parallel")]
+    parallel_scan: bool,
+}
+
+impl SqlSplitter {
+    pub fn new<P: AsRef<Path>>(
+        output_dir: P,
+        max_size_kb: usize,
+        concurrent_writes: usize,
+    ) -> Self {
+        SqlSplitter {
+            max_size: max_size_kb * 1024,
+            output_dir: output_dir.as_ref().to_path_buf(),
+            spread_dirs: Vec::new(),
+            concurrent_writes,
+            concurrent_reads: 1,
+            max_open_files: None,
+            ignore: TablePatterns::default(),
+            only_tables: None,
+            keep_matching: None,
+            drop_matching: None,
+            match_normalized: false,
+            source_map: None,
+            manifest_flush: None,
+            max_statement_bytes: None,
+            compression: Compression::None,
+            compress_level: None,
+            preserve_whitespace: false,
+            trailing_delimiter: None,
+            split_by_type: false,
+            by_schema: false,
+            dedupe: None,
+            ascii: None,
+            output_encoding: None,
+            on_unmappable: OnUnmappable::Replace,
+            strip_definer: false,
+            table_prefix: None,
+            expand_includes: false,
+            mask: Mask::default(),
+            normalize_semicolons: false,
+            keep_going: false,
+            write_timeout: None,
+            progress: None,
+            dry_run: false,
+            done_marker: None,
+            dir_mode: None,
+            file_mode: None,
+            validate_sql: false,
+            escape_validation: false,
+            trailing_statement_warn: false,
+            split_rows: false,
+            max_rows_per_insert: None,
+            replay_variables: false,
+            add_drop_table: false,
+            comment_out_drops: false,
+            order_by_references: false,
+            extract_header: false,
+            trim_trailing_semicolons: true,
+            final_newline: false,
+            embed_checksum: false,
+            comment_style: CommentStyle::Dash,
+            pipe_to: None,
+            fail_on_warnings: false,
+            strict: false,
+            index_width: None,
+            limit: None,
+            skip_statements: 0,
+            tail: None,
+            prefix_read_bytes: DEFAULT_PREFIX_READ_BYTES,
+            first_index: 1,
+            prefix_from_input: false,
+            sanitize_filenames: true,
+            line_endings: LineEndings::Keep,
+            boundary: None,
+            expand_go_count: false,
+            newline_boundaries: false,
+            output_ext: "sql".to_string(),
+            balance_files: None,
+            lookahead: 0,
+            group_transactions: false,
+            resume: false,
+            append: false,
+            dialect: Dialect::Generic,
+            auto_dialect: false,
+            #[cfg(feature = "parallel")]
+            parallel_scan: false,
+        }
+    }
+
+    /// Reads and scans up to `reads` inputs at once under
+    /// [`split_list`](Self::split_list) and [`split_dir`](Self::split_dir),
+    /// ahead of the input being written; output is the same as with one.
+    /// Each scanned input is held in memory until its turn to be written.
+    pub fn with_concurrent_reads(mut self, reads: usize) -> Self {
+        self.concurrent_reads = reads.max(1);
+        self
+    }
+
+    /// Writes one file at a time, in order, so every side effect of a run
+    /// (including the order of entries in the `--resume` state file) is
+    /// reproducible. Output file contents don't depend on scheduling either
+    /// way; this matters for features that accumulate state across files.
+    pub fn with_sequential(mut self, sequential: bool) -> Self {
+        if sequential {
+            self.concurrent_writes = 1;
+        }
+        self
+    }
+
+    /// Caps how many output files are open at once, independently of how
+    /// many writes are in flight: a write waits for a slot before creating
+    /// its file and holds it until the file (and any compression stream or
+    /// `--pipe-to` command fed from it) is finished.
+    pub fn with_max_open_files(mut self, limit: usize) -> Self {
+        self.max_open_files = Some(limit.max(1));
+        self
+    }
+
+    /// Caps each output file at `bytes` instead of the `max_size_kb` given
+    /// to [`new`](Self::new).
+    pub fn with_max_size(mut self, bytes: usize) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Drops statements whose target table matches any of `patterns`.
+    pub fn with_ignore_patterns(mut self, patterns: TablePatterns) -> Self {
+        self.ignore = patterns;
+        self
+    }
+
+    /// Writes a source map sidecar to `path` on each successful split.
+    pub fn with_source_map(mut self, path: impl AsRef<Path>) -> Self {
+        self.source_map = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Rewrites the manifest at `path` (see [`manifest`]) every
+    /// `every_files` completed files and once more as each split finishes,
+    /// so an interrupted run leaves a listing of the files it completed.
+    /// Snapshots list files in load order and each replaces the last
+    /// atomically.
+    pub fn with_manifest_flush(
+        mut self,
+        path: impl AsRef<Path>,
+        format: ManifestFormat,
+        every_files: usize,
+    ) -> Self {
+        self.manifest_flush = Some(ManifestFlush {
+            path: path.as_ref().to_path_buf(),
+            format,
+            every: every_files.max(1),
+            earlier: SplitReport::default(),
+        });
+        self
+    }
+
+    /// Rejects inputs containing a statement larger than `limit` bytes.
+    pub fn with_max_statement_bytes(mut self, limit: usize) -> Self {
+        self.max_statement_bytes = Some(limit);
+        self
+    }
+
+    /// Cuts multi-row `INSERT ... VALUES` statements and `COPY ... FROM
+    /// stdin` blocks larger than the file size limit into several, each
+    /// with its own header, so their rows spread across files instead of
+    /// one oversized file; see [`rows::chunk_rows`].
+    pub fn with_split_rows(mut self, split: bool) -> Self {
+        self.split_rows = split;
+        self
+    }
+
+    /// Cuts multi-row `INSERT ... VALUES` statements and `COPY ... FROM
+    /// stdin` blocks into pieces of at most `rows` rows.
+    pub fn with_max_rows_per_insert(mut self, rows: usize) -> Self {
+        self.max_rows_per_insert = Some(rows.max(1));
+        self
+    }
+
+    /// Compresses output files, optionally at a codec-specific `level`.
+    pub fn with_compression(mut self, compression: Compression, level: Option<i32>) -> Self {
+        self.compression = compression;
+        self.compress_level = level;
+        self
+    }
+
+    /// Keeps statements byte-for-byte instead of trimming them and joining
+    /// them with blank lines.
+    pub fn with_preserve_whitespace(mut self, preserve: bool) -> Self {
+        self.preserve_whitespace = preserve;
+        self
+    }
+
+    /// Splits only the first `limit` statements of the input (counted before
+    /// any filtering), reading no further into the file than they need.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Scans but discards the first `count` statements of the input
+    /// (counted before any filtering), as when restarting after a partial
+    /// load; the rest are written as usual, from the first file index. A
+    /// [`with_limit`](Self::with_limit) counts the statements after these.
+    pub fn with_skip_statements(mut self, count: usize) -> Self {
+        self.skip_statements = count;
+        self
+    }
+
+    /// Splits only the last `count` statements of the input (counted before
+    /// any filtering). The whole input is still read and scanned, but only
+    /// a ring of the latest `count` statements is kept while scanning, so
+    /// memory beyond the input text itself stays bounded by `count`. Takes
+    /// the place of [`with_limit`](Self::with_limit) and any parallel scan.
+    pub fn with_tail(mut self, count: usize) -> Self {
+        self.tail = Some(count);
+        self
+    }
+
+    /// Sets the size of the first read when only a prefix of the input is
+    /// scanned (see [`with_limit`](Self::with_limit) and
+    /// [`preview`](Self::preview)); later reads double. Output is the same
+    /// whatever the size; it only trades read calls against over-reading.
+    /// A full scan reads the whole input at once and doesn't use it.
+    pub fn with_prefix_read_size(mut self, bytes: usize) -> Self {
+        self.prefix_read_bytes = bytes.max(1);
+        self
+    }
+
+    /// Zero-pads file indices to `width` digits (clamped to 1..=10);
+    /// indices past that many digits still print in full. By default the
+    /// width is 3, widened as needed to the digits of the last index
+    /// written, so the names of one run all sort in numeric order.
+    pub fn with_index_width(mut self, width: usize) -> Self {
+        self.index_width = Some(width.clamp(1, MAX_INDEX_WIDTH));
+        self
+    }
+
+    /// Names output files `split_NNN.<ext>` instead of `split_NNN.sql`; any
+    /// compression suffix follows. A leading `.` is optional.
+    pub fn with_output_ext(mut self, ext: impl Into<String>) -> Self {
+        let ext = ext.into();
+        self.output_ext = ext.strip_prefix('.').map(str::to_string).unwrap_or(ext);
+        self
+    }
+
+    /// Sets when unterminated statements gain a `;`. Without an explicit
+    /// policy this is [`TrailingDelimiter::Source`] when whitespace is
+    /// preserved and [`TrailingDelimiter::Always`] otherwise.
+    pub fn with_trailing_delimiter(mut self, policy: TrailingDelimiter) -> Self {
+        self.trailing_delimiter = Some(policy);
+        self
+    }
+
+    fn trailing_delimiter(&self) -> TrailingDelimiter {
+        self.trailing_delimiter
+            .unwrap_or(if self.preserve_whitespace {
+                TrailingDelimiter::Source
+            } else {
+                TrailingDelimiter::Always
+            })
+    }
+
+    /// Distributes statements across exactly `num_files` files of similar
+    /// total size instead of cutting sequential batches at the size limit.
+    pub fn with_balance(mut self, num_files: usize) -> Self {
+        self.balance_files = Some(num_files.max(1));
+        self
+    }
+
+    /// Looks up to `statements` statements past each cut when batching by
+    /// size, moving statements back into a file the next cut would leave
+    /// underfilled (as when a slightly-too-large statement follows it).
+    /// Statement order and the file count are unchanged; files just come
+    /// out more even. 0, the default, batches greedily.
+    ///
+    /// Deciding a cut needs the sizes of the statements within the window,
+    /// so a streaming batcher would hold up to that many statements in
+    /// memory; here the whole input is in memory already, and the cost is
+    /// a little extra work per file.
+    pub fn with_lookahead(mut self, statements: usize) -> Self {
+        self.lookahead = statements;
+        self
+    }
+
+    /// Batches by whole transactions: a `BEGIN` (or `START TRANSACTION`)
+    /// and the statements through its `COMMIT` or `ROLLBACK` go to one
+    /// file together, packed with other transactions and standalone
+    /// statements up to the size limit. A transaction larger than the
+    /// limit gets a file of its own. Lookahead is ignored, and
+    /// [`with_balance`](Self::with_balance) takes precedence.
+    pub fn with_group_transactions(mut self, group: bool) -> Self {
+        self.group_transactions = group;
+        self
+    }
+
+    /// Skips batches that the state file of an interrupted run in the same
+    /// output directory records as complete. The input must be unchanged and
+    /// split with the same options so batch boundaries line up.
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Keeps writing the other output files when one fails, then fails
+    /// with [`SplitError::WritesFailed`] listing every file that did. The
+    /// state file is kept, so a [`with_resume`](Self::with_resume) rerun
+    /// writes just those.
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Fails an output file with [`SplitError::WriteTimeout`] if writing
+    /// it (and any [`with_pipe_to`](Self::with_pipe_to) command) takes
+    /// longer than `timeout` once it has an open-file slot, as when a
+    /// network filesystem hangs. Its partial file is left behind; with
+    /// [`with_keep_going`](Self::with_keep_going) the other files are still
+    /// written, and a [`with_resume`](Self::with_resume) rerun retries it.
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Calls `report` with the [`Progress`] of the split each time an
+    /// output file is written, its ETA extrapolated from the smoothed
+    /// throughput so far. Each input of a multi-input run reports from the
+    /// start; dry runs report nothing.
+    pub fn with_progress(mut self, report: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressHook(Arc::new(report)));
+        self
+    }
+
+    /// Numbers new files after the highest-numbered `split_NNN` file (of the
+    /// same name prefix and extension) already in the output directory, so
+    /// adding to an existing split set overwrites none of it.
+    pub fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Scans a fully-read input in segments on every core (see
+    /// [`parallel`](crate::parallel)), with the same result as a serial
+    /// scan. Ignored with a [`with_boundary_regex`](Self::with_boundary_regex)
+    /// terminator or [`with_newline_boundaries`](Self::with_newline_boundaries),
+    /// and when only a prefix is read.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_scan(mut self, parallel: bool) -> Self {
+        self.parallel_scan = parallel;
+        self
+    }
+
+    /// Scans with the rules of `dialect`.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Picks the dialect per input by sniffing for `DELIMITER` directives
+    /// and dollar quoting, overriding [`with_dialect`](Self::with_dialect).
+    pub fn with_auto_delimiter(mut self, auto: bool) -> Self {
+        self.auto_dialect = auto;
+        self
+    }
+
+    /// Drops statements identical to an earlier kept one once whitespace
+    /// outside string literals is collapsed. `COPY` data must match too.
+    pub fn with_dedupe(mut self, mode: Dedupe) -> Self {
+        self.dedupe = Some(mode);
+        self
+    }
+
+    /// Checks every statement, `COPY` data included, for non-ASCII
+    /// characters and handles them per `mode`.
+    pub fn with_ascii(mut self, mode: AsciiMode) -> Self {
+        self.ascii = Some(mode);
+        self
+    }
+
+    /// Writes statements, `COPY` data included, in `encoding` rather than
+    /// UTF-8; characters it lacks are handled per
+    /// [`with_on_unmappable`](Self::with_on_unmappable). Size limits still
+    /// count UTF-8 bytes, which single-byte encodings never exceed.
+    pub fn with_output_encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.output_encoding = Some(encoding);
+        self
+    }
+
+    /// What to do with characters the
+    /// [`with_output_encoding`](Self::with_output_encoding) encoding can't
+    /// represent: write `?` (the default), or fail with
+    /// [`SplitError::Unmappable`] before writing anything.
+    pub fn with_on_unmappable(mut self, policy: OnUnmappable) -> Self {
+        self.on_unmappable = policy;
+        self
+    }
+
+    /// Names the file [`mark_done`](Self::mark_done) creates in the output
+    /// directory to tell pollers the split is complete. Each split removes
+    /// it before writing, so it never sits beside a partial or failed run.
+    pub fn with_done_marker(mut self, name: impl AsRef<Path>) -> Self {
+        self.done_marker = Some(self.output_dir.join(name));
+        self
+    }
+
+    /// Spreads the output files across the output directory and `dirs`,
+    /// round-robin by index: with one extra directory, odd-numbered files
+    /// go to the output directory and even-numbered ones to `dirs[0]`. The
+    /// resume state, header, marker and source map stay in the output
+    /// directory; subdirectories are mirrored in each.
+    pub fn with_spread_dirs<P: AsRef<Path>>(mut self, dirs: impl IntoIterator<Item = P>) -> Self {
+        self.spread_dirs = dirs
+            .into_iter()
+            .map(|dir| dir.as_ref().to_path_buf())
+            .collect();
+        self
+    }
+
+    /// Gives the directories the split creates these permission bits (such
+    /// as `0o750`) instead of the umask's default.
+    #[cfg(unix)]
+    pub fn with_dir_mode(mut self, mode: u32) -> Self {
+        self.dir_mode = Some(mode);
+        self
+    }
+
+    /// Gives the files the split writes in the output directory these
+    /// permission bits (such as `0o640`) instead of the umask's default.
+    #[cfg(unix)]
+    pub fn with_file_mode(mut self, mode: u32) -> Self {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    /// Creates the empty [`with_done_marker`](Self::with_done_marker) file;
+    /// call it once everything the run produces (the manifest included) is
+    /// written. Does nothing without a marker, or in a dry run.
+    pub async fn mark_done(&self) -> Result<(), SplitError> {
+        match &self.done_marker {
+            Some(path) if !self.dry_run => {
+                fs::write(long_path::extended(path), "")
+                    .await
+                    .map_err(SplitError::at_path(path))?;
+                permissions::set_mode(path, self.file_mode)
+                    .await
+                    .map_err(SplitError::at_path(path))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Plans the split without writing any output files: the report (and
+    /// the manifest under [`with_manifest_flush`](Self::with_manifest_flush))
+    /// lists the files that would be written, with their projected sizes
+    /// after compression and statement counts.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Removes MySQL `DEFINER=user@host` clauses from `CREATE VIEW`,
+    /// `PROCEDURE`, `FUNCTION`, `TRIGGER` and `EVENT` statements, so they
+    /// load on a server without that account; see [`definer::strip_definer`].
+    pub fn with_strip_definer(mut self, strip: bool) -> Self {
+        self.strip_definer = strip;
+        self
+    }
+
+    /// Renames tables whose names start with `old` to start with `new`
+    /// instead, wherever statements refer to them; see
+    /// [`table::replace_table_prefix`].
+    pub fn with_replace_table_prefix(mut self, old: &str, new: &str) -> Self {
+        self.table_prefix = Some((old.to_string(), new.to_string()));
+        self
+    }
+
+    /// Collapses repeated `;`s (`;;`, `; ;`) left in statement text under a
+    /// `DELIMITER` or [`with_boundary_regex`](Self::with_boundary_regex)
+    /// into one, dropping statements left with nothing but a `;` as empty;
+    /// see [`semicolons::collapse_semicolons`].
+    pub fn with_normalize_semicolons(mut self, normalize: bool) -> Self {
+        self.normalize_semicolons = normalize;
+        self
+    }
+
+    /// Masks the contents of string literals in data statements (`INSERT`,
+    /// `UPDATE`, `COPY` data, ...) with `x`s of the same length, so a dump
+    /// can be shared for its shape; see [`obfuscate::obfuscate`]. Schema
+    /// statements keep their defaults and enum values.
+    pub fn with_obfuscate_strings(mut self, obfuscate: bool) -> Self {
+        self.mask.strings = obfuscate;
+        self
+    }
+
+    /// Masks the digits of numbers in data statements with `0`s, as
+    /// [`with_obfuscate_strings`](Self::with_obfuscate_strings) does strings.
+    pub fn with_obfuscate_numbers(mut self, obfuscate: bool) -> Self {
+        self.mask.numbers = obfuscate;
+        self
+    }
+
+    /// Inlines the files that psql `\i` and MySQL `source` directives in
+    /// input files name before scanning; see [`include::expand_includes`].
+    pub fn with_expand_includes(mut self, expand: bool) -> Self {
+        self.expand_includes = expand;
+        self
+    }
+
+    /// Runs [`validate::check`] on every statement, reporting any that
+    /// look malformed as warnings, or failing the split before anything is
+    /// written under [`with_strict`](Self::with_strict).
+    pub fn with_validate_sql(mut self, validate: bool) -> Self {
+        self.validate_sql = validate;
+        self
+    }
+
+    /// Runs [`escapes::check_escapes`] on every statement, reporting
+    /// backslashes that look like they desynced the scan, such as a `\'`
+    /// that kept a string open past where it was meant to end, as warnings
+    /// with their input lines.
+    pub fn with_escape_validation(mut self, validate: bool) -> Self {
+        self.escape_validation = validate;
+        self
+    }
+
+    /// Warns, with its line, when the input's last statement has no
+    /// delimiter, which often means the input was cut off; under
+    /// [`with_strict`](Self::with_strict) the split fails with
+    /// [`SplitError::UnterminatedLastStatement`] instead. Not checked with
+    /// [`with_newline_boundaries`](Self::with_newline_boundaries), where a
+    /// missing final newline is no sign of truncation.
+    pub fn with_trailing_statement_warn(mut self, warn: bool) -> Self {
+        self.trailing_statement_warn = warn;
+        self
+    }
+
+    /// Starts each output file with the `SET @...` variable assignments that
+    /// precede it in the input, so files load independently. The replayed
+    /// statements are not counted against the size limit.
+    pub fn with_replay_variables(mut self, replay: bool) -> Self {
+        self.replay_variables = replay;
+        self
+    }
+
+    /// Fails the split with [`SplitError::Warnings`] once it completes if
+    /// [`SplitReport::warnings`] reports anything. Output is still written.
+    pub fn with_fail_on_warnings(mut self, strict: bool) -> Self {
+        self.fail_on_warnings = strict;
+        self
+    }
+
+    /// Fails the split with [`SplitError::Unclosed`], before writing any
+    /// output, if the input ends inside a string literal, quoted identifier
+    /// or comment, or with parentheses open. Otherwise the last statement is
+    /// kept as it is and the constructs are reported as warnings. Under
+    /// [`with_validate_sql`](Self::with_validate_sql) it also fails with
+    /// [`SplitError::Malformed`] on suspicious statements, and under
+    /// [`with_trailing_statement_warn`](Self::with_trailing_statement_warn)
+    /// with [`SplitError::UnterminatedLastStatement`] on an unterminated
+    /// last statement.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Also pipes each batch, as written, into `command` (run through the
+    /// shell, one process per file, within the concurrent-write limit). Any
+    /// command exiting unsuccessfully fails the run.
+    pub fn with_pipe_to(mut self, command: impl Into<String>) -> Self {
+        self.pipe_to = Some(command.into());
+        self
+    }
+
+    /// Skips the `;` normally written after a statement whose text already
+    /// ends with one, so each statement gets exactly one terminator. On by
+    /// default.
+    pub fn with_trim_trailing_semicolons(mut self, trim: bool) -> Self {
+        self.trim_trailing_semicolons = trim;
+        self
+    }
+
+    /// Ends each output file with `\n-- sha256: <hex>\n`, the SHA-256 of
+    /// everything before that leading `\n` (before any compression), so a
+    /// file can be checked against its own contents. The comment counts
+    /// against the size limit.
+    pub fn with_embed_checksum(mut self, embed: bool) -> Self {
+        self.embed_checksum = embed;
+        self
+    }
+
+    /// Sets how comments the splitter adds to output files are written
+    /// (`--` by default).
+    pub fn with_comment_style(mut self, style: CommentStyle) -> Self {
+        self.comment_style = style;
+        self
+    }
+
+    /// Ends each output file with a `\n` after the last statement's
+    /// terminator, counted against the size limit. Off by default, so files
+    /// end at the terminator.
+    pub fn with_final_newline(mut self, newline: bool) -> Self {
+        self.final_newline = newline;
+        self
+    }
+
+    /// Moves the comments leading the input's first statement (a dump's
+    /// banner) into `header.<ext>` instead of the first output file.
+    pub fn with_extract_header(mut self, extract: bool) -> Self {
+        self.extract_header = extract;
+        self
+    }
+
+    /// Precedes each `CREATE TABLE` with `DROP TABLE IF EXISTS` for the same
+    /// table, so the output can be reloaded over an existing schema.
+    pub fn with_add_drop_table(mut self, add: bool) -> Self {
+        self.add_drop_table = add;
+        self
+    }
+
+    /// Writes each `DROP` statement commented out in the
+    /// [`with_comment_style`](Self::with_comment_style) style, without its
+    /// terminator or `DELIMITER` wrapper, so the output can't destroy data
+    /// if run by accident but still shows the drops for review.
+    pub fn with_comment_out_drops(mut self, comment_out: bool) -> Self {
+        self.comment_out_drops = comment_out;
+        self
+    }
+
+    /// Names output files after the input's file stem, e.g.
+    /// `mydump_split_001.sql` for `mydump.sql`, so the splits of several
+    /// inputs can share a directory.
+    pub fn with_prefix_from_input(mut self, prefix: bool) -> Self {
+        self.prefix_from_input = prefix;
+        self
+    }
+
+    /// Replaces characters that some filesystems reject (`\ / : * ? " < >
+    /// |`, control characters, undecodable bytes) in names derived from the
+    /// input's file name, and strips trailing dots and spaces, which Windows
+    /// drops. On by default; turn off to keep input names exactly as they are.
+    pub fn with_sanitize_filenames(mut self, sanitize: bool) -> Self {
+        self.sanitize_filenames = sanitize;
+        self
+    }
+
+    /// Moves each `CREATE TABLE`, with the statements on its table, after
+    /// the tables it references; see [`references::order_by_references`].
+    /// Tables forced out of order by a reference cycle are reported as a
+    /// warning.
+    pub fn with_reference_order(mut self, order: bool) -> Self {
+        self.order_by_references = order;
+        self
+    }
+
+    /// Rewrites `\r\n` line endings to `\n`; see [`LineEndings`] for
+    /// whether string literals and `COPY` data are included.
+    pub fn with_line_endings(mut self, line_endings: LineEndings) -> Self {
+        self.line_endings = line_endings;
+        self
+    }
+
+    /// Ends statements where `boundary` matches outside strings and comments
+    /// instead of at `;`, re-emitting the matched text as the terminator.
+    /// See [`Scanner::boundary`] for the performance cost.
+    pub fn with_boundary_regex(mut self, boundary: Regex) -> Self {
+        self.boundary = Some(boundary);
+        self
+    }
+
+    /// Repeats each statement whose [`with_boundary_regex`](Self::with_boundary_regex)
+    /// terminator is a T-SQL `GO N` the `N` times that asks for, each copy
+    /// ended by a plain `GO`. A boundary regex given must match the count
+    /// too, as `\nGO( [0-9]+)?\n` does; without one, statements end at
+    /// [`GO_BOUNDARY`]. Copies are made before filtering, so
+    /// [`with_dedupe`](Self::with_dedupe) drops them again.
+    pub fn with_expand_go_count(mut self, expand: bool) -> Self {
+        self.expand_go_count = expand;
+        self
+    }
+
+    /// Lets a newline end a statement that looks complete when the next
+    /// line starts another, for inputs with missing semicolons. Best-effort:
+    /// see [`Scanner::newline_boundaries`].
+    pub fn with_newline_boundaries(mut self, enabled: bool) -> Self {
+        self.newline_boundaries = enabled;
+        self
+    }
+
+    /// Routes statements into `ddl/`, `dml/` and `other/` subdirectories of
+    /// the output directory by leading keyword, batching each separately.
+    pub fn with_split_by_type(mut self, split_by_type: bool) -> Self {
+        self.split_by_type = split_by_type;
+        self
+    }
+
+    /// Routes statements into a subdirectory per schema, named after the
+    /// schema qualifying their table (`sales.orders` goes to `sales/`,
+    /// lowercased), batching each separately. Statements without a qualified
+    /// table go to [`DEFAULT_SCHEMA_DIR`]. With
+    /// [`with_split_by_type`](Self::with_split_by_type) as well, each schema
+    /// directory is divided by type in turn.
+    pub fn with_by_schema(mut self, by_schema: bool) -> Self {
+        self.by_schema = by_schema;
+        self
+    }
+
+    /// Keeps only statements targeting a table matching `tables`, plus
+    /// statements with no target table (the `SET`/`USE` prelude and the like).
+    pub fn with_table_filter(mut self, tables: TablePatterns) -> Self {
+        self.only_tables = Some(tables);
+        self
+    }
+
+    /// Keeps only statements whose text `pattern` matches. Applied after
+    /// the table filters and before
+    /// [`with_drop_matching`](Self::with_drop_matching), so a statement is
+    /// kept when it matches this and not that.
+    pub fn with_keep_matching(mut self, pattern: Regex) -> Self {
+        self.keep_matching = Some(pattern);
+        self
+    }
+
+    /// Drops statements whose text `pattern` matches, after
+    /// [`with_keep_matching`](Self::with_keep_matching).
+    pub fn with_drop_matching(mut self, pattern: Regex) -> Self {
+        self.drop_matching = Some(pattern);
+        self
+    }
+
+    /// Matches the [`with_keep_matching`](Self::with_keep_matching) and
+    /// [`with_drop_matching`](Self::with_drop_matching) patterns against
+    /// statements trimmed, with whitespace runs outside strings collapsed to
+    /// one space (as `--dedupe` compares them), so `^CREATE TABLE` matches
+    /// however the dump laid the statement out.
+    pub fn with_match_normalized(mut self, normalized: bool) -> Self {
+        self.match_normalized = normalized;
+        self
+    }
+
+    /// `statement`, or the pieces [`with_split_rows`](Self::with_split_rows)
+    /// and [`with_max_rows_per_insert`](Self::with_max_rows_per_insert) cut
+    /// it into.
+    fn chunk_rows(&self, statement: LocatedStatement) -> Vec<LocatedStatement> {
+        let max_bytes =
+            (self.split_rows && self.balance_files.is_none()).then_some(self.statement_budget());
+        if max_bytes.is_none() && self.max_rows_per_insert.is_none() {
+            return vec![statement];
+        }
+        rows::chunk_rows(&statement, self.max_rows_per_insert, max_bytes)
+            .unwrap_or_else(|| vec![statement])
+    }
+
+    /// A scanner over `content` configured with this splitter's options.
+    fn scanner(&self, content: impl Into<String>) -> Scanner {
+        let content = content.into();
+        let dialect = self.scan_dialect(&content);
+        self.scanner_with_dialect(content, dialect)
+    }
+
+    /// The dialect to scan `content` with: the configured one, or the one
+    /// sniffed from it under `--auto-delimiter`.
+    fn scan_dialect(&self, content: &str) -> Dialect {
+        if self.auto_dialect {
+            Dialect::sniff(content)
+        } else {
+            self.dialect
+        }
+    }
+
+    fn scanner_with_dialect(&self, content: impl Into<String>, dialect: Dialect) -> Scanner {
+        let scanner = Scanner::new(content)
+            .preserve_whitespace(self.preserve_whitespace)
+            .dialect(dialect)
+            .line_endings(self.line_endings)
+            .newline_boundaries(self.newline_boundaries)
+            .count_literals(true);
+        match self.boundary() {
+            Some(boundary) => scanner.boundary(boundary),
+            None => scanner,
+        }
+    }
+
+    /// The regex ending statements in place of `;`: the configured one, or
+    /// [`GO_BOUNDARY`] under [`with_expand_go_count`](Self::with_expand_go_count).
+    fn boundary(&self) -> Option<&Regex> {
+        static GO: OnceLock<Regex> = OnceLock::new();
+        self.boundary.as_ref().or_else(|| {
+            self.expand_go_count
+                .then(|| GO.get_or_init(|| Regex::new(GO_BOUNDARY).unwrap()))
+        })
+    }
+
+    fn is_excluded(&self, statement: &str) -> bool {
+        if self.ignore.is_empty() && self.only_tables.is_none() {
+            return false;
+        }
+        let Some(name) = table::table_name(statement) else {
+            return false;
+        };
+        self.ignore.matches(&name)
+            || self
+                .only_tables
+                .as_ref()
+                .is_some_and(|tables| !tables.matches(&name))
+    }
+
+    /// Whether the `--keep-matching`/`--drop-matching` patterns drop
+    /// `statement`.
+    fn is_unmatched(&self, statement: &str) -> bool {
+        if self.keep_matching.is_none() && self.drop_matching.is_none() {
+            return false;
+        }
+        let text = if self.match_normalized {
+            Cow::Owned(normalize(statement))
+        } else {
+            Cow::Borrowed(statement)
+        };
+        self.keep_matching
+            .as_ref()
+            .is_some_and(|keep| !keep.is_match(&text))
+            || self
+                .drop_matching
+                .as_ref()
+                .is_some_and(|drop| drop.is_match(&text))
+    }
+
+    pub fn split_statements(content: &str) -> Vec<String> {
+        scanner::split_statements(content)
+    }
+
+    async fn write_sql_file(
+        &self,
+        index: usize,
+        statements: Vec<LocatedStatement>,
+        output_path: PathBuf,
+        open_files: &OpenFiles,
+    ) -> Result<Duration, SplitError> {
+        let _open = open_files.acquire().await;
+        let start = Instant::now();
+        self.within_write_timeout(index, &output_path, async {
+            self.write_open_file(&statements, &output_path).await?;
+            let elapsed = start.elapsed();
+            if let Some(command) = &self.pipe_to {
+                self.pipe_statements(command, &statements, &output_path)
+                    .await?;
+            }
+            Ok(elapsed)
+        })
+        .await
+    }
+
+    /// Runs `write`, the writing of output file `index` at `path`, failing
+    /// with [`SplitError::WriteTimeout`] if it outlasts the
+    /// [`with_write_timeout`](Self::with_write_timeout) limit.
+    async fn within_write_timeout<T>(
+        &self,
+        index: usize,
+        path: &Path,
+        write: impl std::future::Future<Output = Result<T, SplitError>>,
+    ) -> Result<T, SplitError> {
+        let Some(timeout) = self.write_timeout else {
+            return write.await;
+        };
+        tokio::time::timeout(timeout, write)
+            .await
+            .unwrap_or_else(|_| {
+                Err(SplitError::WriteTimeout {
+                    index,
+                    path: path.to_path_buf(),
+                    timeout,
+                })
+            })
+    }
+
+    /// Writes `statements` to `output_path` under a temporary name, renaming
+    /// it into place once complete.
+    async fn write_open_file(
+        &self,
+        statements: &[LocatedStatement],
+        output_path: &Path,
+    ) -> Result<(), SplitError> {
+        // Write under a temporary name so a file only appears once complete
+        let mut partial = output_path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let file = File::create(long_path::extended(&partial))
+            .await
+            .map_err(SplitError::at_path(&partial))?;
+        permissions::set_mode(&partial, self.file_mode)
+            .await
+            .map_err(SplitError::at_path(&partial))?;
+        self.write_content(statements, BufWriter::new(file)).await?;
+        fs::rename(
+            long_path::extended(&partial),
+            long_path::extended(output_path),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Writes the content of an output file holding `statements` to
+    /// `inner`, compressed and checksummed as configured.
+    async fn write_content<W>(
+        &self,
+        statements: &[LocatedStatement],
+        inner: W,
+    ) -> Result<(), SplitError>
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut writer = self.compression.encoder(inner, self.compress_level);
+        if self.embed_checksum {
+            // Buffered to hash the exact bytes written
+            let mut content = Vec::new();
+            self.write_statements(statements, &mut content).await?;
+            writer.write_all(&content).await?;
+            let digest = format!("{:x}", Sha256::digest(&content));
+            writer
+                .write_all(self.checksum_comment(&digest).as_bytes())
+                .await?;
+        } else {
+            self.write_statements(statements, &mut writer).await?;
+        }
+        // Shutdown finishes the compressed stream before flushing the file
+        writer.shutdown().await?;
+        Ok(())
+    }
+
+    /// The line [`with_embed_checksum`](Self::with_embed_checksum) ends a
+    /// file with for the hex `digest`, with the newline before it.
+    fn checksum_comment(&self, digest: &str) -> String {
+        let text = format!("{}{}", CHECKSUM_LABEL, digest);
+        format!("\n{}\n", self.comment_style.comment(&text))
+    }
+
+    /// The bytes of statements a file may hold: the size limit less what
+    /// is written after them, so the whole file stays within the limit.
+    fn statement_budget(&self) -> usize {
+        let mut reserved = usize::from(self.final_newline);
+        if self.embed_checksum {
+            // A SHA-256 digest is always 64 hex digits
+            reserved += self.checksum_comment(&"0".repeat(64)).len();
+        }
+        self.max_size.saturating_sub(reserved)
+    }
+
+    /// The size an output file holding `statements` would have on disk,
+    /// found by writing it to a counter instead.
+    async fn projected_size(&self, statements: &[LocatedStatement]) -> Result<u64, SplitError> {
+        let counter = ByteCounter::default();
+        self.write_content(statements, counter.clone()).await?;
+        Ok(counter.0.load(Ordering::SeqCst))
+    }
+
+    /// Feeds `statements` to `command`, run through the shell with
+    /// `SQL_SPLITTER_FILE` naming the file they were written to.
+    async fn pipe_statements(
+        &self,
+        command: &str,
+        statements: &[LocatedStatement],
+        output_path: &Path,
+    ) -> Result<(), SplitError> {
+        // Killed if a --write-timeout gives up on the file
+        let mut child = shell_command(command)
+            .env("SQL_SPLITTER_FILE", output_path)
+            .stdin(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        self.write_statements(statements, &mut stdin).await?;
+        stdin.shutdown().await?;
+        drop(stdin);
+
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(SplitError::CommandFailed {
+                command: command.to_string(),
+                file: output_path.to_path_buf(),
+                status,
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes `statements` to `writer` in output form: separators, each
+    /// statement's terminator and any `COPY` data.
+    async fn write_statements<W: AsyncWrite + Unpin + ?Sized>(
+        &self,
+        statements: &[LocatedStatement],
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        let policy = self.trailing_delimiter();
+        for (i, statement) in statements.iter().enumerate() {
+            if i > 0 && !self.preserve_whitespace {
+                writer.write_all(STATEMENT_SEPARATOR.as_bytes()).await?;
+            }
+            if self.comment_out_drops && category::is_drop(&statement.text) {
+                let mut commented = self.comment_style.comment_out(&statement.text);
+                // Keep whatever follows out of a trailing line comment
+                if self.preserve_whitespace && !commented.ends_with(['\n', '/']) {
+                    commented.push('\n');
+                }
+                writer.write_all(&self.encode(&commented)).await?;
+                continue;
+            }
+            match (&statement.delimiter, &statement.boundary) {
+                // Re-emit the directive so the file loads in the mysql client
+                (Some(delimiter), _) => {
+                    let wrapped = format!(
+                        "DELIMITER {}\n{}{}\nDELIMITER ;",
+                        delimiter, statement.text, delimiter
+                    );
+                    writer.write_all(&self.encode(&wrapped)).await?;
+                }
+                (None, Some(boundary)) => {
+                    writer.write_all(&self.encode(&statement.text)).await?;
+                    writer.write_all(&self.encode(boundary)).await?;
+                }
+                (None, None) => {
+                    writer.write_all(&self.encode(&statement.text)).await?;
+                    let has_terminator = self.trim_trailing_semicolons
+                        && scanner::ends_with_semicolon(&statement.text, self.dialect);
+                    if !has_terminator
+                        && (statement.terminated || policy == TrailingDelimiter::Always)
+                    {
+                        // Untrimmed text cut off by the end of input may end
+                        // in a comment that would swallow the `;`
+                        if !statement.terminated
+                            && scanner::ends_in_line_comment(&statement.text, self.dialect)
+                        {
+                            writer.write_all(b"\n").await?;
+                        }
+                        writer.write_all(b";").await?;
+                    }
+                }
+            }
+            // COPY data goes out as read, through its `\.` line: the
+            // terminator logic above applies only to the statement text
+            if let Some(data) = &statement.data {
+                writer.write_all(&self.encode(data)).await?;
+            }
+        }
+        if self.final_newline && !statements.is_empty() {
+            writer.write_all(b"\n").await?;
+        }
+        Ok(())
+    }
+
+    /// `text` as bytes in the output encoding.
+    fn encode<'a>(&self, text: &'a str) -> Cow<'a, [u8]> {
+        match self.output_encoding {
+            Some(encoding) => encoding::encode(text, encoding),
+            None => Cow::Borrowed(text.as_bytes()),
+        }
+    }
+
+    /// Writes `file<TAB>statement<TAB>line` rows (statement indices are 1-based
+    /// within each file) mapping output statements back to the input.
+    /// With `append`, rows are added to an existing map (as when numbering
+    /// continues across inputs) instead of starting a new one.
+    async fn write_source_map(
+        path: &Path,
+        file_names: &[String],
+        batches: &[Vec<LocatedStatement>],
+        append: bool,
+    ) -> Result<(), std::io::Error> {
+        let mut map = if append {
+            String::new()
+        } else {
+            String::from("file\tstatement\tline\n")
+        };
+        for (name, batch) in file_names.iter().zip(batches) {
+            for (i, statement) in batch.iter().enumerate() {
+                map.push_str(&format!("{}\t{}\t{}\n", name, i + 1, statement.line));
+            }
+        }
+        if !append {
+            return fs::write(long_path::extended(path), map).await;
+        }
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(long_path::extended(path))
+            .await?;
+        file.write_all(map.as_bytes()).await?;
+        file.flush().await
+    }
+
+    /// Splits `input_file` into the output directory; a convenience wrapper
+    /// around [`split_reader`](Self::split_reader) that also names files
+    /// after the input under [`with_prefix_from_input`](Self::with_prefix_from_input).
+    pub async fn split_file(
+        &self,
+        input_file: impl AsRef<Path>,
+    ) -> Result<SplitReport, SplitError> {
+        let name_prefix = self.name_prefix(input_file.as_ref());
+        let scanned = self.scan_file(input_file.as_ref()).await?;
+        self.split_scanned(scanned, &name_prefix).await
+    }
+
+    /// The prefix of output file names for `input_file`: its stem under
+    /// [`with_prefix_from_input`](Self::with_prefix_from_input), else none.
+    fn name_prefix(&self, input_file: &Path) -> String {
+        match input_file.file_stem() {
+            Some(stem) if self.prefix_from_input => {
+                let stem = stem.to_string_lossy();
+                if self.sanitize_filenames {
+                    format!("{}_", sanitize_file_name(&stem))
+                } else {
+                    format!("{}_", stem)
+                }
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Splits SQL read from `reader` (a socket, pipe or in-memory buffer)
+    /// into the output directory. Without a limit the input is read to the
+    /// end before anything is written; it must be UTF-8.
+    pub async fn split_reader<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<SplitReport, SplitError> {
+        self.split_from(reader, "").await
+    }
+
+    /// Splits the `INSERT INTO table` statements generated from the CSV file
+    /// `input_file` (see [`csv_insert::csv_to_inserts`]), one per record,
+    /// as if they were the input; file names follow the input as in
+    /// [`split_file`](Self::split_file).
+    pub async fn split_csv(
+        &self,
+        input_file: impl AsRef<Path>,
+        table: &str,
+    ) -> Result<SplitReport, SplitError> {
+        let name_prefix = self.name_prefix(input_file.as_ref());
+        let csv = fs::read(input_file).await?;
+        let script = csv_insert::csv_to_inserts(csv.as_slice(), table, self.dialect)?;
+        self.split_from(script.as_bytes(), &name_prefix).await
+    }
+
+    async fn split_from<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        name_prefix: &str,
+    ) -> Result<SplitReport, SplitError> {
+        let scanned = self.scan_input(reader).await?;
+        self.split_scanned(scanned, name_prefix).await
+    }
+
+    /// Reads and scans `reader`, as far as [`with_limit`](Self::with_limit)
+    /// needs. A full scan runs on the blocking pool, off the async workers.
+    async fn scan_input<R: AsyncRead + Unpin>(&self, mut reader: R) -> Result<Scanned, SplitError> {
+        let (statements, stats, input_bytes) = match self.limit.filter(|_| self.tail.is_none()) {
+            Some(limit) => {
+                self.scan_prefix(reader, limit.saturating_add(self.skip_statements))
+                    .await?
+            }
+            None => {
+                // Read the entire input
+                let mut content = String::new();
+                reader.read_to_string(&mut content).await?;
+                let input_bytes = content.len();
+                let splitter = self.clone();
+                let (statements, stats) =
+                    match tokio::task::spawn_blocking(move || splitter.scan_all(content)).await {
+                        Ok(scan) => scan,
+                        Err(e) => std::panic::resume_unwind(e.into_panic()),
+                    };
+                (statements, stats, input_bytes)
+            }
+        };
+        Ok(Scanned {
+            statements,
+            stats,
+            input_bytes,
+        })
+    }
+
+    /// [`scan_input`](Self::scan_input) on `input_file`, run as a task of
+    /// its own so it progresses while the caller writes an earlier input.
+    fn spawn_scan(
+        &self,
+        input_file: PathBuf,
+    ) -> tokio::task::JoinHandle<Result<Scanned, SplitError>> {
+        let splitter = self.clone();
+        tokio::spawn(async move { splitter.scan_file(&input_file).await })
+    }
+
+    /// [`scan_input`](Self::scan_input) on `input_file`, with its includes
+    /// expanded under [`with_expand_includes`](Self::with_expand_includes).
+    async fn scan_file(&self, input_file: &Path) -> Result<Scanned, SplitError> {
+        if !self.expand_includes {
+            return self.scan_input(File::open(input_file).await?).await;
+        }
+        let expanded = self.read_expanded(input_file).await?;
+        self.scan_input(expanded.as_bytes()).await
+    }
+
+    /// Reads `input_file`, with its includes expanded under
+    /// [`with_expand_includes`](Self::with_expand_includes).
+    async fn read_expanded(&self, input_file: &Path) -> Result<String, SplitError> {
+        let content = fs::read_to_string(input_file).await?;
+        if !self.expand_includes {
+            return Ok(content);
+        }
+        let input_file = input_file.to_path_buf();
+        let dialect = self.dialect;
+        match tokio::task::spawn_blocking(move || {
+            include::expand_includes(&input_file, &content, dialect)
+        })
+        .await
+        {
+            Ok(expanded) => expanded,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Filters, transforms and writes the statements of a scanned input.
+    async fn split_scanned(
+        &self,
+        scanned: Scanned,
+        name_prefix: &str,
+    ) -> Result<SplitReport, SplitError> {
+        if self.output_ext.contains(['/', '\\']) {
+            return Err(SplitError::InvalidOutputExt(self.output_ext.clone()));
+        }
+
+        // Create output directory if it doesn't exist
+        if !self.dry_run {
+            self.create_output_dirs().await?;
+            if let Some(marker) = &self.done_marker {
+                match fs::remove_file(long_path::extended(marker)).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(SplitError::at_path(marker)(e))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let Scanned {
+            mut statements,
+            stats,
+            input_bytes,
+        } = scanned;
+        let mut report = SplitReport {
+            input_bytes,
+            ..SplitReport::default()
+        };
+        report.record_scan(&stats);
+        statements.drain(..self.skip_statements.min(statements.len()));
+        if self.strict && !stats.unclosed.is_empty() {
+            return Err(SplitError::Unclosed(stats.unclosed));
+        }
+        if self.trailing_statement_warn && !self.newline_boundaries {
+            if let Some(last) = statements.last().filter(|last| !last.terminated) {
+                if self.strict {
+                    return Err(SplitError::UnterminatedLastStatement { line: last.line });
+                }
+                report.warn(
+                    WarningKind::UnterminatedLastStatement,
+                    Some(last.line),
+                    format!(
+                        "the last statement, starting on line {}, has no delimiter (truncated input?)",
+                        last.line
+                    ),
+                );
+            }
+        }
+        if let Some(mode) = self.ascii {
+            for statement in &mut statements {
+                check_ascii(mode, statement, &mut report)?;
+            }
+        }
+        if self.strip_definer {
+            for statement in &mut statements {
+                if let Some(text) = definer::strip_definer(&statement.text) {
+                    statement.text = text;
+                    report.warn(
+                        WarningKind::StrippedDefiner,
+                        Some(statement.line),
+                        format!("stripped the DEFINER clause on line {}", statement.line),
+                    );
+                }
+            }
+        }
+        if self.normalize_semicolons {
+            for statement in &mut statements {
+                if let Some(text) = semicolons::collapse_semicolons(&statement.text, self.dialect) {
+                    statement.text = text;
+                }
+            }
+            let before = statements.len();
+            statements.retain(|statement| {
+                !matches!(statement.text.trim(), "" | ";") || statement.data.is_some()
+            });
+            report.empty_segments += before - statements.len();
+        }
+        if !self.mask.is_empty() {
+            for statement in &mut statements {
+                if category::categorize(&statement.text) != Category::Dml {
+                    continue;
+                }
+                statement.text = obfuscate::obfuscate(&statement.text, self.dialect, self.mask);
+                if let Some(data) = &mut statement.data {
+                    *data = obfuscate::obfuscate_copy_data(data, self.mask);
+                }
+            }
+        }
+        if let Some((old, new)) = &self.table_prefix {
+            for statement in &mut statements {
+                if let Some(text) = table::replace_table_prefix(&statement.text, old, new) {
+                    statement.text = text;
+                }
+            }
+        }
+        if self.validate_sql {
+            report.malformed = statements
+                .iter()
+                .filter_map(|statement| {
+                    let problem = validate::check(&statement.text, self.dialect)?;
+                    Some(Malformed {
+                        line: statement.line,
+                        problem,
+                    })
+                })
+                .collect();
+            if self.strict && !report.malformed.is_empty() {
+                return Err(SplitError::Malformed(report.malformed));
+            }
+            for malformed in report.malformed.clone() {
+                report.warn(
+                    WarningKind::Malformed,
+                    Some(malformed.line),
+                    malformed.to_string(),
+                );
+            }
+        }
+        if self.escape_validation {
+            report.suspicious_escapes = statements
+                .iter()
+                .filter_map(|statement| {
+                    let (lines, problem) = escapes::check_escapes(&statement.text, self.dialect)?;
+                    Some(SuspiciousEscape {
+                        line: statement.line + lines,
+                        problem,
+                    })
+                })
+                .collect();
+            for escape in report.suspicious_escapes.clone() {
+                report.warn(
+                    WarningKind::SuspiciousEscape,
+                    Some(escape.line),
+                    escape.to_string(),
+                );
+            }
+        }
+
+        if self.expand_go_count {
+            statements = expand_go_counts(statements)?;
+        }
+
+        if self.extract_header {
+            if let Some(first) = statements.first_mut() {
+                let header = split_header(first);
+                if !header.is_empty() && !self.dry_run {
+                    let path = self.output_dir.join(format!("header.{}", self.output_ext));
+                    fs::write(long_path::extended(&path), header)
+                        .await
+                        .map_err(SplitError::at_path(&path))?;
+                    permissions::set_mode(&path, self.file_mode)
+                        .await
+                        .map_err(SplitError::at_path(&path))?;
+                }
+            }
+        }
+
+        let mut seen = self.dedupe.map(Seen::new);
+        let mut kept = Vec::new();
+        for statement in statements {
+            if self.is_excluded(&statement.text) || self.is_unmatched(&statement.text) {
+                continue;
+            }
+            if let Some(seen) = &mut seen {
+                if !seen.insert(&statement) {
+                    report.duplicate_statements += 1;
+                    continue;
+                }
+            }
+            for statement in self.chunk_rows(statement) {
+                if let Some(limit) = self.max_statement_bytes {
+                    if statement.output_len() > limit {
+                        return Err(SplitError::StatementTooLarge {
+                            line: statement.line,
+                            size: statement.output_len(),
+                            limit,
+                        });
+                    }
+                }
+                if self.add_drop_table {
+                    if let Some(table) = table::created_table(&statement.text) {
+                        kept.push(LocatedStatement {
+                            text: format!("DROP TABLE IF EXISTS {}", table),
+                            line: statement.line,
+                            data: None,
+                            delimiter: None,
+                            boundary: statement.boundary.clone(),
+                            terminated: true,
+                        });
+                    }
+                }
+                kept.push(statement);
+            }
+        }
+        if self.order_by_references {
+            let (ordered, cycles) = references::order_by_references(kept);
+            kept = ordered;
+            report.reference_cycles += cycles;
+            if cycles > 0 {
+                report.warn(
+                    WarningKind::ReferenceCycle,
+                    None,
+                    format!(
+                        "{} table(s) in a foreign key cycle were created before a table they reference",
+                        cycles
+                    ),
+                );
+            }
+        }
+        if let Some(encoding) = self.output_encoding {
+            if self.on_unmappable == OnUnmappable::Error {
+                for statement in &kept {
+                    check_encodable(statement, encoding)?;
+                }
+            }
+        }
+        for statement in &kept {
+            report.record_statement(statement);
+            if self.balance_files.is_none() && statement.output_len() > self.statement_budget() {
+                report.oversized_statements += 1;
+                report.warn(
+                    WarningKind::OversizedStatement,
+                    Some(statement.line),
+                    format!(
+                        "statement on line {} is {} bytes, over the {} byte file size limit",
+                        statement.line,
+                        statement.output_len(),
+                        self.max_size
+                    ),
+                );
+            }
+        }
+
+        let variables: Vec<LocatedStatement> = if self.replay_variables {
+            kept.iter()
+                .filter(|statement| category::is_variable_assignment(&statement.text))
+                .cloned()
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        if !self.split_by_type && !self.by_schema {
+            self.write_batches(kept, &variables, name_prefix, &mut report)
+                .await?;
+            return self.check_warnings(report);
+        }
+        let mut groups: BTreeMap<PathBuf, Vec<LocatedStatement>> = BTreeMap::new();
+        for statement in kept {
+            groups
+                .entry(self.subdir(&statement.text))
+                .or_default()
+                .push(statement);
+        }
+        for (subdir, statements) in groups {
+            let mut splitter = self.clone();
+            splitter.output_dir = self.output_dir.join(&subdir);
+            splitter.spread_dirs = self
+                .spread_dirs
+                .iter()
+                .map(|dir| dir.join(&subdir))
+                .collect();
+            if let Some(map) = &self.source_map {
+                let name = map.file_name().unwrap_or(map.as_os_str());
+                splitter.source_map = Some(splitter.output_dir.join(name));
+            }
+            if !self.dry_run {
+                splitter.create_output_dirs().await?;
+            }
+            splitter
+                .write_batches(statements, &variables, name_prefix, &mut report)
+                .await?;
+        }
+        self.check_warnings(report)
+    }
+
+    /// Creates the output directory and any [`with_spread_dirs`](Self::with_spread_dirs).
+    async fn create_output_dirs(&self) -> Result<(), SplitError> {
+        for dir in std::iter::once(&self.output_dir).chain(&self.spread_dirs) {
+            permissions::create_dir_all(dir, self.dir_mode)
+                .await
+                .map_err(SplitError::at_path(dir))?;
+        }
+        Ok(())
+    }
+
+    /// The path of output file `index`, named `name`: in the output
+    /// directory, or the spread directory its turn comes round to.
+    fn file_path(&self, index: usize, name: &str) -> PathBuf {
+        let turn = (index - 1) % (self.spread_dirs.len() + 1);
+        match turn {
+            0 => self.output_dir.join(name),
+            _ => self.spread_dirs[turn - 1].join(name),
+        }
+    }
+
+    /// The subdirectory of the output directory `statement` is routed to
+    /// under `--by-schema` and `--split-by-type`: `<schema>/<category>` with
+    /// both.
+    fn subdir(&self, statement: &str) -> PathBuf {
+        let mut subdir = PathBuf::new();
+        if self.by_schema {
+            subdir.push(
+                table::schema_name(statement)
+                    .map(|schema| schema_dir_name(&schema))
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| DEFAULT_SCHEMA_DIR.to_string()),
+            );
+        }
+        if self.split_by_type {
+            subdir.push(category::categorize(statement).name());
+        }
+        subdir
+    }
+
+    fn check_warnings(&self, report: SplitReport) -> Result<SplitReport, SplitError> {
+        let warnings = report.warning_summary();
+        if self.fail_on_warnings && !warnings.is_empty() {
+            return Err(SplitError::Warnings(warnings));
+        }
+        Ok(report)
+    }
+
+    /// Batches `statements` by size (or balance) and writes the files into
+    /// the output directory, adding them to `report`. Each file is prefixed
+    /// with the `variables` assignments that precede its first statement,
+    /// and each file name with `name_prefix`.
+    async fn write_batches(
+        &self,
+        statements: Vec<LocatedStatement>,
+        variables: &[LocatedStatement],
+        name_prefix: &str,
+        report: &mut SplitReport,
+    ) -> Result<(), SplitError> {
+        let separator_len = if self.preserve_whitespace {
+            0
+        } else {
+            STATEMENT_SEPARATOR.len()
+        };
+        let limits = BatchLimits {
+            max_bytes: self.statement_budget(),
+            separator_len,
+            lookahead: self.lookahead,
+            balance_files: self.balance_files,
+            group_transactions: self.group_transactions,
+        };
+        let batches = batch_statements(statements, &limits);
+
+        report.files += batches.len();
+        let batch_statements: Vec<usize> = batches.iter().map(Vec::len).collect();
+        report.statements += batch_statements.iter().sum::<usize>();
+
+        let batches: Vec<Vec<LocatedStatement>> = batches
+            .into_iter()
+            .map(|batch| {
+                let first_line = batch.first().map_or(0, |statement| statement.line);
+                variables
+                    .iter()
+                    .take_while(|variable| variable.line < first_line)
+                    .cloned()
+                    .chain(batch)
+                    .collect()
+            })
+            .collect();
+
+        let first_index = if self.append {
+            self.first_index
+                .max(self.last_index(name_prefix).await? + 1)
+        } else {
+            self.first_index
+        };
+        let last_index = first_index + batches.len() - 1;
+        let width = self
+            .index_width
+            .unwrap_or_else(|| DEFAULT_INDEX_WIDTH.max(last_index.to_string().len()));
+        let file_names: Vec<String> = (first_index..=last_index)
+            .map(|i| self.file_name(name_prefix, i, width))
+            .collect();
+        let file_paths: Vec<PathBuf> = (first_index..)
+            .zip(&file_names)
+            .map(|(i, name)| self.file_path(i, name))
+            .collect();
+
+        report.output_files.extend(file_paths.iter().cloned());
+
+        if self.dry_run {
+            for (batch, statements) in batches.iter().zip(batch_statements) {
+                let bytes = self.projected_size(batch).await?;
+                report
+                    .output_file_stats
+                    .push(OutputFileStats { statements, bytes });
+                report.write_times.push(Duration::ZERO);
+            }
+            return self.flush_manifest(report, []).await;
+        }
+
+        if let Some(path) = &self.source_map {
+            let append = self.first_index > 1;
+            Self::write_source_map(path, &file_names, &batches, append).await?;
+            permissions::set_mode(path, self.file_mode).await?;
+        }
+
+        let state_path = self.output_dir.join(STATE_FILE_NAME);
+        let completed = if self.resume {
+            read_state(&state_path).await?
+        } else {
+            HashSet::new()
+        };
+        let mut state = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .truncate(false)
+            .open(long_path::extended(&state_path))
+            .await
+            .map_err(SplitError::at_path(&state_path))?;
+        permissions::set_mode(&state_path, self.file_mode)
+            .await
+            .map_err(SplitError::at_path(&state_path))?;
+        if !self.resume {
+            state.set_len(0).await?;
+        }
+
+        let batch_bytes: Vec<u64> = batches
+            .iter()
+            .map(|batch| batch.iter().map(|s| s.output_len() as u64).sum())
+            .collect();
+        let skipped = |i: &usize| *i <= batch_bytes.len();
+        let mut files_done = completed.iter().filter(|i| skipped(i)).count();
+        let mut bytes_done: u64 = completed
+            .iter()
+            .filter(|i| skipped(i))
+            .map(|&i| batch_bytes[i - 1])
+            .sum();
+        let mut eta = EtaEstimator::new(batch_bytes.iter().sum(), bytes_done);
+        let write_start = Instant::now();
+
+        // Process batches concurrently with limited parallelism
+        let open_files = OpenFiles::new(self.max_open_files);
+        let open_files = &open_files;
+        let mut futures = futures::stream::iter(
+            batches
+                .into_iter()
+                .zip(&file_paths)
+                .enumerate()
+                .filter(|(i, _)| !completed.contains(&(i + 1)))
+                .map(|(i, (batch, path))| async move {
+                    (
+                        i + 1,
+                        self.write_sql_file(i + 1, batch, path.clone(), open_files)
+                            .await,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )
+        .buffer_unordered(self.concurrent_writes);
+
+        // Files finished so far by position, for manifest snapshots
+        let mut written = BTreeMap::new();
+        if self.manifest_flush.is_some() {
+            for &i in completed.iter().filter(|&&i| i <= file_names.len()) {
+                let stats = output_file_stats(&file_paths[i - 1], batch_statements[i - 1]).await?;
+                written.insert(i, stats);
+            }
+        }
+        let mut write_times = vec![Duration::ZERO; file_names.len()];
+        let mut since_flush = 0;
+        let mut failed = Vec::new();
+        while let Some((index, result)) = futures.next().await {
+            let elapsed = match result {
+                Ok(elapsed) => elapsed,
+                Err(e) if self.keep_going => {
+                    failed.push((file_paths[index - 1].clone(), e));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            write_times[index - 1] = elapsed;
+            state.write_all(format!("{}\n", index).as_bytes()).await?;
+            state.flush().await?;
+            if let Some(ProgressHook(report_progress)) = &self.progress {
+                files_done += 1;
+                bytes_done += batch_bytes[index - 1];
+                eta.record(write_start.elapsed(), bytes_done);
+                report_progress(Progress {
+                    files_done,
+                    files: file_names.len(),
+                    bytes_done,
+                    bytes: eta.total(),
+                    elapsed: write_start.elapsed(),
+                    eta: eta.eta(),
+                });
+            }
+            if let Some(flush) = &self.manifest_flush {
+                let stats =
+                    output_file_stats(&file_paths[index - 1], batch_statements[index - 1]).await?;
+                written.insert(index, stats);
+                since_flush += 1;
+                if since_flush == flush.every {
+                    since_flush = 0;
+                    let files = written
+                        .iter()
+                        .map(|(&i, &stats)| (file_paths[i - 1].clone(), stats));
+                    self.flush_manifest(report, files).await?;
+                }
+            }
+        }
+
+        drop(futures);
+        report.peak_open_files = report
+            .peak_open_files
+            .max(open_files.peak.load(Ordering::SeqCst));
+
+        drop(state);
+        if !failed.is_empty() {
+            failed.sort_by(|a, b| a.0.cmp(&b.0));
+            return Err(SplitError::WritesFailed(failed));
+        }
+        fs::remove_file(long_path::extended(&state_path)).await?;
+
+        for (path, statements) in file_paths.iter().zip(batch_statements) {
+            let stats = output_file_stats(path, statements).await?;
+            report.output_file_stats.push(stats);
+        }
+        report.write_times.extend(write_times);
+        self.flush_manifest(report, []).await?;
+
+        Ok(())
+    }
+
+    /// Writes a manifest snapshot if [`with_manifest_flush`](Self::with_manifest_flush)
+    /// is on: the files of earlier inputs, those of `report` already
+    /// complete, then `written`.
+    async fn flush_manifest(
+        &self,
+        report: &SplitReport,
+        written: impl IntoIterator<Item = (PathBuf, OutputFileStats)>,
+    ) -> Result<(), SplitError> {
+        let Some(flush) = &self.manifest_flush else {
+            return Ok(());
+        };
+        let mut snapshot = flush.earlier.clone();
+        let done = report.output_file_stats.len();
+        snapshot
+            .output_files
+            .extend_from_slice(&report.output_files[..done]);
+        snapshot
+            .output_file_stats
+            .extend_from_slice(&report.output_file_stats);
+        for (path, stats) in written {
+            snapshot.output_files.push(path);
+            snapshot.output_file_stats.push(stats);
+        }
+        manifest::write_manifest(&flush.path, &snapshot, flush.format)
+            .await
+            .map_err(SplitError::at_path(&flush.path))
+    }
+
+    /// The name of output file `index`, zero-padded to `width` digits.
+    fn file_name(&self, name_prefix: &str, index: usize, width: usize) -> String {
+        format!(
+            "{}split_{:0width$}.{}{}",
+            name_prefix,
+            index,
+            self.output_ext,
+            self.compression.extension(),
+        )
+    }
+
+    /// The highest index among the output (and spread) directories' files
+    /// named as [`file_name`](Self::file_name) would name them, at any
+    /// padding; 0 if there are none.
+    async fn last_index(&self, name_prefix: &str) -> Result<usize, SplitError> {
+        let prefix = format!("{}split_", name_prefix);
+        let suffix = format!(".{}{}", self.output_ext, self.compression.extension());
+        let mut last = 0;
+        for dir in std::iter::once(&self.output_dir).chain(&self.spread_dirs) {
+            let mut entries = match fs::read_dir(long_path::extended(dir)).await {
+                Ok(entries) => entries,
+                // A dry run doesn't create the directory
+                Err(e) if self.dry_run && e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(SplitError::at_path(dir)(e)),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name();
+                let index = name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix(&prefix))
+                    .and_then(|rest| rest.strip_suffix(&suffix))
+                    .filter(|digits| {
+                        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+                    })
+                    .and_then(|digits| digits.parse::<usize>().ok());
+                if let Some(index) = index {
+                    last = last.max(index);
+                }
+            }
+        }
+        Ok(last)
+    }
+
+    /// Guesses the dialect of `input_file` from its first megabyte; see
+    /// [`dialect::detect`]. Nothing is split or written.
+    pub async fn detect_dialect(
+        &self,
+        input_file: impl AsRef<Path>,
+    ) -> Result<Detection, SplitError> {
+        let mut sample = Vec::new();
+        File::open(input_file)
+            .await?
+            .take(DETECT_SAMPLE_BYTES)
+            .read_to_end(&mut sample)
+            .await?;
+        Ok(dialect::detect(&String::from_utf8_lossy(&sample)))
+    }
+
+    /// The number of statements in `input_file`, counted by a bare scan:
+    /// nothing is filtered, kept or written, and no statement text is
+    /// copied out. Includes are expanded and `GO N` batches repeated as a
+    /// split would, under [`with_expand_includes`](Self::with_expand_includes)
+    /// and [`with_expand_go_count`](Self::with_expand_go_count).
+    pub async fn count_statements(
+        &self,
+        input_file: impl AsRef<Path>,
+    ) -> Result<usize, SplitError> {
+        let content = self.read_expanded(input_file.as_ref()).await?;
+        let splitter = self.clone();
+        match tokio::task::spawn_blocking(move || {
+            let mut scanner = splitter.scanner(content);
+            if !splitter.expand_go_count {
+                return Ok(scanner.count_statements());
+            }
+            // The repeat counts live in the boundaries, so these need the
+            // statements themselves
+            let count = scanner.statements().try_fold(0usize, |count, statement| {
+                let copies = go_copies(&statement)?.map_or(1, |(copies, _)| copies);
+                Ok(count + copies)
+            });
+            count
+        })
+        .await
+        {
+            Ok(count) => count,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Scans `input_file` and reports on its statements without writing
+    /// anything: counts, the largest statement, the size histogram and
+    /// the statements per table.
+    pub async fn analyze(&self, input_file: impl AsRef<Path>) -> Result<SplitReport, SplitError> {
+        let content = fs::read_to_string(input_file).await?;
+        let mut report = SplitReport {
+            input_bytes: content.len(),
+            ..SplitReport::default()
+        };
+        let mut scanner = self.scanner(content);
+        for statement in scanner.statements() {
+            report.statements += 1;
+            report.record_statement(&statement);
+            if let Some(table) = table::table_name(&statement.text) {
+                *report.table_statements.entry(table).or_default() += 1;
+            }
+        }
+        report.record_scan(&scanner.stats());
+        Ok(report)
+    }
+
+    /// Returns the first `limit` statements of `input_file` without writing
+    /// anything, reading only as much of the file as their scan needs.
+    pub async fn preview(
+        &self,
+        input_file: impl AsRef<Path>,
+        limit: usize,
+    ) -> Result<Vec<LocatedStatement>, SplitError> {
+        let file = File::open(input_file).await?;
+        Ok(self.scan_prefix(file, limit).await?.0)
+    }
+
+    /// Scans all of `content`, in parallel under
+    /// [`with_parallel_scan`](Self::with_parallel_scan), keeping only the
+    /// last statements under [`with_tail`](Self::with_tail).
+    fn scan_all(&self, content: String) -> (Vec<LocatedStatement>, ScanStats) {
+        if let Some(tail) = self.tail {
+            let mut scanner = self.scanner(content);
+            let mut ring = VecDeque::with_capacity(tail.min(1024));
+            for statement in scanner.statements() {
+                if ring.len() == tail {
+                    ring.pop_front();
+                }
+                if tail > 0 {
+                    ring.push_back(statement);
+                }
+            }
+            return (ring.into(), scanner.stats());
+        }
+        #[cfg(feature = "parallel")]
+        if self.parallel_scan && self.boundary().is_none() && !self.newline_boundaries {
+            let dialect = self.scan_dialect(&content);
+            let segment_bytes = (content.len() / (rayon::current_num_threads() * 4))
+                .max(parallel::MIN_SEGMENT_BYTES);
+            return parallel::scan(&content, segment_bytes, |text| {
+                self.scanner_with_dialect(text, dialect)
+            });
+        }
+        let mut scanner = self.scanner(content);
+        let statements = scanner.statements().collect();
+        (statements, scanner.stats())
+    }
+
+    /// Scans the first `limit` statements from `reader`, returning them
+    /// with the scan stats for that stretch and the number of bytes read.
+    ///
+    /// The input is read in growing chunks and the prefix re-scanned after
+    /// each. A statement counts once the scan has moved past it into the
+    /// next one, since only then are its delimiter and any `COPY` data known
+    /// to be complete.
+    async fn scan_prefix<R: AsyncRead + Unpin>(
+        &self,
+        mut reader: R,
+        limit: usize,
+    ) -> Result<(Vec<LocatedStatement>, ScanStats, usize), SplitError> {
+        let mut bytes = Vec::new();
+        let mut chunk_len = self.prefix_read_bytes;
+        loop {
+            let read = (&mut reader)
+                .take(chunk_len as u64)
+                .read_to_end(&mut bytes)
+                .await?;
+            let eof = read == 0;
+            let prefix = match std::str::from_utf8(&bytes) {
+                Ok(prefix) => prefix,
+                Err(e) if e.error_len().is_none() && !eof => {
+                    // A multi-byte character straddles the chunk boundary
+                    std::str::from_utf8(&bytes[..e.valid_up_to()]).expect("valid prefix")
+                }
+                Err(e) => {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+                }
+            };
+
+            let mut scanner = self.scanner(prefix);
+            let statements: Vec<LocatedStatement> = scanner.statements().take(limit).collect();
+            let stats = scanner.stats();
+            if eof || (statements.len() == limit && scanner.statements().next().is_some()) {
+                return Ok((statements, stats, prefix.len()));
+            }
+            chunk_len *= 2;
+        }
+    }
+
+    /// Splits each input named in `list_file` (one path per line; blank
+    /// lines and `#` comments are skipped) into the output directory, in
+    /// order, numbering files continuously across inputs. Relative paths are
+    /// resolved against the list file's directory. The header extracted by
+    /// [`with_extract_header`](Self::with_extract_header) comes from the first
+    /// input only.
+    pub async fn split_list(&self, list_file: impl AsRef<Path>) -> Result<SplitReport, SplitError> {
+        let list_file = list_file.as_ref();
+        let base = list_file.parent().unwrap_or(Path::new(""));
+        let list = fs::read_to_string(list_file).await?;
+        let inputs: Vec<PathBuf> = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| base.join(line))
+            .collect();
+
+        let mut scans = self.scan_ahead(inputs.clone());
+        let mut total = SplitReport::default();
+        for (i, input) in inputs.iter().enumerate() {
+            let scanned = next_scan(&mut scans).await?;
+            let mut splitter = self.clone();
+            splitter.first_index = self.first_index + total.files;
+            splitter.extract_header = self.extract_header && i == 0;
+            if let Some(flush) = &mut splitter.manifest_flush {
+                flush.earlier = total.clone();
+            }
+            let report = splitter
+                .split_scanned(scanned, &self.name_prefix(input))
+                .await?;
+            total.merge(&report);
+        }
+        Ok(total)
+    }
+
+    /// Scans of `inputs` in order, up to
+    /// [`with_concurrent_reads`](Self::with_concurrent_reads) of them
+    /// running at once.
+    fn scan_ahead(&self, inputs: Vec<PathBuf>) -> impl Stream<Item = ScanResult> + Unpin + '_ {
+        futures::stream::iter(inputs)
+            .map(|input| self.spawn_scan(input))
+            .buffered(self.concurrent_reads)
+    }
+
+    /// Splits every `.sql` file in `input_dir`, each into a subdirectory of
+    /// the output directory named after the file's stem (mirroring the
+    /// relative path when `recursive`). Inputs are processed one at a time so
+    /// the concurrent-write limit holds across the whole run. Returns each
+    /// input with its report.
+    pub async fn split_dir(
+        &self,
+        input_dir: impl AsRef<Path>,
+        recursive: bool,
+    ) -> Result<Vec<(PathBuf, SplitReport)>, SplitError> {
+        let input_dir = input_dir.as_ref();
+        let inputs = find_sql_files(input_dir, recursive).await?;
+        let mut scans = self.scan_ahead(inputs.clone());
+        let mut reports = Vec::new();
+        let mut total = SplitReport::default();
+        for input in inputs {
+            let scanned = next_scan(&mut scans).await?;
+            let relative = input.strip_prefix(input_dir).unwrap_or(&input);
+            let mut splitter = self.clone();
+            if let Some(flush) = &mut splitter.manifest_flush {
+                flush.earlier = total.clone();
+            }
+            splitter.output_dir = self.output_dir.join(relative.with_extension(""));
+            if let Some(map) = &self.source_map {
+                let name = map.file_name().unwrap_or(map.as_os_str());
+                splitter.source_map = Some(splitter.output_dir.join(name));
+            }
+            let report = splitter
+                .split_scanned(scanned, &self.name_prefix(&input))
+                .await?;
+            total.merge(&report);
+            reports.push((input, report));
+        }
+        Ok(reports)
+    }
+}
+
+/// An input read and scanned, waiting to be split.
+struct Scanned {
+    statements: Vec<LocatedStatement>,
+    stats: ScanStats,
+    input_bytes: usize,
+}
+
+/// The outcome of a task [`SqlSplitter::spawn_scan`] started.
+type ScanResult = Result<Result<Scanned, SplitError>, tokio::task::JoinError>;
+
+/// The next input's scan from `scans`, re-raising a panic inside it.
+async fn next_scan(
+    scans: &mut (impl Stream<Item = ScanResult> + Unpin),
+) -> Result<Scanned, SplitError> {
+    match scans.next().await.expect("a scan per input") {
+        Ok(scanned) => scanned,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// A command running `command` through the platform shell.
+fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+/// A directory name for `schema`: lowercased, so names differing only in
+/// case share a directory on every filesystem, and with anything but
+/// letters, digits, `_` and `-` replaced by `_`.
+fn schema_dir_name(schema: &str) -> String {
+    schema
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `name` safe to use in a file name on any common filesystem: characters
+/// Windows or unix reject or treat specially (`\ / : * ? " < > |`),
+/// control characters and U+FFFD (from undecodable bytes) become `_`, and
+/// trailing dots and spaces are dropped.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '\u{FFFD}' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    sanitized.trim_end_matches(['.', ' ']).to_string()
+}
+
+/// Removes the comments and blank lines leading `statement`, returning them
+/// without trailing whitespace and moving the statement's line past them.
+fn split_header(statement: &mut LocatedStatement) -> String {
+    let rest = Cursor::new(&statement.text).rest();
+    let header_len = statement.text.len() - rest.len();
+    let header: String = statement.text.drain(..header_len).collect();
+    statement.line += header.matches('\n').count();
+    header.trim_end().to_string()
+}
+
+/// Trims `statement` and collapses each whitespace run outside single-quoted
+/// strings to one space, so formatting differences don't defeat `--dedupe`.
+fn normalize(statement: &str) -> String {
+    let mut normalized = String::with_capacity(statement.len());
+    let mut in_string = false;
+    let mut pending_space = false;
+    for c in statement.trim().chars() {
+        if !in_string && c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            normalized.push(' ');
+            pending_space = false;
+        }
+        if c == '\'' {
+            in_string = !in_string;
+        }
+        normalized.push(c);
+    }
+    normalized
+}
+
+/// Lists the `.sql` files directly in `dir` (or beneath it, if
+/// `recursive`), sorted for a deterministic processing order.
+async fn find_sql_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, SplitError> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                if recursive {
+                    pending.push(path);
+                }
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `statements` with each one ended by a `GO N` boundary repeated `N`
+/// times, each copy ended by the boundary without its count. Fails with
+/// [`SplitError::GoCountTooLarge`] on a count over [`MAX_GO_COUNT`].
+fn expand_go_counts(
+    statements: Vec<LocatedStatement>,
+) -> Result<Vec<LocatedStatement>, SplitError> {
+    let mut expanded = Vec::with_capacity(statements.len());
+    for mut statement in statements {
+        let Some((count, boundary)) = go_copies(&statement)? else {
+            expanded.push(statement);
+            continue;
+        };
+        statement.boundary = Some(boundary);
+        expanded.extend(std::iter::repeat_n(statement, count));
+    }
+    Ok(expanded)
+}
+
+/// The number of copies a `GO N` boundary asks of `statement`, with the
+/// boundary text minus the count; `None` if it doesn't end in one. Fails
+/// with [`SplitError::GoCountTooLarge`] over [`MAX_GO_COUNT`].
+fn go_copies(statement: &LocatedStatement) -> Result<Option<(usize, String)>, SplitError> {
+    let Some((count, boundary)) = statement.boundary.as_deref().and_then(go_count) else {
+        return Ok(None);
+    };
+    let count = usize::try_from(count)
+        .ok()
+        .filter(|&count| count <= MAX_GO_COUNT)
+        .ok_or(SplitError::GoCountTooLarge {
+            line: statement.line,
+            count,
+            limit: MAX_GO_COUNT,
+        })?;
+    Ok(Some((count, boundary)))
+}
+
+/// The repeat count of a `GO N` boundary (`u64::MAX` if larger), with the
+/// boundary text minus the count; `None` for any other boundary, or a
+/// count of 0.
+fn go_count(boundary: &str) -> Option<(u64, String)> {
+    static GO_COUNT: OnceLock<Regex> = OnceLock::new();
+    let pattern =
+        GO_COUNT.get_or_init(|| Regex::new(r"(?is)^(.*\bGO)[ \t]+([0-9]+)(\s*)$").unwrap());
+    let captures = pattern.captures(boundary)?;
+    // All digits, so only overflow fails to parse
+    let count = captures[2].parse().unwrap_or(u64::MAX);
+    (count > 0).then(|| (count, format!("{}{}", &captures[1], &captures[3])))
+}
+
+/// Fails with [`SplitError::Unmappable`] if `encoding` can't represent a
+/// character of `statement` or its `COPY` data.
+fn check_encodable(
+    statement: &LocatedStatement,
+    encoding: &'static Encoding,
+) -> Result<(), SplitError> {
+    let unmappable = encoding::first_unmappable(&statement.text, encoding).or_else(|| {
+        statement
+            .data
+            .as_deref()
+            .and_then(|data| encoding::first_unmappable(data, encoding))
+    });
+    match unmappable {
+        Some(character) => Err(SplitError::Unmappable {
+            line: statement.line,
+            character,
+            encoding: encoding.name(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Applies `mode` to `statement` if it holds non-ASCII characters.
+fn check_ascii(
+    mode: AsciiMode,
+    statement: &mut LocatedStatement,
+    report: &mut SplitReport,
+) -> Result<(), SplitError> {
+    let Some(character) = ascii::first_non_ascii(&statement.text)
+        .or_else(|| statement.data.as_deref().and_then(ascii::first_non_ascii))
+    else {
+        return Ok(());
+    };
+    match mode {
+        AsciiMode::Error => {
+            return Err(SplitError::NonAscii {
+                line: statement.line,
+                character,
+            })
+        }
+        AsciiMode::Warn => {
+            report.non_ascii_statements += 1;
+            report.warn(
+                WarningKind::NonAscii,
+                Some(statement.line),
+                format!(
+                    "statement on line {} contains non-ASCII character {:?}",
+                    statement.line, character
+                ),
+            );
+        }
+        AsciiMode::Transliterate => {
+            statement.text = ascii::transliterate(&statement.text);
+            if let Some(data) = &mut statement.data {
+                *data = ascii::transliterate(data);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Statement count and on-disk size of the output file at `path`.
+async fn output_file_stats(path: &Path, statements: usize) -> Result<OutputFileStats, SplitError> {
+    let bytes = fs::metadata(long_path::extended(path)).await?.len();
+    Ok(OutputFileStats { statements, bytes })
+}
+
+/// Reads the batch indices recorded in a state file; a missing file means
+/// nothing has completed yet.
+async fn read_state(path: &Path) -> Result<HashSet<usize>, SplitError> {
+    match fs::read_to_string(long_path::extended(path)).await {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Warning;
+    use crate::scanner::Unclosed;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_split_statements() {
+        let input = "SELECT * FROM table1; INSERT INTO table2 VALUES ('test;test'); UPDATE table3 SET col = 1;";
+        let statements = SqlSplitter::split_statements(input);
+        assert_eq!(statements.len(), 3);
+        assert_eq!(
+            statements[1].trim(),
+            "INSERT INTO table2 VALUES ('test;test')"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_statements_with_escaped_quotes() {
+        let input = "SELECT 'it\\'s working'; INSERT INTO table2 VALUES ('test');";
+        let statements = SqlSplitter::split_statements(input);
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].trim(), "SELECT 'it\\'s working'");
+    }
+
+    #[tokio::test]
+    async fn test_file_splitting() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let splitter = SqlSplitter::new(temp_dir.path(), 1, 2).with_max_size(10);
+
+        // Create a test input file
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input_file = File::create(&input_path).await?;
+        input_file
+            .write_all(b"SELECT 1; SELECT 2; SELECT 3;")
+            .await?;
+
+        let num_files = splitter.split_file(input_path).await?.files;
+        assert!(num_files > 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_splitting_by_kilobytes() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let splitter = SqlSplitter::new(temp_dir.path(), 1, 2);
+
+        let input_path = temp_dir.path().join("input.sql");
+        let statement = format!("SELECT '{}';", "x".repeat(600));
+        fs::write(&input_path, statement.repeat(3)).await?;
+
+        let num_files = splitter.split_file(input_path).await?.files;
+        assert!(num_files > 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_reader_from_memory() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let statement = format!("SELECT '{}';", "x".repeat(600));
+        let input = std::io::Cursor::new(statement.repeat(3).into_bytes());
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .split_reader(input)
+            .await?;
+        assert_eq!(report.files, 3);
+        assert_eq!(report.input_bytes, statement.len() * 3);
+        for i in 1..=3 {
+            let output = fs::read_to_string(output_dir.join(format!("split_{:03}.sql", i))).await?;
+            assert_eq!(output, statement);
+        }
+
+        // A limit stops reading part-way, as it does for files
+        let limited_dir = temp_dir.path().join("limited");
+        let input = std::io::Cursor::new(statement.repeat(3).into_bytes());
+        let report = SqlSplitter::new(&limited_dir, 1000, 2)
+            .with_limit(1)
+            .with_prefix_read_size(700)
+            .split_reader(input)
+            .await?;
+        assert_eq!(report.statements, 1);
+        assert!(report.input_bytes < statement.len() * 3);
+
+        let invalid = std::io::Cursor::new(b"SELECT '\xff';".to_vec());
+        let result = SqlSplitter::new(temp_dir.path().join("invalid"), 1000, 2)
+            .split_reader(invalid)
+            .await;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ignore_file_drops_matching_tables() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let ignore_path = temp_dir.path().join(".sqlsplitterignore");
+        fs::write(&ignore_path, "# anonymized export\naudit_*\n").await?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE users (id INT);\n\
+             CREATE TABLE audit_log (id INT);\n\
+             INSERT INTO users VALUES (1);\n\
+             INSERT INTO `audit_log` VALUES (1);\n\
+             INSERT INTO audit_events VALUES (2);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_ignore_patterns(TablePatterns::load(&ignore_path).await?);
+        assert_eq!(splitter.split_file(&input_path).await?.files, 1);
+
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "CREATE TABLE users (id INT);\n\nINSERT INTO users VALUES (1);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_source_map_lines() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SELECT 1;\n\nSELECT\n  'a;\nb';\nSELECT 3; SELECT 4;\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let map_path = temp_dir.path().join("map.tsv");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2).with_source_map(&map_path);
+        splitter.split_file(&input_path).await?;
+
+        let map = fs::read_to_string(&map_path).await?;
+        assert_eq!(
+            map,
+            "file\tstatement\tline\n\
+             split_001.sql\t1\t1\n\
+             split_001.sql\t2\t3\n\
+             split_001.sql\t3\t6\n\
+             split_001.sql\t4\t6\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_statement_bytes_rejects_oversized() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let content = format!(
+            "SELECT 1;\nSELECT 2;\nINSERT INTO t VALUES ('{}');\n",
+            "x".repeat(100)
+        );
+        fs::write(&input_path, content).await?;
+
+        let splitter =
+            SqlSplitter::new(temp_dir.path().join("out"), 1000, 2).with_max_statement_bytes(64);
+        match splitter.split_file(&input_path).await {
+            Err(SplitError::StatementTooLarge { line, limit, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(limit, 64);
+            }
+            other => panic!("expected StatementTooLarge, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    async fn decompress(path: PathBuf, compression: Compression) -> std::io::Result<String> {
+        use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+        use tokio::io::{AsyncReadExt, BufReader};
+
+        let file = BufReader::new(File::open(path).await?);
+        let mut output = String::new();
+        match compression {
+            Compression::Gzip => GzipDecoder::new(file).read_to_string(&mut output).await?,
+            Compression::Zstd => ZstdDecoder::new(file).read_to_string(&mut output).await?,
+            _ => unreachable!(),
+        };
+        Ok(output)
+    }
+
+    #[tokio::test]
+    async fn test_compressed_output_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1; SELECT 2;").await?;
+
+        for (compression, file_name) in [
+            (Compression::Gzip, "split_001.sql.gz"),
+            (Compression::Zstd, "split_001.sql.zst"),
+        ] {
+            let output_dir = temp_dir.path().join(file_name);
+            let splitter =
+                SqlSplitter::new(&output_dir, 1000, 2).with_compression(compression, Some(3));
+            assert_eq!(splitter.split_file(&input_path).await?.files, 1);
+
+            let output = decompress(output_dir.join(file_name), compression).await?;
+            assert_eq!(output, "SELECT 1;\n\nSELECT 2;");
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_permission_denied_output_dir() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;").await?;
+        let locked = temp_dir.path().join("locked");
+        fs::create_dir(&locked).await?;
+        fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).await?;
+
+        // Privileged users bypass directory permissions; nothing to assert then
+        if fs::write(locked.join("probe"), "").await.is_ok() {
+            return Ok(());
+        }
+
+        let output_dir = locked.join("out");
+        let result = SqlSplitter::new(&output_dir, 1000, 2)
+            .split_file(&input_path)
+            .await;
+        match result {
+            Err(SplitError::PermissionDenied { path, .. }) => assert_eq!(path, output_dir),
+            other => panic!("expected PermissionDenied, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dir_and_file_modes() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1; SELECT 2;").await?;
+
+        let output_dir = temp_dir.path().join("shared/out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_dir_mode(0o770)
+            .with_file_mode(0o660)
+            .split_file(&input_path)
+            .await?;
+        let mode = |path: PathBuf| async move {
+            Ok::<_, std::io::Error>(fs::metadata(path).await?.permissions().mode() & 0o777)
+        };
+        assert_eq!(mode(temp_dir.path().join("shared")).await?, 0o770);
+        assert_eq!(mode(output_dir.clone()).await?, 0o770);
+        assert_eq!(mode(output_dir.join("split_001.sql")).await?, 0o660);
+        // A directory that already existed keeps its mode
+        assert_ne!(mode(temp_dir.path().to_path_buf()).await?, 0o770);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multiline_strings_and_copy_data_survive() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let input = "INSERT INTO notes VALUES ('line one  \n\n  line two\t');\n\
+                     COPY public.items (id, name, note) FROM stdin;\n\
+                     1\twidget\t\n\
+                     2\tgadget; large\t  \n\
+                     \\.\n\
+                     SELECT 1;\n";
+        fs::write(&input_path, input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "INSERT INTO notes VALUES ('line one  \n\n  line two\t');\n\n\
+             COPY public.items (id, name, note) FROM stdin;\n\
+             1\twidget\t\n\
+             2\tgadget; large\t  \n\
+             \\.\n\n\
+             SELECT 1;"
+        );
+
+        let preserved_dir = temp_dir.path().join("preserved");
+        SqlSplitter::new(&preserved_dir, 1000, 2)
+            .with_preserve_whitespace(true)
+            .split_file(&input_path)
+            .await?;
+        let preserved = fs::read_to_string(preserved_dir.join("split_001.sql")).await?;
+        assert_eq!(preserved, input.trim_end());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_copy_block_written_raw_in_its_own_file() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let data = "\n1\tends;\r\n2\t\\N\t'open\r\n\\.not the end\r\n\\.";
+        let input = format!(
+            "SELECT '{big}';\nCOPY t (id, v) FROM stdin;{data}\nSELECT '{big}';\n",
+            big = "x".repeat(1000),
+        );
+        fs::write(&input_path, &input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_line_endings(LineEndings::Unix)
+            .with_final_newline(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.files, 3);
+        let copy = fs::read_to_string(output_dir.join("split_002.sql")).await?;
+        assert_eq!(copy, format!("COPY t (id, v) FROM stdin;{}\n", data));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_group_transactions_keeps_them_whole() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE t (id INT);\n\
+             BEGIN;\nINSERT INTO t VALUES (1);\nINSERT INTO t VALUES (2);\nCOMMIT;\n\
+             START TRANSACTION;\nINSERT INTO t VALUES (3);\nROLLBACK TO SAVEPOINT s;\n\
+             INSERT INTO t VALUES (4);\nCOMMIT;\n\
+             BEGIN;\nDELETE FROM t;\nROLLBACK;\n",
+        )
+        .await?;
+
+        // No two transactions fit in 90 bytes; the second alone is over
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_max_size(90)
+            .with_group_transactions(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 13);
+        let mut files = Vec::new();
+        for path in &report.output_files {
+            files.push(fs::read_to_string(path).await?);
+        }
+        assert_eq!(
+            files,
+            vec![
+                "CREATE TABLE t (id INT);",
+                "BEGIN;\n\nINSERT INTO t VALUES (1);\n\nINSERT INTO t VALUES (2);\n\nCOMMIT;",
+                "START TRANSACTION;\n\nINSERT INTO t VALUES (3);\n\n\
+                 ROLLBACK TO SAVEPOINT s;\n\nINSERT INTO t VALUES (4);\n\nCOMMIT;",
+                "BEGIN;\n\nDELETE FROM t;\n\nROLLBACK;",
+            ]
+        );
+
+        // Greedy batching cuts transactions apart
+        let report = SqlSplitter::new(temp_dir.path().join("greedy"), 1, 2)
+            .with_max_size(90)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.files, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lookahead_avoids_underfilled_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        // Output sizes 500, 400, 200 and 900 bytes against a 1024-byte limit:
+        // greedily, the 900-byte statement strands the 200-byte one alone
+        let statement = |size: usize| format!("SELECT '{}';\n", "x".repeat(size - 10));
+        let input = [500, 400, 200, 900].map(statement).concat();
+        fs::write(&input_path, input).await?;
+
+        let file_sizes = |dir: PathBuf, files: usize| async move {
+            let mut sizes = Vec::new();
+            for i in 1..=files {
+                sizes.push(
+                    fs::metadata(dir.join(format!("split_{:03}.sql", i)))
+                        .await?
+                        .len(),
+                );
+            }
+            Ok::<_, std::io::Error>(sizes)
+        };
+
+        let greedy_dir = temp_dir.path().join("greedy");
+        let greedy = SqlSplitter::new(&greedy_dir, 1, 2)
+            .split_file(&input_path)
+            .await?;
+        let greedy_sizes = file_sizes(greedy_dir, greedy.files).await?;
+        assert_eq!(greedy_sizes, vec![902, 200, 900]);
+
+        let lookahead_dir = temp_dir.path().join("lookahead");
+        let lookahead = SqlSplitter::new(&lookahead_dir, 1, 2)
+            .with_lookahead(4)
+            .split_file(&input_path)
+            .await?;
+        let lookahead_sizes = file_sizes(lookahead_dir, lookahead.files).await?;
+        assert_eq!(lookahead_sizes, vec![500, 602, 900]);
+        assert_eq!(lookahead.statements, greedy.statements);
+        assert!(lookahead_sizes.iter().min() > greedy_sizes.iter().min());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_balance_evens_out_file_sizes() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = String::from("CREATE TABLE t (v TEXT);\n");
+        for i in 0..40 {
+            let width = (i * 37) % 200 + 10;
+            input.push_str(&format!(
+                "INSERT INTO t VALUES ('{}');\n",
+                "x".repeat(width)
+            ));
+        }
+        fs::write(&input_path, &input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2).with_balance(4);
+        assert_eq!(splitter.split_file(&input_path).await?.files, 4);
+
+        let mut sizes = Vec::new();
+        for i in 1..=4 {
+            let output = fs::read_to_string(output_dir.join(format!("split_{:03}.sql", i))).await?;
+            if i == 1 {
+                assert!(output.starts_with("CREATE TABLE t"));
+            }
+            sizes.push(output.len());
+        }
+        let spread = sizes.iter().max().unwrap() - sizes.iter().min().unwrap();
+        // Greedy LPT keeps every bin within one statement of the others
+        assert!(spread <= 250, "sizes too uneven: {:?}", sizes);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_open_files_caps_concurrent_writes() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let statement = format!("SELECT '{}';", "x".repeat(600));
+        fs::write(&input_path, statement.repeat(40)).await?;
+
+        let splitter = SqlSplitter::new(temp_dir.path().join("out"), 1, 16)
+            .with_compression(Compression::Gzip, None)
+            .with_max_open_files(2);
+        let report = splitter.split_file(&input_path).await?;
+        assert_eq!(report.files, 40);
+        assert!(report.peak_open_files >= 1);
+        assert!(
+            report.peak_open_files <= 2,
+            "{} open",
+            report.peak_open_files
+        );
+
+        // The semaphore itself never lets a third writer through
+        let open_files = OpenFiles::new(Some(2));
+        let first = open_files.acquire().await;
+        let _second = open_files.acquire().await;
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), open_files.acquire())
+                .await
+                .is_err()
+        );
+        drop(first);
+        let _third = open_files.acquire().await;
+        assert_eq!(open_files.peak.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_append_numbers_after_existing_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(&output_dir).await?;
+        for name in [
+            "split_001.sql",
+            "split_0004.sql",
+            "split_009.txt",
+            "split_x.sql",
+        ] {
+            fs::write(output_dir.join(name), "SELECT 0;").await?;
+        }
+
+        let input_path = temp_dir.path().join("input.sql");
+        let statement = format!("SELECT '{}';", "x".repeat(600));
+        fs::write(&input_path, statement.repeat(2)).await?;
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_append(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            report.output_files,
+            vec![
+                output_dir.join("split_005.sql"),
+                output_dir.join("split_006.sql")
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_005.sql")).await?,
+            statement
+        );
+        for name in ["split_001.sql", "split_0004.sql"] {
+            assert_eq!(
+                fs::read_to_string(output_dir.join(name)).await?,
+                "SELECT 0;"
+            );
+        }
+
+        // Into an empty directory, appending numbers from 1 as usual
+        let empty_dir = temp_dir.path().join("empty");
+        let report = SqlSplitter::new(&empty_dir, 1, 2)
+            .with_append(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.output_files[0], empty_dir.join("split_001.sql"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_completed_batches() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let statement = format!("SELECT '{}';\n", "x".repeat(600));
+        fs::write(&input_path, statement.repeat(4)).await?;
+
+        // Simulate a run that was interrupted after writing the first two files
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(&output_dir).await?;
+        fs::write(output_dir.join("split_001.sql"), "-- from first run").await?;
+        fs::write(output_dir.join("split_002.sql"), "-- from first run").await?;
+        fs::write(output_dir.join(STATE_FILE_NAME), "1\n2\n").await?;
+
+        let splitter = SqlSplitter::new(&output_dir, 1, 2).with_resume(true);
+        assert_eq!(splitter.split_file(&input_path).await?.files, 4);
+
+        for i in 1..=2 {
+            let output = fs::read_to_string(output_dir.join(format!("split_{:03}.sql", i))).await?;
+            assert_eq!(output, "-- from first run");
+        }
+        for i in 3..=4 {
+            let output = fs::read_to_string(output_dir.join(format!("split_{:03}.sql", i))).await?;
+            assert!(output.starts_with("SELECT 'xxx"));
+        }
+        assert!(!output_dir.join(STATE_FILE_NAME).exists());
+        assert!(!output_dir.join("split_003.sql.partial").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_report_times_each_write() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let statement = format!("SELECT '{}';\n", "x".repeat(600));
+        fs::write(&input_path, statement.repeat(4)).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.write_times.len(), 4);
+        assert!(report
+            .write_times
+            .iter()
+            .all(|elapsed| *elapsed > Duration::ZERO && *elapsed < Duration::from_secs(60)));
+        let timings = report.write_timings();
+        assert_eq!(timings.lines().count(), 1 + 4 + 2);
+        assert!(timings.contains("split_004.sql: "));
+
+        // Files a resumed run didn't write take no time
+        fs::write(output_dir.join(STATE_FILE_NAME), "1\n2\n").await?;
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_resume(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.write_times[..2], [Duration::ZERO; 2]);
+        assert!(report.write_times[2..].iter().all(|e| *e > Duration::ZERO));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_counts_multibyte_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+
+        // 510 bytes of text + ';' = 511; two plus the blank-line separator is
+        // exactly 1024 bytes, although each statement is only 176 chars
+        let statement = format!("SELECT '{}{}';", "中".repeat(163), "😀".repeat(3));
+        assert_eq!(statement.len(), 511);
+        fs::write(&input_path, statement.repeat(4)).await?;
+
+        let output_dir = temp_dir.path().join("exact");
+        assert_eq!(
+            SqlSplitter::new(&output_dir, 1, 2)
+                .split_file(&input_path)
+                .await?
+                .files,
+            2
+        );
+        for i in 1..=2 {
+            let output = fs::read(output_dir.join(format!("split_{:03}.sql", i))).await?;
+            assert_eq!(output.len(), 1024);
+        }
+
+        // One more three-byte char per statement no longer fits two per file
+        let statement = format!("SELECT '{}{}';", "中".repeat(164), "😀".repeat(3));
+        fs::write(&input_path, statement.repeat(4)).await?;
+        let output_dir = temp_dir.path().join("over");
+        assert_eq!(
+            SqlSplitter::new(&output_dir, 1, 2)
+                .split_file(&input_path)
+                .await?
+                .files,
+            4
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_delimiter_handles_procedure_dumps() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+
+        let mysql_path = temp_dir.path().join("mysql.sql");
+        fs::write(
+            &mysql_path,
+            "DROP PROCEDURE IF EXISTS p;\n\
+             DELIMITER ;;\n\
+             CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND ;;\n\
+             DELIMITER ;\n\
+             CALL p();\n",
+        )
+        .await?;
+        let mysql_out = temp_dir.path().join("mysql");
+        SqlSplitter::new(&mysql_out, 1000, 2)
+            .with_auto_delimiter(true)
+            .split_file(&mysql_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(mysql_out.join("split_001.sql")).await?,
+            "DROP PROCEDURE IF EXISTS p;\n\n\
+             DELIMITER ;;\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND;;\nDELIMITER ;\n\n\
+             CALL p();"
+        );
+
+        let pg_path = temp_dir.path().join("pg.sql");
+        fs::write(
+            &pg_path,
+            "CREATE FUNCTION add(a int, b int) RETURNS int AS $$\n\
+             BEGIN\n  RETURN a + b;\nEND;\n$$ LANGUAGE plpgsql;\n\
+             SELECT add(1, 2);\n",
+        )
+        .await?;
+        let pg_out = temp_dir.path().join("pg");
+        SqlSplitter::new(&pg_out, 1000, 2)
+            .with_auto_delimiter(true)
+            .split_file(&pg_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(pg_out.join("split_001.sql")).await?,
+            "CREATE FUNCTION add(a int, b int) RETURNS int AS $$\n\
+             BEGIN\n  RETURN a + b;\nEND;\n$$ LANGUAGE plpgsql;\n\n\
+             SELECT add(1, 2);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_report_counts_skipped_segments() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SELECT 1;;\n-- stray comment\n;\nSELECT 2;\n/* trailer */;\nSELECT 3",
+        )
+        .await?;
+
+        let report = SqlSplitter::new(temp_dir.path().join("out"), 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        let output_path = temp_dir.path().join("out").join("split_001.sql");
+        let output_bytes = fs::metadata(&output_path).await?.len();
+        assert_eq!(
+            report,
+            SplitReport {
+                files: 1,
+                statements: 3,
+                empty_segments: 1,
+                comment_only_segments: 2,
+                unterminated_statements: 1,
+                unclosed: vec![],
+                malformed: vec![],
+                suspicious_escapes: vec![],
+                string_literals: 0,
+                comments: 2,
+                oversized_statements: 0,
+                duplicate_statements: 0,
+                non_ascii_statements: 0,
+                reference_cycles: 0,
+                table_statements: BTreeMap::new(),
+                input_bytes: 63,
+                largest_statement_bytes: 9,
+                largest_statement_line: 1,
+                size_histogram: [3, 0, 0, 0],
+                peak_open_files: 1,
+                output_files: vec![output_path],
+                output_file_stats: vec![OutputFileStats {
+                    statements: 3,
+                    bytes: output_bytes,
+                }],
+                write_times: report.write_times.clone(),
+                warnings: vec![
+                    Warning {
+                        kind: WarningKind::EmptyStatements,
+                        message: "1 empty statement(s) skipped".to_string(),
+                        line: None,
+                    },
+                    Warning {
+                        kind: WarningKind::CommentOnlySegments,
+                        message: "2 comment-only segment(s) dropped".to_string(),
+                        line: None,
+                    },
+                    Warning {
+                        kind: WarningKind::UnterminatedStatements,
+                        message: "1 statement(s) lacked a trailing delimiter".to_string(),
+                        line: None,
+                    },
+                ],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_dir_creates_subdir_per_input() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_dir = temp_dir.path().join("dumps");
+        fs::create_dir_all(input_dir.join("nested")).await?;
+        fs::write(input_dir.join("users.sql"), "SELECT 1; SELECT 2;").await?;
+        fs::write(input_dir.join("orders.SQL"), "SELECT 3;").await?;
+        fs::write(input_dir.join("notes.txt"), "SELECT 4;").await?;
+        fs::write(input_dir.join("nested/extra.sql"), "SELECT 5;").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2);
+        let reports = splitter.split_dir(&input_dir, false).await?;
+        assert_eq!(reports.len(), 2);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("users/split_001.sql")).await?,
+            "SELECT 1;\n\nSELECT 2;"
+        );
+        assert_eq!(
+            fs::read_to_string(output_dir.join("orders/split_001.sql")).await?,
+            "SELECT 3;"
+        );
+        assert!(!output_dir.join("notes").exists());
+        assert!(!output_dir.join("nested").exists());
+
+        let reports = splitter.split_dir(&input_dir, true).await?;
+        assert_eq!(reports.len(), 3);
+        assert!(output_dir.join("nested/extra/split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_filter_keeps_named_tables() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET NAMES utf8mb4;\n\
+             CREATE TABLE users (id INT);\n\
+             CREATE TABLE orders (id INT);\n\
+             CREATE TABLE logs (id INT);\n\
+             CREATE INDEX idx_orders ON orders (id);\n\
+             INSERT INTO users VALUES (1);\n\
+             INSERT INTO logs VALUES (1);\n\
+             INSERT INTO `orders` VALUES (1);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_table_filter(TablePatterns::parse("users\norders")?)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SET NAMES utf8mb4;\n\n\
+             CREATE TABLE users (id INT);\n\n\
+             CREATE TABLE orders (id INT);\n\n\
+             CREATE INDEX idx_orders ON orders (id);\n\n\
+             INSERT INTO users VALUES (1);\n\n\
+             INSERT INTO `orders` VALUES (1);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_table_filter_from_allowlist_file() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET search_path = public;\n\
+             CREATE TABLE public.users (id INT);\n\
+             CREATE TABLE public.user_roles (id INT);\n\
+             CREATE TABLE public.orders (id INT);\n\
+             CREATE TABLE audit.orders (id INT);\n\
+             INSERT INTO public.users VALUES (1);\n\
+             INSERT INTO audit.orders VALUES (1);\n",
+        )
+        .await?;
+        let allowlist = temp_dir.path().join("tables.txt");
+        fs::write(
+            &allowlist,
+            "# tables for the staging copy\nuser*\n\npublic.orders\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_table_filter(TablePatterns::load(&allowlist).await?)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SET search_path = public;\n\n\
+             CREATE TABLE public.users (id INT);\n\n\
+             CREATE TABLE public.user_roles (id INT);\n\n\
+             CREATE TABLE public.orders (id INT);\n\n\
+             INSERT INTO public.users VALUES (1);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trailing_delimiter_tracks_source() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT 2").await?;
+
+        let cases = [
+            ("default", None, false, "SELECT 1;\n\nSELECT 2;"),
+            (
+                "source",
+                Some(TrailingDelimiter::Source),
+                false,
+                "SELECT 1;\n\nSELECT 2",
+            ),
+            ("preserved", None, true, "SELECT 1;\nSELECT 2"),
+            (
+                "forced",
+                Some(TrailingDelimiter::Always),
+                true,
+                "SELECT 1;\nSELECT 2;",
+            ),
+        ];
+        for (name, policy, preserve, expected) in cases {
+            let output_dir = temp_dir.path().join(name);
+            let mut splitter =
+                SqlSplitter::new(&output_dir, 1000, 2).with_preserve_whitespace(preserve);
+            if let Some(policy) = policy {
+                splitter = splitter.with_trailing_delimiter(policy);
+            }
+            splitter.split_file(&input_path).await?;
+            let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+            assert_eq!(output, expected, "{}", name);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semicolon_in_trailing_comment_is_no_terminator(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 0;\nSELECT 1 -- note;").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_preserve_whitespace(true)
+            .with_trailing_delimiter(TrailingDelimiter::Always)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SELECT 0;\nSELECT 1 -- note;\n;"
+        );
+        assert!(scanner::ends_with_semicolon("SELECT 1;", Dialect::Generic));
+        assert!(!scanner::ends_with_semicolon(
+            "SELECT 1 # a;",
+            Dialect::Mysql
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_statements_without_whitespace_between() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;SELECT ';';;SELECT 3 -- last").await?;
+
+        let cases = [
+            (false, "SELECT 1;\n\nSELECT ';';\n\nSELECT 3 -- last\n;"),
+            (true, "SELECT 1;SELECT ';';SELECT 3 -- last\n;"),
+        ];
+        for (preserve, expected) in cases {
+            let output_dir = temp_dir.path().join(format!("out_{}", preserve));
+            let report = SqlSplitter::new(&output_dir, 1000, 2)
+                .with_preserve_whitespace(preserve)
+                .with_trailing_delimiter(TrailingDelimiter::Always)
+                .split_file(&input_path)
+                .await?;
+            assert_eq!(report.statements, 3);
+            assert_eq!(report.empty_segments, 1);
+            let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+            assert_eq!(output, expected, "preserve: {}", preserve);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_output_ext_names_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "CREATE TABLE t (id INT);").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_output_ext(".ddl")
+            .split_file(&input_path)
+            .await?;
+        let output = fs::read_to_string(output_dir.join("split_001.ddl")).await?;
+        assert_eq!(output, "CREATE TABLE t (id INT);");
+
+        let gzip_dir = temp_dir.path().join("gzip");
+        SqlSplitter::new(&gzip_dir, 1000, 2)
+            .with_output_ext("ddl")
+            .with_compression(Compression::Gzip, None)
+            .split_file(&input_path)
+            .await?;
+        assert!(gzip_dir.join("split_001.ddl.gz").is_file());
+
+        let result = SqlSplitter::new(temp_dir.path().join("bad"), 1000, 2)
+            .with_output_ext("../ddl")
+            .split_file(&input_path)
+            .await;
+        assert!(matches!(result, Err(SplitError::InvalidOutputExt(_))));
+        assert!(!temp_dir.path().join("bad").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_by_type_routes_categories() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET NAMES utf8;\n\
+             CREATE TABLE a (id INT);\n\
+             INSERT INTO a VALUES (1);\n\
+             CREATE INDEX a_id ON a (id);\n\
+             UPDATE a SET id = 2;\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_split_by_type(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!((report.files, report.statements), (3, 5));
+
+        let read =
+            |category: &str| fs::read_to_string(output_dir.join(category).join("split_001.sql"));
+        assert_eq!(
+            read("ddl").await?,
+            "CREATE TABLE a (id INT);\n\nCREATE INDEX a_id ON a (id);"
+        );
+        assert_eq!(
+            read("dml").await?,
+            "INSERT INTO a VALUES (1);\n\nUPDATE a SET id = 2;"
+        );
+        assert_eq!(read("other").await?, "SET NAMES utf8;");
+        assert!(!output_dir.join("split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_by_schema_routes_qualified_names() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET client_encoding = 'UTF8';\n\
+             CREATE TABLE sales.orders (id INT);\n\
+             CREATE TABLE \"HR\".staff (id INT);\n\
+             INSERT INTO sales.orders VALUES (1);\n\
+             INSERT INTO hr.staff VALUES (1);\n\
+             CREATE INDEX staff_id ON \"HR\".staff (id);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_by_schema(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!((report.files, report.statements), (3, 6));
+
+        let read = |schema: &str| fs::read_to_string(output_dir.join(schema).join("split_001.sql"));
+        assert_eq!(
+            read("sales").await?,
+            "CREATE TABLE sales.orders (id INT);\n\nINSERT INTO sales.orders VALUES (1);"
+        );
+        assert_eq!(
+            read("hr").await?,
+            "CREATE TABLE \"HR\".staff (id INT);\n\n\
+             INSERT INTO hr.staff VALUES (1);\n\n\
+             CREATE INDEX staff_id ON \"HR\".staff (id);"
+        );
+        assert_eq!(
+            read(DEFAULT_SCHEMA_DIR).await?,
+            "SET client_encoding = 'UTF8';"
+        );
+
+        // Combined with --split-by-type, each schema is divided by type
+        let nested_dir = temp_dir.path().join("nested");
+        SqlSplitter::new(&nested_dir, 1000, 2)
+            .with_by_schema(true)
+            .with_split_by_type(true)
+            .split_file(&input_path)
+            .await?;
+        assert!(nested_dir.join("sales/ddl/split_001.sql").exists());
+        assert!(nested_dir.join("sales/dml/split_001.sql").exists());
+        assert!(nested_dir.join("default/other/split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preview_stops_reading_early() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = Vec::new();
+        for i in 0..50_000 {
+            input.extend_from_slice(format!("INSERT INTO t VALUES ({}, 'é');\n", i).as_bytes());
+        }
+        // Invalid UTF-8 far past the previewed statements is never read
+        input.extend_from_slice(b"SELECT '\xff';\n");
+        fs::write(&input_path, &input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2);
+        let preview = splitter.preview(&input_path, 5).await?;
+        assert_eq!(preview.len(), 5);
+        assert_eq!(preview[4].text, "INSERT INTO t VALUES (4, 'é')");
+        assert_eq!(preview[4].line, 5);
+        assert!(!output_dir.exists());
+
+        // Asking for more statements than the file holds returns them all
+        let small_path = temp_dir.path().join("small.sql");
+        fs::write(&small_path, "SELECT 1; SELECT 2").await?;
+        assert_eq!(splitter.preview(&small_path, 10).await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expand_go_count_repeats_batch() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "INSERT INTO t DEFAULT VALUES\nGO 3\nSELECT COUNT(*) FROM t\nGO\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_boundary_regex(Regex::new(r"\nGO( [0-9]+)?\n")?)
+            .with_preserve_whitespace(true)
+            .with_expand_go_count(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 4);
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "INSERT INTO t DEFAULT VALUES\nGO\n".repeat(3) + "SELECT COUNT(*) FROM t\nGO\n"
+        );
+
+        assert_eq!(go_count("\nGO 0\n"), None);
+        assert_eq!(
+            go_count("\nGO 99999999999999999999999\n").map(|(count, _)| count),
+            Some(u64::MAX)
+        );
+        assert_eq!(
+            go_count(";\r\ngo 2 \r\n"),
+            Some((2, ";\r\ngo \r\n".to_string()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expand_go_count_defaults_to_go_lines() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "INSERT INTO t DEFAULT VALUES\r\ngo 2\r\nSELECT 'GO 5' AS ago\r\n  GO\r\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_expand_go_count(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 3);
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "INSERT INTO t DEFAULT VALUES\ngo\n\n\
+             INSERT INTO t DEFAULT VALUES\ngo\n\n\
+             SELECT 'GO 5' AS ago\n  GO"
+        );
+
+        // A GO on the first line ends an (empty) batch; one ending a word doesn't
+        fs::write(&input_path, "GO\nSELECT ERGO\nGO\n").await?;
+        let output_dir = temp_dir.path().join("first_line");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_expand_go_count(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 1);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SELECT ERGO\nGO"
+        );
+
+        // A count that would exhaust memory fails instead
+        fs::write(&input_path, "SELECT 1\nGO\nSELECT 2\nGO 4000000000\n").await?;
+        let result = SqlSplitter::new(temp_dir.path().join("huge"), 1000, 2)
+            .with_expand_go_count(true)
+            .split_file(&input_path)
+            .await;
+        assert!(
+            matches!(
+                result,
+                Err(SplitError::GoCountTooLarge {
+                    line: 3,
+                    count: 4_000_000_000,
+                    limit: MAX_GO_COUNT,
+                })
+            ),
+            "{:?}",
+            result
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_boundary_regex_splits_on_custom_terminator(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "let x = 1; x + 1;;\n-- not here;;\nlet s = 'a;;b';;\nlet y = 2",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_boundary_regex(Regex::new(";;")?)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 3);
+        assert_eq!(report.unterminated_statements, 1);
+
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "let x = 1; x + 1;;\n\n-- not here;;\nlet s = 'a;;b';;\n\nlet y = 2;"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_drops_repeated_statements() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE INDEX IF NOT EXISTS i ON t (a);\n\
+             INSERT INTO t VALUES (1, 'a  b');\n\
+             INSERT  INTO t\n  VALUES (1, 'a  b');\n\
+             INSERT INTO t VALUES (1, 'a b');\n\
+             CREATE INDEX IF NOT EXISTS i ON t (a);\n",
+        )
+        .await?;
+
+        for mode in [Dedupe::Exact, Dedupe::Hash] {
+            let output_dir = temp_dir.path().join(format!("{:?}", mode));
+            let report = SqlSplitter::new(&output_dir, 1000, 2)
+                .with_dedupe(mode)
+                .split_file(&input_path)
+                .await?;
+            assert_eq!((report.statements, report.duplicate_statements), (3, 2));
+
+            let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+            assert_eq!(
+                output,
+                "CREATE INDEX IF NOT EXISTS i ON t (a);\n\n\
+                 INSERT INTO t VALUES (1, 'a  b');\n\n\
+                 INSERT INTO t VALUES (1, 'a b');"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_variables_in_each_file() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let row = format!("INSERT INTO t VALUES (@run, '{}');", "x".repeat(600));
+        let input = format!(
+            "SET @run := 7;\nSET NAMES utf8;\n{row}\n{row}\nSET @@session.sql_mode = '';\n{row}\n"
+        );
+        fs::write(&input_path, input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_replay_variables(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!((report.files, report.statements), (3, 6));
+
+        let read = |i: usize| fs::read_to_string(output_dir.join(format!("split_{:03}.sql", i)));
+        assert!(read(1)
+            .await?
+            .starts_with("SET @run := 7;\n\nSET NAMES utf8;\n\nINSERT"));
+        assert_eq!(
+            read(2).await?,
+            format!("SET @run := 7;\n\n{row}\n\nSET @@session.sql_mode = '';")
+        );
+        assert_eq!(
+            read(3).await?,
+            format!("SET @run := 7;\n\nSET @@session.sql_mode = '';\n\n{row}")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reference_order_creates_parents_first() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE child (id INT, parent_id INT,\n  \
+             FOREIGN KEY (parent_id) REFERENCES parent (id));\n\
+             INSERT INTO child VALUES (1, 1);\n\
+             CREATE TABLE parent (id INT PRIMARY KEY);\n\
+             INSERT INTO parent VALUES (1);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_reference_order(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.reference_cycles, 0);
+
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "CREATE TABLE parent (id INT PRIMARY KEY);\n\n\
+             INSERT INTO parent VALUES (1);\n\n\
+             CREATE TABLE child (id INT, parent_id INT,\n  \
+             FOREIGN KEY (parent_id) REFERENCES parent (id));\n\n\
+             INSERT INTO child VALUES (1, 1);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_comment_out_drops() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "DROP TABLE IF EXISTS `users`;\n\
+             CREATE TABLE `users` (id INT);\n\
+             INSERT INTO `users` VALUES (1);\n\
+             drop view\n  v1,\n\n  v2;\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_comment_out_drops(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 4);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "-- DROP TABLE IF EXISTS `users`\n\n\
+             CREATE TABLE `users` (id INT);\n\n\
+             INSERT INTO `users` VALUES (1);\n\n\
+             -- drop view\n--   v1,\n\n--   v2"
+        );
+
+        // The comment style is followed, and a block can't end early
+        fs::write(&input_path, "DROP TABLE t /* old */;\nSELECT 1;\n").await?;
+        for (style, expected) in [
+            (CommentStyle::Hash, "# DROP TABLE t /* old */\n\nSELECT 1;"),
+            (
+                CommentStyle::Block,
+                "/* DROP TABLE t / * old * / */\n\nSELECT 1;",
+            ),
+        ] {
+            let output_dir = temp_dir.path().join(format!("{:?}", style));
+            SqlSplitter::new(&output_dir, 1000, 2)
+                .with_dialect(Dialect::Mysql)
+                .with_comment_out_drops(true)
+                .with_comment_style(style)
+                .split_file(&input_path)
+                .await?;
+            assert_eq!(
+                fs::read_to_string(output_dir.join("split_001.sql")).await?,
+                expected
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_comment_out_drops_writes_no_terminator() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+
+        // Cut off at the end of input, under a policy that adds the `;`
+        fs::write(&input_path, "SELECT 1;\nDROP TABLE\n  t -- gone").await?;
+        let output_dir = temp_dir.path().join("eof");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_comment_out_drops(true)
+            .with_trailing_delimiter(TrailingDelimiter::Always)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SELECT 1;\n\n-- DROP TABLE\n--   t -- gone\n"
+        );
+
+        // Ended by a custom DELIMITER, which isn't re-emitted around it
+        fs::write(
+            &input_path,
+            "DELIMITER $$\nDROP PROCEDURE p$$\nCREATE PROCEDURE p() BEGIN SELECT 1; END$$\n\
+             DELIMITER ;\n",
+        )
+        .await?;
+        let output_dir = temp_dir.path().join("delimiter");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_dialect(Dialect::Mysql)
+            .with_comment_out_drops(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "-- DROP PROCEDURE p\n\n\
+             DELIMITER $$\nCREATE PROCEDURE p() BEGIN SELECT 1; END$$\nDELIMITER ;"
+        );
+
+        // With whitespace kept, a statement on the same line isn't swallowed
+        fs::write(&input_path, "DROP TABLE a; SELECT 1;").await?;
+        let output_dir = temp_dir.path().join("preserved");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_comment_out_drops(true)
+            .with_preserve_whitespace(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "-- DROP TABLE a\n SELECT 1;"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_drop_table_precedes_creates() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET NAMES utf8;\n\
+             CREATE TABLE `users` (id INT);\n\
+             INSERT INTO `users` VALUES (1);\n\
+             CREATE INDEX users_id ON users (id);\n\
+             CREATE TABLE IF NOT EXISTS shop.orders (id INT);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_add_drop_table(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 7);
+
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "SET NAMES utf8;\n\n\
+             DROP TABLE IF EXISTS `users`;\n\n\
+             CREATE TABLE `users` (id INT);\n\n\
+             INSERT INTO `users` VALUES (1);\n\n\
+             CREATE INDEX users_id ON users (id);\n\n\
+             DROP TABLE IF EXISTS shop.orders;\n\n\
+             CREATE TABLE IF NOT EXISTS shop.orders (id INT);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sequential_runs_are_byte_identical() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = String::from("CREATE TABLE t (v TEXT);\n");
+        for i in 0..200 {
+            input.push_str(&format!(
+                "INSERT INTO t VALUES ('{}');\n",
+                "x".repeat(i % 90)
+            ));
+        }
+        fs::write(&input_path, &input).await?;
+
+        let mut runs = Vec::new();
+        for run in ["first", "second"] {
+            let output_dir = temp_dir.path().join(run);
+            let report = SqlSplitter::new(&output_dir, 1, 8)
+                .with_sequential(true)
+                .split_file(&input_path)
+                .await?;
+            let mut files = Vec::new();
+            for i in 1..=report.files {
+                let name = format!("split_{:03}.sql", i);
+                files.push((name.clone(), fs::read(output_dir.join(name)).await?));
+            }
+            runs.push(files);
+        }
+        assert!(runs[0].len() > 1);
+        assert_eq!(runs[0], runs[1]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "parallel")]
+    #[tokio::test]
+    async fn test_parallel_scan_writes_same_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = String::new();
+        for i in 0..60_000 {
+            input.push_str(&format!("INSERT INTO t VALUES ({}, 'a;\nb');\n", i));
+        }
+        fs::write(&input_path, &input).await?;
+        assert!(input.len() > 2 * parallel::MIN_SEGMENT_BYTES);
+
+        let serial_dir = temp_dir.path().join("serial");
+        let serial = SqlSplitter::new(&serial_dir, 100, 4)
+            .split_file(&input_path)
+            .await?;
+        let parallel_dir = temp_dir.path().join("parallel");
+        let parallel = SqlSplitter::new(&parallel_dir, 100, 4)
+            .with_parallel_scan(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(parallel.statements, serial.statements);
+        assert_eq!(parallel.files, serial.files);
+        for i in 1..=serial.files {
+            let name = format!("split_{:03}.sql", i);
+            assert_eq!(
+                fs::read(parallel_dir.join(&name)).await?,
+                fs::read(serial_dir.join(&name)).await?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_extract_header_moves_banner() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "-- MySQL dump 10.13\n\
+             --\n\
+             -- Host: localhost\n\
+             /* generated\n   nightly */\n\n\
+             CREATE TABLE t (id INT);\n\
+             -- rows\n\
+             INSERT INTO t VALUES (1);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let source_map = temp_dir.path().join("map.tsv");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_extract_header(true)
+            .with_source_map(&source_map)
+            .split_file(&input_path)
+            .await?;
+
+        let header = fs::read_to_string(output_dir.join("header.sql")).await?;
+        assert_eq!(
+            header,
+            "-- MySQL dump 10.13\n--\n-- Host: localhost\n/* generated\n   nightly */"
+        );
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "CREATE TABLE t (id INT);\n\n-- rows\nINSERT INTO t VALUES (1);"
+        );
+        let map = fs::read_to_string(&source_map).await?;
+        assert!(map.contains("split_001.sql\t1\t7\n"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exactly_one_terminator_per_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SELECT 1 -- one;\n;\nSELECT 2;\nSELECT 3 -- three",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2);
+        splitter.split_file(&input_path).await?;
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "SELECT 1 -- one;\n;\n\nSELECT 2;\n\nSELECT 3 -- three\n;"
+        );
+
+        // Statements that already carry their `;` don't gain a second one
+        let statement = |text: &str| LocatedStatement {
+            text: text.to_string(),
+            line: 1,
+            data: None,
+            delimiter: None,
+            boundary: None,
+            terminated: true,
+        };
+        let path = temp_dir.path().join("terminated.sql");
+        let open_files = OpenFiles::new(None);
+        splitter
+            .write_sql_file(
+                1,
+                vec![statement("SELECT 1;"), statement("SELECT 2")],
+                path.clone(),
+                &open_files,
+            )
+            .await?;
+        let output = fs::read_to_string(&path).await?;
+        assert_eq!(output, "SELECT 1;\n\nSELECT 2;");
+        assert!(!output.contains(";;"));
+
+        splitter
+            .clone()
+            .with_trim_trailing_semicolons(false)
+            .write_sql_file(1, vec![statement("SELECT 1;")], path.clone(), &open_files)
+            .await?;
+        assert_eq!(fs::read_to_string(&path).await?, "SELECT 1;;");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_report_tracks_largest_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let big = format!("INSERT INTO t VALUES ('{}')", "x".repeat(5000));
+        fs::write(
+            &input_path,
+            format!("SELECT 1;\n-- bulk rows\n\n{};\nSELECT 2;\n", big),
+        )
+        .await?;
+
+        let report = SqlSplitter::new(temp_dir.path().join("out"), 1, 2)
+            .split_file(&input_path)
+            .await?;
+        // The leading comment belongs to the statement and counts towards it
+        assert_eq!(
+            report.largest_statement_bytes,
+            "-- bulk rows\n\n".len() + big.len() + 1
+        );
+        assert_eq!(report.largest_statement_line, 2);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pipe_to_feeds_each_batch() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let row = format!("INSERT INTO t VALUES ('{}');\n", "x".repeat(600));
+        fs::write(&input_path, row.repeat(3)).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_pipe_to("cat > \"$SQL_SPLITTER_FILE.piped\"")
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.files, 3);
+        for i in 1..=3 {
+            let file = output_dir.join(format!("split_{:03}.sql", i));
+            let piped = output_dir.join(format!("split_{:03}.sql.piped", i));
+            assert_eq!(fs::read(&piped).await?, fs::read(&file).await?);
+        }
+
+        let result = SqlSplitter::new(temp_dir.path().join("failing"), 1000, 2)
+            .with_pipe_to("cat > /dev/null; exit 3")
+            .split_file(&input_path)
+            .await;
+        match result {
+            Err(SplitError::CommandFailed { status, .. }) => assert_eq!(status.code(), Some(3)),
+            other => panic!("expected CommandFailed, got {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manifest_flush_snapshots_progress() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let row = format!("INSERT INTO t VALUES ('{}');\n", "x".repeat(600));
+        fs::write(&input_path, row.repeat(5)).await?;
+
+        // The pipe runs as each file is written, before it counts as
+        // complete, so it sees the manifest as of the previous flush
+        let output_dir = temp_dir.path().join("out");
+        let manifest_path = temp_dir.path().join("manifest.csv");
+        let report = SqlSplitter::new(&output_dir, 1, 4)
+            .with_sequential(true)
+            .with_manifest_flush(&manifest_path, ManifestFormat::Csv, 2)
+            .with_pipe_to(format!(
+                "cat > /dev/null; cp {} \"$SQL_SPLITTER_FILE.manifest\" 2>/dev/null || true",
+                manifest_path.display()
+            ))
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.files, 5);
+
+        let listed = |manifest: &str| -> Vec<String> {
+            manifest
+                .lines()
+                .skip(1)
+                .map(|row| row.split(',').next().unwrap().to_string())
+                .collect()
+        };
+        let file = |i: usize| output_dir.join(format!("split_{:03}.sql", i));
+        let names = |range: std::ops::RangeInclusive<usize>| -> Vec<String> {
+            range.map(|i| file(i).display().to_string()).collect()
+        };
+        for i in 1..=2 {
+            let snapshot = output_dir.join(format!("split_{:03}.sql.manifest", i));
+            assert!(!snapshot.exists(), "no manifest before the first flush");
+        }
+        for (i, expected) in [(3, names(1..=2)), (4, names(1..=2)), (5, names(1..=4))] {
+            let snapshot = output_dir.join(format!("split_{:03}.sql.manifest", i));
+            assert_eq!(listed(&fs::read_to_string(snapshot).await?), expected);
+        }
+
+        // The final flush lists every file, as the end-of-run manifest does
+        assert_eq!(
+            fs::read_to_string(&manifest_path).await?,
+            manifest::manifest(&report, ManifestFormat::Csv)
+        );
+        assert!(!temp_dir.path().join("manifest.csv.partial").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_writes_only_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let row = format!("INSERT INTO t VALUES ('{}');\n", "x".repeat(600));
+        fs::write(&input_path, row.repeat(5)).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let manifest_path = temp_dir.path().join("plan.json");
+        let splitter = SqlSplitter::new(&output_dir, 1, 2)
+            .with_compression(Compression::Gzip, None)
+            .with_embed_checksum(true);
+        let plan = splitter
+            .clone()
+            .with_dry_run(true)
+            .with_manifest_flush(&manifest_path, ManifestFormat::Json, 1)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(plan.files, 5);
+        assert!(manifest_path.exists());
+        assert!(
+            !output_dir.exists(),
+            "a dry run writes no split_*.sql files"
+        );
+        assert_eq!(
+            fs::read_to_string(&manifest_path).await?,
+            manifest::manifest(&plan, ManifestFormat::Json)
+        );
+
+        // The projected sizes are those a real run writes
+        let report = splitter.split_file(&input_path).await?;
+        assert_eq!(plan.output_files, report.output_files);
+        assert_eq!(plan.output_file_stats, report.output_file_stats);
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn test_output_dir_beyond_max_path() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE s.t (id INT);\nINSERT INTO s.t VALUES (1);\n",
+        )
+        .await?;
+
+        let mut output_dir = temp_dir.path().to_path_buf();
+        for i in 0..12 {
+            output_dir.push(format!("nested_output_directory_{}", i));
+        }
+        assert!(output_dir.as_os_str().len() > 260);
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_by_schema(true)
+            .with_split_by_type(true)
+            .split_file(&input_path)
+            .await?;
+
+        // Reported paths stay as given; they open through the prefix
+        assert_eq!(report.files, 2);
+        for path in &report.output_files {
+            assert!(path.starts_with(&output_dir));
+            let content = fs::read_to_string(long_path::extended(path)).await?;
+            assert!(content.contains("s.t"));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_csv_into_inserts() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("items.csv");
+        fs::write(&input_path, "id,name\n1,apple\n2,\"pear, ripe\"\n3,plum\n").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        // Room for two of the ~55-byte statements per file
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_max_size(120)
+            .split_csv(&input_path, "items")
+            .await?;
+        assert_eq!(report.files, 2);
+        assert_eq!(report.statements, 3);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "INSERT INTO items (id, name) VALUES ('1', 'apple');\n\n\
+             INSERT INTO items (id, name) VALUES ('2', 'pear, ripe');"
+        );
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_002.sql")).await?,
+            "INSERT INTO items (id, name) VALUES ('3', 'plum');"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keep_and_drop_matching() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET NAMES utf8;\n\
+             CREATE TABLE a (id INT);\n\
+             INSERT INTO a VALUES (1);\n\
+             CREATE   INDEX a_id ON a (id);\n\
+             CREATE VIEW v AS SELECT * FROM a;\n\
+             \n  create table b (id INT);\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_keep_matching(Regex::new("^CREATE (TABLE|INDEX)")?)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "CREATE TABLE a (id INT);"
+        );
+
+        // Normalized and case-insensitive, minus what --drop-matching removes
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_keep_matching(Regex::new("(?i)^CREATE (TABLE|INDEX)")?)
+            .with_drop_matching(Regex::new(r"\bb\b")?)
+            .with_match_normalized(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "CREATE TABLE a (id INT);\n\nCREATE   INDEX a_id ON a (id);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_report_lists_structured_warnings() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE DEFINER=`root`@`%` VIEW v AS SELECT 1;\n\
+             INSERT INTO t VALUES ('naïve');\n\
+             SELECT (1;\n\
+             ;\n\
+             INSERT INTO t VALUES ('xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx');\n",
+        )
+        .await?;
+
+        let report = SqlSplitter::new(temp_dir.path().join("out"), 1, 2)
+            .with_max_size(60)
+            .with_strip_definer(true)
+            .with_ascii(AsciiMode::Warn)
+            .with_validate_sql(true)
+            .split_file(&input_path)
+            .await?;
+        let warnings: Vec<(WarningKind, Option<usize>)> = report
+            .warnings
+            .iter()
+            .map(|warning| (warning.kind, warning.line))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (WarningKind::EmptyStatements, None),
+                (WarningKind::NonAscii, Some(2)),
+                (WarningKind::StrippedDefiner, Some(1)),
+                (WarningKind::Malformed, Some(3)),
+                (WarningKind::OversizedStatement, Some(5)),
+            ]
+        );
+        assert_eq!(
+            report.warnings[4].to_string(),
+            "statement on line 5 is 76 bytes, over the 60 byte file size limit"
+        );
+        // Stripping was asked for, so it doesn't fail --fail-on-warnings
+        assert_eq!(report.warning_summary().len(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_done_marker_only_after_success() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT 2;\n").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let marker = output_dir.join(".done");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2).with_done_marker(".done");
+        splitter.split_file(&input_path).await?;
+        assert!(!marker.exists(), "not before the caller says so");
+        splitter.mark_done().await?;
+        assert_eq!(fs::read(&marker).await?, b"");
+
+        // A failed rerun leaves no marker from the earlier success
+        fs::write(&input_path, "SELECT 1;\nSELECT 'unclosed;\n").await?;
+        assert!(splitter
+            .clone()
+            .with_strict(true)
+            .split_file(&input_path)
+            .await
+            .is_err());
+        assert!(!marker.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fail_on_warnings() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let clean_path = temp_dir.path().join("clean.sql");
+        fs::write(&clean_path, "SELECT 1;\nSELECT 2;\n").await?;
+        let warning_path = temp_dir.path().join("warning.sql");
+        fs::write(&warning_path, "SELECT 1;;\nSELECT 2").await?;
+
+        let splitter =
+            SqlSplitter::new(temp_dir.path().join("out"), 1000, 2).with_fail_on_warnings(true);
+        assert_eq!(splitter.split_file(&clean_path).await?.statements, 2);
+        match splitter.split_file(&warning_path).await {
+            Err(SplitError::Warnings(warnings)) => assert_eq!(
+                warnings,
+                vec![
+                    "1 empty statement(s) skipped",
+                    "1 statement(s) lacked a trailing delimiter"
+                ]
+            ),
+            other => panic!("expected warnings, got {:?}", other),
+        }
+
+        let oversized_path = temp_dir.path().join("oversized.sql");
+        fs::write(&oversized_path, format!("SELECT '{}';", "x".repeat(2000))).await?;
+        let splitter =
+            SqlSplitter::new(temp_dir.path().join("big"), 1, 2).with_fail_on_warnings(true);
+        assert!(matches!(
+            splitter.split_file(&oversized_path).await,
+            Err(SplitError::Warnings(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_output_encoding_writes_latin1() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "INSERT INTO t VALUES ('Café', 'naïve');\n\
+             COPY t (name) FROM stdin;\nJosé\n\\.\n",
+        )
+        .await?;
+
+        let latin1 = encoding::parse_encoding("latin1")?;
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_output_encoding(latin1)
+            .split_file(&input_path)
+            .await?;
+        let output = fs::read(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            b"INSERT INTO t VALUES ('Caf\xe9', 'na\xefve');\n\n\
+              COPY t (name) FROM stdin;\nJos\xe9\n\\."
+        );
+
+        fs::write(&input_path, "SELECT 'ok';\nSELECT '€ ≠ £';\n").await?;
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_output_encoding(encoding::parse_encoding("iso-8859-15")?)
+            .split_file(&input_path)
+            .await?;
+        let output = fs::read(output_dir.join("split_001.sql")).await?;
+        assert_eq!(output, b"SELECT 'ok';\n\nSELECT '\xa4 ? \xa3';");
+
+        let strict_dir = temp_dir.path().join("strict");
+        let result = SqlSplitter::new(&strict_dir, 1000, 2)
+            .with_output_encoding(latin1)
+            .with_on_unmappable(OnUnmappable::Error)
+            .split_file(&input_path)
+            .await;
+        match result {
+            Err(SplitError::Unmappable {
+                line, character, ..
+            }) => assert_eq!((line, character), (2, '≠')),
+            other => panic!("expected Unmappable, got {:?}", other),
+        }
+        assert!(!strict_dir.join("split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ascii_modes() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let input = "SELECT 1;\nINSERT INTO t VALUES ('Café');\n\
+                     COPY t (v) FROM stdin;\nnaïve\n\\.\n";
+        fs::write(&input_path, input).await?;
+
+        match SqlSplitter::new(temp_dir.path().join("error"), 1000, 2)
+            .with_ascii(AsciiMode::Error)
+            .split_file(&input_path)
+            .await
+        {
+            Err(SplitError::NonAscii { line, character }) => {
+                assert_eq!((line, character), (2, 'é'))
+            }
+            other => panic!("expected a non-ASCII error, got {:?}", other),
+        }
+        assert!(!temp_dir.path().join("error/split_001.sql").exists());
+
+        let warn_dir = temp_dir.path().join("warn");
+        let report = SqlSplitter::new(&warn_dir, 1000, 2)
+            .with_ascii(AsciiMode::Warn)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.non_ascii_statements, 2);
+        assert_eq!(
+            report.warning_summary(),
+            vec!["2 statement(s) contained non-ASCII characters"]
+        );
+        let output = fs::read_to_string(warn_dir.join("split_001.sql")).await?;
+        assert!(output.contains("'Café'") && output.contains("naïve"));
+
+        let ascii_dir = temp_dir.path().join("ascii");
+        let report = SqlSplitter::new(&ascii_dir, 1000, 2)
+            .with_ascii(AsciiMode::Transliterate)
+            .split_file(&input_path)
+            .await?;
+        assert!(report.warning_summary().is_empty());
+        let output = fs::read_to_string(ascii_dir.join("split_001.sql")).await?;
+        assert!(output.is_ascii());
+        assert_eq!(
+            output,
+            "SELECT 1;\n\nINSERT INTO t VALUES ('Cafe');\n\n\
+             COPY t (v) FROM stdin;\nnaive\n\\."
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_rows_spreads_one_block_across_files(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let rows: Vec<String> = (1..=10_000)
+            .map(|i| format!("({},'row {}')", i, i))
+            .collect();
+        fs::write(
+            &input_path,
+            format!("INSERT INTO t VALUES {};\n", rows.join(",")),
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 16, 4)
+            .with_split_rows(true)
+            .split_file(&input_path)
+            .await?;
+        assert!(report.files > 1);
+        assert_eq!(report.oversized_statements, 0);
+        let mut loaded = Vec::new();
+        for path in &report.output_files {
+            let content = fs::read_to_string(path).await?;
+            assert!(content.len() <= 16 * 1024);
+            for statement in SqlSplitter::split_statements(&content) {
+                let values = crate::values::parse_values(&statement).expect("an INSERT");
+                assert_eq!(values.head, "INSERT INTO t VALUES");
+                loaded.extend(values.rows.iter().map(|row| row.to_string()));
+            }
+        }
+        assert_eq!(loaded, rows);
+
+        // COPY data cut by row count instead
+        let copy_path = temp_dir.path().join("copy.sql");
+        let data: String = (1..=10_000).map(|i| format!("{}\tv{}\n", i, i)).collect();
+        fs::write(
+            &copy_path,
+            format!("COPY t (id, v) FROM stdin;\n{}\\.\n", data),
+        )
+        .await?;
+        let copy_dir = temp_dir.path().join("copy");
+        let report = SqlSplitter::new(&copy_dir, 1000, 4)
+            .with_max_rows_per_insert(3000)
+            .split_file(&copy_path)
+            .await?;
+        assert_eq!(report.statements, 4);
+        let content = fs::read_to_string(copy_dir.join("split_001.sql")).await?;
+        assert_eq!(content.matches("COPY t (id, v) FROM stdin;\n").count(), 4);
+        assert_eq!(content.matches("\\.").count(), 4);
+        assert!(content.ends_with("9999\tv9999\n10000\tv10000\n\\."));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validate_sql_flags_malformed_statement() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE t (id INT);\n\
+             INSERT INTO t VALUES (1), (2;\n\
+             SELEC id FROM t;\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_validate_sql(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            report.warning_summary(),
+            vec![
+                "statement on line 2 has unbalanced parentheses (--validate-sql)",
+                "statement on line 3 starts with \"SELEC\", not a SQL statement verb \
+                 (--validate-sql)"
+            ]
+        );
+        assert_eq!(report.statements, 3);
+
+        let strict_dir = temp_dir.path().join("strict");
+        let result = SqlSplitter::new(&strict_dir, 1000, 2)
+            .with_validate_sql(true)
+            .with_strict(true)
+            .split_file(&input_path)
+            .await;
+        match result {
+            Err(SplitError::Malformed(malformed)) => {
+                let lines: Vec<usize> = malformed.iter().map(|m| m.line).collect();
+                assert_eq!(lines, vec![2, 3]);
+            }
+            other => panic!("expected Malformed, got {:?}", other),
+        }
+        assert!(!strict_dir.join("split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_escape_validation_flags_escaped_closing_quote(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "INSERT INTO t VALUES ('it\\'s');\n\
+             INSERT INTO t VALUES\n('C:\\dir\\', 1);\n\
+             SELECT 2;\n\
+             INSERT INTO t VALUES ('done');\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_escape_validation(true)
+            .split_file(&input_path)
+            .await?;
+        // The escaped quote swallowed the delimiters up to the next quote
+        assert_eq!(report.statements, 2);
+        assert_eq!(
+            report.suspicious_escapes,
+            vec![SuspiciousEscape {
+                line: 3,
+                problem: escapes::EscapeProblem::EscapedClosingQuote,
+            }]
+        );
+        // ...and the string it opened in the last statement runs to the end
+        assert!(report.warning_summary().contains(
+            &"backslash on line 3 escapes a quote that looks like it ends the string \
+              (--escape-validation)"
+                .to_string()
+        ));
+
+        let report = SqlSplitter::new(temp_dir.path().join("off"), 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        assert!(report.suspicious_escapes.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strict_rejects_unclosed_string() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT 'it;\n").await?;
+
+        let output_dir = temp_dir.path().join("lenient");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.unclosed, vec![Unclosed::String { line: 2 }]);
+        assert_eq!(
+            report.warning_summary(),
+            vec![
+                "1 statement(s) lacked a trailing delimiter",
+                "input ended inside a string literal opened on line 2"
+            ]
+        );
+        let content = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert!(content.ends_with("SELECT 'it;"));
+
+        let strict_dir = temp_dir.path().join("strict");
+        let result = SqlSplitter::new(&strict_dir, 1000, 2)
+            .with_strict(true)
+            .split_file(&input_path)
+            .await;
+        match result {
+            Err(SplitError::Unclosed(unclosed)) => {
+                assert_eq!(unclosed, vec![Unclosed::String { line: 2 }])
+            }
+            other => panic!("expected Unclosed, got {:?}", other),
+        }
+        assert!(!strict_dir.join("split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trailing_statement_warn() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "INSERT INTO t VALUES (1);\nINSERT INTO t VALUES (2),\n(3)",
+        )
+        .await?;
+
+        let report = SqlSplitter::new(temp_dir.path().join("plain"), 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.unterminated_statements, 1);
+        assert!(!report
+            .warnings
+            .iter()
+            .any(|warning| warning.kind == WarningKind::UnterminatedLastStatement));
+
+        let report = SqlSplitter::new(temp_dir.path().join("warn"), 1000, 2)
+            .with_trailing_statement_warn(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 2);
+        assert!(report.warnings.contains(&Warning {
+            kind: WarningKind::UnterminatedLastStatement,
+            message: "the last statement, starting on line 2, has no delimiter (truncated input?)"
+                .to_string(),
+            line: Some(2),
+        }));
+
+        let strict_dir = temp_dir.path().join("strict");
+        let result = SqlSplitter::new(&strict_dir, 1000, 2)
+            .with_trailing_statement_warn(true)
+            .with_strict(true)
+            .split_file(&input_path)
+            .await;
+        assert!(matches!(
+            result,
+            Err(SplitError::UnterminatedLastStatement { line: 2 })
+        ));
+        assert!(!strict_dir.join("split_001.sql").exists());
+
+        fs::write(&input_path, "INSERT INTO t VALUES (1);\n").await?;
+        SqlSplitter::new(temp_dir.path().join("terminated"), 1000, 2)
+            .with_trailing_statement_warn(true)
+            .with_strict(true)
+            .split_file(&input_path)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_final_newline() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT 2").await?;
+
+        let default_dir = temp_dir.path().join("default");
+        SqlSplitter::new(&default_dir, 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        let content = fs::read(default_dir.join("split_001.sql")).await?;
+        assert_eq!(content.last(), Some(&b';'));
+
+        let newline_dir = temp_dir.path().join("newline");
+        SqlSplitter::new(&newline_dir, 1000, 2)
+            .with_final_newline(true)
+            .split_file(&input_path)
+            .await?;
+        let content = fs::read_to_string(newline_dir.join("split_001.sql")).await?;
+        assert!(content.ends_with("SELECT 2;\n"));
+        assert!(!content.ends_with("\n\n"));
+
+        // Two statements and their separator fill 20 bytes exactly, leaving
+        // no room for the newline
+        fs::write(&input_path, "SELECT 1;SELECT 2;SELECT 3;").await?;
+        let limited_dir = temp_dir.path().join("limited");
+        let report = SqlSplitter::new(&limited_dir, 1000, 2)
+            .with_max_size(20)
+            .with_final_newline(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.files, 3);
+        for path in &report.output_files {
+            assert!(fs::metadata(path).await?.len() <= 20);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_embed_checksum_matches_statements() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT 'two';\n").await?;
+
+        let plain_dir = temp_dir.path().join("plain");
+        SqlSplitter::new(&plain_dir, 1000, 2)
+            .split_file(&input_path)
+            .await?;
+        let statements = fs::read_to_string(plain_dir.join("split_001.sql")).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_embed_checksum(true)
+            .split_file(&input_path)
+            .await?;
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        // The comment is the last line, after a newline of its own
+        let (content, comment) = output
+            .strip_suffix('\n')
+            .and_then(|body| body.rsplit_once('\n'))
+            .expect("checksum line");
+        assert_eq!(content, statements);
+        let digest = comment
+            .strip_prefix("-- ")
+            .and_then(|text| text.strip_prefix(CHECKSUM_LABEL))
+            .expect("checksum comment");
+        assert_eq!(
+            digest,
+            format!("{:x}", Sha256::digest(statements.as_bytes()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_embed_checksum_stays_within_max_size() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let rows: String = (0..50)
+            .map(|i| format!("INSERT INTO t VALUES ({}, '{}');\n", i, "x".repeat(80)))
+            .collect();
+        fs::write(&input_path, rows).await?;
+
+        for style in [CommentStyle::Dash, CommentStyle::Block] {
+            let output_dir = temp_dir.path().join(format!("{:?}", style));
+            let report = SqlSplitter::new(&output_dir, 1, 2)
+                .with_embed_checksum(true)
+                .with_comment_style(style)
+                .split_file(&input_path)
+                .await?;
+            assert!(report.files > 1);
+            for path in &report.output_files {
+                let size = fs::metadata(path).await?.len();
+                assert!(size <= 1024, "{}: {} bytes", path.display(), size);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strip_definer_from_view() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "INSERT INTO t VALUES ('DEFINER=`root`@`localhost`');\n\
+             /*!50001 CREATE ALGORITHM=UNDEFINED */\n\
+             /*!50013 DEFINER=`root`@`localhost` SQL SECURITY DEFINER */\n\
+             /*!50001 VIEW `v` AS select `t`.`a` AS `a` from `t` */;\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_dialect(Dialect::Mysql)
+            .with_strip_definer(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "INSERT INTO t VALUES ('DEFINER=`root`@`localhost`');\n\n\
+             /*!50001 CREATE ALGORITHM=UNDEFINED */\n\
+             /*!50013 SQL SECURITY DEFINER */\n\
+             /*!50001 VIEW `v` AS select `t`.`a` AS `a` from `t` */;"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replace_table_prefix_spares_data() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE wp_posts (id INT, author INT REFERENCES wp_users (id));
+             INSERT INTO `wp_posts` VALUES (1, 'moved from wp_posts');
+             INSERT INTO users VALUES (1);
+",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_replace_table_prefix("wp_", "blog_")
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "CREATE TABLE blog_posts (id INT, author INT REFERENCES blog_users (id));\n\n\
+             INSERT INTO `blog_posts` VALUES (1, 'moved from wp_posts');\n\n\
+             INSERT INTO users VALUES (1);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expand_includes_inlines_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        fs::write(
+            temp_dir.path().join("tables.sql"),
+            "CREATE TABLE t (id INT);\nCREATE TABLE u (id INT);\n",
+        )
+        .await?;
+        let input_path = temp_dir.path().join("main.sql");
+        fs::write(&input_path, "SELECT 1;\nsource tables.sql\nSELECT 2;\n").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_dialect(Dialect::Mysql)
+            .with_expand_includes(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 4);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SELECT 1;\n\nCREATE TABLE t (id INT);\n\nCREATE TABLE u (id INT);\n\nSELECT 2;"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_count_statements() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SELECT 1;\n-- SELECT 2;\nSELECT ';';;\nINSERT INTO t VALUES (1)",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2);
+        assert_eq!(splitter.count_statements(&input_path).await?, 3);
+        assert!(!output_dir.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_count_statements_matches_split() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        fs::write(
+            temp_dir.path().join("tables.sql"),
+            "CREATE TABLE t (id INT);\nCOPY t FROM stdin;\n1\n2\n\\.\n",
+        )
+        .await?;
+        let input_path = temp_dir.path().join("main.sql");
+        fs::write(&input_path, "SELECT 1;\nsource tables.sql\nSELECT 2;\n").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_dialect(Dialect::Mysql)
+            .with_expand_includes(true);
+        assert_eq!(splitter.count_statements(&input_path).await?, 4);
+        assert_eq!(splitter.split_file(&input_path).await?.statements, 4);
+
+        fs::write(&input_path, "SELECT 1\nGO 3\nSELECT 2\nGO\n").await?;
+        let splitter = SqlSplitter::new(&output_dir, 1000, 2).with_expand_go_count(true);
+        assert_eq!(splitter.count_statements(&input_path).await?, 4);
+        assert_eq!(splitter.split_file(&input_path).await?.statements, 4);
+
+        fs::write(&input_path, "SELECT 1\nGO 10001\n").await?;
+        assert!(matches!(
+            splitter.count_statements(&input_path).await,
+            Err(SplitError::GoCountTooLarge { line: 1, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_obfuscate_masks_data_only() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "CREATE TABLE users (name VARCHAR(20) DEFAULT 'anon');\n\
+             INSERT INTO users (id, name) VALUES (42, 'Ada; Lovelace'), (7, 'Bob');\n\
+             COPY users (id, name) FROM stdin;\n3\tCy\n\\.\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_obfuscate_strings(true)
+            .with_obfuscate_numbers(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 3);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "CREATE TABLE users (name VARCHAR(20) DEFAULT 'anon');\n\n\
+             INSERT INTO users (id, name) VALUES (00, 'xxxxxxxxxxxxx'), (0, 'xxx');\n\n\
+             COPY users (id, name) FROM stdin;\n0\txx\n\\."
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spread_dirs_alternate_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SELECT 1; SELECT 2; SELECT 3; SELECT 4; SELECT 5;",
+        )
+        .await?;
+
+        let disk1 = temp_dir.path().join("disk1");
+        let disk2 = temp_dir.path().join("disk2");
+        let report = SqlSplitter::new(&disk1, 1000, 2)
+            .with_max_size(10)
+            .with_spread_dirs([&disk2])
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            report.output_files,
+            vec![
+                disk1.join("split_001.sql"),
+                disk2.join("split_002.sql"),
+                disk1.join("split_003.sql"),
+                disk2.join("split_004.sql"),
+                disk1.join("split_005.sql"),
+            ]
+        );
+        for (i, path) in report.output_files.iter().enumerate() {
+            assert_eq!(
+                fs::read_to_string(path).await?,
+                format!("SELECT {};", i + 1)
+            );
+        }
+        assert!(!disk1.join("split_002.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_normalize_semicolons() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "DELIMITER $$\nCREATE PROCEDURE p() BEGIN SELECT ';;'; ; SELECT 1;; END$$\n;;$$\n\
+             DELIMITER ;\nSELECT 2;;\n; ;\n",
+        )
+        .await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_dialect(Dialect::Mysql)
+            .with_normalize_semicolons(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 2);
+        assert_eq!(report.empty_segments, 4);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "DELIMITER $$\nCREATE PROCEDURE p() BEGIN SELECT ';;'; SELECT 1; END$$\nDELIMITER ;\n\n\
+             SELECT 2;"
+        );
+
+        let boundary_path = temp_dir.path().join("boundary.sql");
+        fs::write(&boundary_path, "SELECT 1; ;\nGO\nSELECT 2;\nGO\n").await?;
+        let output_dir = temp_dir.path().join("boundary");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_boundary_regex(Regex::new(r"\nGO\n")?)
+            .with_normalize_semicolons(true)
+            .split_file(&boundary_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_001.sql")).await?,
+            "SELECT 1;\nGO\n\n\nSELECT 2;\nGO\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_progress_reports_each_file() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT 2;\nSELECT 3;\n").await?;
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        SqlSplitter::new(temp_dir.path().join("out"), 1, 1)
+            .with_max_size(10)
+            .with_progress(move |progress| sink.lock().unwrap().push(progress))
+            .split_file(&input_path)
+            .await?;
+        let seen = seen.lock().unwrap();
+        let counts: Vec<(usize, usize, u64, u64)> = seen
+            .iter()
+            .map(|p| (p.files_done, p.files, p.bytes_done, p.bytes))
+            .collect();
+        assert_eq!(counts, vec![(1, 3, 9, 27), (2, 3, 18, 27), (3, 3, 27, 27)]);
+        assert_eq!(seen[2].eta, Some(Duration::ZERO));
+
+        Ok(())
+    }
+
+    /// A writer that never accepts a byte, like a hung network mount.
+    struct StalledWriter;
+
+    impl AsyncWrite for StalledWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Pending
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Pending
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Pending
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_timeout_fails_stalled_write() -> Result<(), Box<dyn std::error::Error>> {
+        let statements = vec![LocatedStatement {
+            text: "SELECT 1".to_string(),
+            line: 1,
+            data: None,
+            delimiter: None,
+            boundary: None,
+            terminated: true,
+        }];
+        let path = PathBuf::from("out/split_003.sql");
+        let splitter =
+            SqlSplitter::new("out", 1000, 2).with_write_timeout(Duration::from_millis(50));
+        let start = Instant::now();
+        let result = splitter
+            .within_write_timeout(3, &path, splitter.write_content(&statements, StalledWriter))
+            .await;
+        match result {
+            Err(SplitError::WriteTimeout {
+                index,
+                path: timed_out,
+                timeout,
+            }) => {
+                assert_eq!(
+                    (index, timed_out, timeout),
+                    (3, path.clone(), Duration::from_millis(50))
+                );
+            }
+            other => panic!("expected WriteTimeout, got {:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Writes that finish in time are unaffected
+        let counter = ByteCounter::default();
+        splitter
+            .within_write_timeout(
+                1,
+                &path,
+                splitter.write_content(&statements, counter.clone()),
+            )
+            .await?;
+        assert_eq!(counter.0.load(Ordering::SeqCst), "SELECT 1;".len() as u64);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_timeout_keeps_going_past_stalled_batch(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1; SELECT 2; SELECT 3;").await?;
+
+        // The command for split_002 never exits, so its write never finishes
+        let output_dir = temp_dir.path().join("out");
+        let pid_path = output_dir.join("split_002.sql.pid");
+        let result = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_max_size(10)
+            .with_pipe_to(
+                "cat > /dev/null; case \"$SQL_SPLITTER_FILE\" in \
+                 *split_002.sql) echo $$ > \"$SQL_SPLITTER_FILE.pid\"; exec sleep 30;; esac",
+            )
+            .with_write_timeout(Duration::from_secs(1))
+            .with_keep_going(true)
+            .split_file(&input_path)
+            .await;
+        match result {
+            Err(SplitError::WritesFailed(failed)) => {
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].0, output_dir.join("split_002.sql"));
+                assert!(
+                    matches!(failed[0].1, SplitError::WriteTimeout { index: 2, .. }),
+                    "{:?}",
+                    failed[0].1
+                );
+            }
+            other => panic!("expected WritesFailed, got {:?}", other),
+        }
+        assert!(output_dir.join("split_001.sql").exists());
+        assert!(output_dir.join("split_003.sql").exists());
+
+        // The stalled command was killed rather than left running
+        let pid: u32 = fs::read_to_string(&pid_path).await?.trim().parse()?;
+        let gone = Command::new("sh")
+            .arg("-c")
+            .arg(format!(
+                "for _ in $(seq 50); do kill -0 {} 2>/dev/null || exit 0; \
+                 ps -o stat= -p {} | grep -q Z && exit 0; sleep 0.1; done; exit 1",
+                pid, pid
+            ))
+            .status()
+            .await?;
+        assert!(gone.success(), "pipe command {} still running", pid);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keep_going_past_failed_write() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1; SELECT 2; SELECT 3;").await?;
+
+        // A directory where split_002's temporary file goes fails its write
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir_all(output_dir.join("split_002.sql.partial")).await?;
+        let splitter = SqlSplitter::new(&output_dir, 1000, 1).with_max_size(10);
+        assert!(splitter.split_file(&input_path).await.is_err());
+        assert!(!output_dir.join("split_003.sql").exists());
+
+        match splitter.with_keep_going(true).split_file(&input_path).await {
+            Err(SplitError::WritesFailed(failed)) => {
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].0, output_dir.join("split_002.sql"));
+            }
+            other => panic!("expected WritesFailed, got {:?}", other),
+        }
+        assert!(output_dir.join("split_001.sql").exists());
+        assert!(output_dir.join("split_003.sql").exists());
+        assert!(output_dir.join(STATE_FILE_NAME).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_comment_styles_reload_cleanly() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;\nSELECT '# -- /* */';\n").await?;
+
+        for (style, opener, closer) in [
+            (CommentStyle::Dash, "-- ", ""),
+            (CommentStyle::Hash, "# ", ""),
+            (CommentStyle::Block, "/* ", " */"),
+        ] {
+            let output_dir = temp_dir.path().join(format!("{:?}", style));
+            SqlSplitter::new(&output_dir, 1000, 2)
+                .with_embed_checksum(true)
+                .with_comment_style(style)
+                .split_file(&input_path)
+                .await?;
+            let output_path = output_dir.join("split_001.sql");
+            let output = fs::read_to_string(&output_path).await?;
+            let comment = output.trim_end().lines().last().unwrap();
+            assert!(
+                comment.starts_with(&format!("{}{}", opener, CHECKSUM_LABEL))
+                    && comment.ends_with(closer),
+                "{:?}: {}",
+                style,
+                comment
+            );
+
+            // Splitting the output again yields just the statements back
+            let reload_dir = temp_dir.path().join(format!("{:?}-reload", style));
+            let report = SqlSplitter::new(&reload_dir, 1000, 2)
+                .with_dialect(Dialect::Mysql)
+                .split_file(&output_path)
+                .await?;
+            assert_eq!(report.statements, 2, "{:?}", style);
+            assert_eq!(report.unterminated_statements, 0, "{:?}", style);
+            assert_eq!(report.comment_only_segments, 1, "{:?}", style);
+        }
+        assert_eq!(
+            CommentStyle::Block.comment("a */ b"),
+            "/* a * / b */".to_string()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_width_sorts_past_999_files() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let input: String = (1..=1500).map(|i| format!("SELECT {};\n", i)).collect();
+        fs::write(&input_path, input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 8)
+            .with_max_size(1)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.files, 1500);
+
+        let mut names = Vec::new();
+        let mut entries = fs::read_dir(&output_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            names.push(entry.file_name().into_string().unwrap());
+        }
+        names.sort();
+        let numeric: Vec<String> = (1..=1500).map(|i| format!("split_{:04}.sql", i)).collect();
+        assert_eq!(names, numeric);
+        assert_eq!(
+            fs::read_to_string(output_dir.join("split_1000.sql")).await?,
+            "SELECT 1000;"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_width_pads_file_names() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(&input_path, "SELECT 1;").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        SqlSplitter::new(&output_dir, 1000, 2)
+            .with_index_width(5)
+            .split_file(&input_path)
+            .await?;
+        assert!(output_dir.join("split_00001.sql").is_file());
+        assert!(!output_dir.join("split_001.sql").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_limit_writes_first_statements() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = Vec::new();
+        for i in 0..50_000 {
+            input.extend_from_slice(format!("INSERT INTO t VALUES ({});\n", i).as_bytes());
+        }
+        // Invalid UTF-8 far past the limit is never read
+        input.extend_from_slice(b"SELECT '\xff';\n");
+        fs::write(&input_path, &input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_limit(3)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 3);
+        assert_eq!(report.unterminated_statements, 0);
+        assert!(report.input_bytes < input.len());
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "INSERT INTO t VALUES (0);\n\nINSERT INTO t VALUES (1);\n\nINSERT INTO t VALUES (2);"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tail_keeps_last_statements() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = String::new();
+        for i in 0..10 {
+            input.push_str(&format!("INSERT INTO t VALUES ({});\n", i));
+        }
+        fs::write(&input_path, &input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_tail(2)
+            .with_limit(1)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 2);
+        assert_eq!(report.input_bytes, input.len());
+        let output = fs::read_to_string(output_dir.join("split_001.sql")).await?;
+        assert_eq!(
+            output,
+            "INSERT INTO t VALUES (8);\n\nINSERT INTO t VALUES (9);"
+        );
+
+        // Asking for more than there are keeps them all
+        let report = SqlSplitter::new(temp_dir.path().join("all"), 1000, 2)
+            .with_tail(50)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_skip_statements_leaves_them_out() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = String::new();
+        for i in 0..10 {
+            input.push_str(&format!("INSERT INTO t VALUES ({});\n", i));
+        }
+        fs::write(&input_path, &input).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_max_size(60)
+            .with_skip_statements(4)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 6);
+        let mut output = String::new();
+        for path in &report.output_files {
+            output.push_str(&fs::read_to_string(path).await?);
+        }
+        for i in 0..4 {
+            assert!(!output.contains(&format!("({})", i)), "statement {}", i + 1);
+        }
+        for i in 4..10 {
+            assert!(output.contains(&format!("({})", i)));
+        }
+        assert_eq!(report.output_files[0], output_dir.join("split_001.sql"));
+
+        // --limit counts the statements after the skipped ones
+        let limit_dir = temp_dir.path().join("limit");
+        let report = SqlSplitter::new(&limit_dir, 1000, 2)
+            .with_skip_statements(4)
+            .with_limit(2)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            fs::read_to_string(limit_dir.join("split_001.sql")).await?,
+            "INSERT INTO t VALUES (4);\n\nINSERT INTO t VALUES (5);"
+        );
+        assert_eq!(report.statements, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefix_read_size_keeps_output() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let mut input = String::new();
+        for i in 0..2_000 {
+            input.push_str(&format!("INSERT INTO t VALUES ({}, 'é');\n", i));
+        }
+        fs::write(&input_path, &input).await?;
+
+        // The default first read takes the whole input; a small one stops
+        // after the first doubling that gets past the first statement
+        let report = SqlSplitter::new(temp_dir.path().join("one"), 8, 2)
+            .with_limit(1)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.input_bytes, input.len());
+        let report = SqlSplitter::new(temp_dir.path().join("one_small"), 8, 2)
+            .with_limit(1)
+            .with_prefix_read_size(16)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.input_bytes, 16 + 32);
+
+        let default_dir = temp_dir.path().join("default");
+        SqlSplitter::new(&default_dir, 8, 2)
+            .with_limit(1_500)
+            .split_file(&input_path)
+            .await?;
+        // Small odd-sized reads split multi-byte characters and statements
+        let small_dir = temp_dir.path().join("small");
+        let report = SqlSplitter::new(&small_dir, 8, 2)
+            .with_limit(1_500)
+            .with_prefix_read_size(7)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(report.statements, 1_500);
+        assert!(report.input_bytes < input.len());
+
+        assert!(report.files > 1);
+        for i in 1..=report.files {
+            let name = format!("split_{:03}.sql", i);
+            assert_eq!(
+                fs::read(small_dir.join(&name)).await?,
+                fs::read(default_dir.join(&name)).await?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analyze_writes_nothing() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        let rows = format!("INSERT INTO t VALUES ('{}');\n", "x".repeat(20 * 1024));
+        fs::write(&input_path, format!("SELECT 1;\nSELECT 2;\n{}", rows)).await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .analyze(&input_path)
+            .await?;
+        assert_eq!(report.statements, 3);
+        assert_eq!(report.size_histogram, [2, 0, 1, 0]);
+        assert_eq!(report.largest_statement_line, 3);
+        assert!(!output_dir.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_analyze_counts_statements_per_table() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("input.sql");
+        fs::write(
+            &input_path,
+            "SET NAMES utf8;\n\
+             CREATE TABLE `users` (id INT);\n\
+             CREATE TABLE `shop`.`orders` (id INT);\n\
+             INSERT INTO `users` VALUES (1);\n\
+             INSERT INTO `shop`.`orders` VALUES (1);\n\
+             INSERT INTO `shop`.`orders` VALUES (2);\n\
+             UPDATE shop.orders SET id = 3 WHERE id = 2;\n\
+             CREATE TABLE zones (id INT);\n\
+             DELETE FROM users;\n",
+        )
+        .await?;
+
+        let report = SqlSplitter::new(temp_dir.path().join("out"), 1000, 2)
+            .analyze(&input_path)
+            .await?;
+        assert_eq!(
+            report.tables_by_count(),
+            vec![("shop.orders", 4), ("users", 3), ("zones", 1)]
+        );
+        assert_eq!(
+            report.table_report(),
+            "Table        Statements\n\
+             shop.orders           4\n\
+             users                 3\n\
+             zones                 1"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_list_numbers_continuously() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let row = format!("INSERT INTO t VALUES ('{}');\n", "x".repeat(600));
+        fs::create_dir(temp_dir.path().join("dumps")).await?;
+        fs::write(temp_dir.path().join("dumps/a.sql"), row.repeat(2)).await?;
+        fs::write(temp_dir.path().join("b.sql"), "SELECT 'b';").await?;
+        let list_path = temp_dir.path().join("inputs.txt");
+        fs::write(&list_path, "# nightly inputs\ndumps/a.sql\n\nb.sql\n").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let source_map = temp_dir.path().join("map.tsv");
+        let report = SqlSplitter::new(&output_dir, 1, 2)
+            .with_source_map(&source_map)
+            .split_list(&list_path)
+            .await?;
+        assert_eq!((report.files, report.statements), (3, 3));
+
+        let last = fs::read_to_string(output_dir.join("split_003.sql")).await?;
+        assert_eq!(last, "SELECT 'b';");
+        assert_eq!(
+            fs::read_to_string(&source_map).await?,
+            "file\tstatement\tline\n\
+             split_001.sql\t1\t1\n\
+             split_002.sql\t1\t2\n\
+             split_003.sql\t1\t1\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_reads_keep_output() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_dir = temp_dir.path().join("dumps");
+        fs::create_dir(&input_dir).await?;
+        let mut list = String::new();
+        for i in 0..12 {
+            // Inputs of varying size, so later scans can finish first
+            let row = format!("INSERT INTO t{} VALUES ('{}');\n", i, "x".repeat(300));
+            let name = format!("input_{:02}.sql", i);
+            fs::write(input_dir.join(&name), row.repeat(1 + (12 - i) * 20)).await?;
+            list.push_str(&format!("dumps/{}\n", name));
+        }
+        let list_path = temp_dir.path().join("inputs.txt");
+        fs::write(&list_path, list).await?;
+
+        let splitter = SqlSplitter::new(temp_dir.path().join("serial"), 2, 2);
+        let serial = splitter.split_list(&list_path).await?;
+        let concurrent_dir = temp_dir.path().join("concurrent");
+        let concurrent = SqlSplitter::new(&concurrent_dir, 2, 2)
+            .with_concurrent_reads(6)
+            .split_list(&list_path)
+            .await?;
+        assert_eq!(concurrent.files, serial.files);
+        assert_eq!(concurrent.output_file_stats, serial.output_file_stats);
+        for (serial, concurrent) in serial.output_files.iter().zip(&concurrent.output_files) {
+            assert_eq!(
+                fs::read_to_string(serial).await?,
+                fs::read_to_string(concurrent).await?
+            );
+        }
+
+        let reports = SqlSplitter::new(temp_dir.path().join("dirs"), 2, 2)
+            .with_concurrent_reads(6)
+            .split_dir(&input_dir, false)
+            .await?;
+        let inputs: Vec<&PathBuf> = reports.iter().map(|(input, _)| input).collect();
+        let mut sorted = inputs.clone();
+        sorted.sort();
+        assert_eq!(inputs, sorted);
+        for (i, (_, report)) in reports.iter().enumerate() {
+            let first = fs::read_to_string(&report.output_files[0]).await?;
+            assert!(first.starts_with(&format!("INSERT INTO t{} ", i)));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefix_from_input_names() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        fs::write(temp_dir.path().join("mydump.sql"), "SELECT 1;").await?;
+        fs::write(temp_dir.path().join("other.dump.sql"), "SELECT 2;").await?;
+        let list_path = temp_dir.path().join("inputs.txt");
+        fs::write(&list_path, "mydump.sql\nother.dump.sql\n").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_prefix_from_input(true)
+            .split_list(&list_path)
+            .await?;
+        assert_eq!(
+            report.output_files,
+            vec![
+                output_dir.join("mydump_split_001.sql"),
+                output_dir.join("other.dump_split_002.sql"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(output_dir.join("mydump_split_001.sql")).await?,
+            "SELECT 1;"
+        );
+
+        Ok(())
+    }
+
+    // Windows can't create the input file
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sanitize_filenames_from_input() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let input_path = temp_dir.path().join("db:prod*\\v2?.sql");
+        fs::write(&input_path, "SELECT 1;").await?;
+
+        let output_dir = temp_dir.path().join("out");
+        let report = SqlSplitter::new(&output_dir, 1000, 2)
+            .with_prefix_from_input(true)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            report.output_files,
+            vec![output_dir.join("db_prod__v2__split_001.sql")]
+        );
+
+        let raw_dir = temp_dir.path().join("raw");
+        let report = SqlSplitter::new(&raw_dir, 1000, 2)
+            .with_prefix_from_input(true)
+            .with_sanitize_filenames(false)
+            .split_file(&input_path)
+            .await?;
+        assert_eq!(
+            report.output_files,
+            vec![raw_dir.join("db:prod*\\v2?_split_001.sql")]
+        );
+
+        assert_eq!(sanitize_file_name("a<b>|c\"\t. ."), "a_b__c__");
+        assert_eq!(sanitize_file_name("dump\u{FFFD}"), "dump_");
+
+        Ok(())
+    }
+}
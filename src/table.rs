@@ -0,0 +1,464 @@
+use crate::values::{Lexer, Token};
+use std::ops::Range;
+
+/// Extracts the name of the table a statement operates on.
+///
+/// Recognizes the common DDL/DML forms (`CREATE TABLE`, `ALTER TABLE`,
+/// `DROP TABLE`, `INSERT INTO`, `REPLACE INTO`, `UPDATE`, `DELETE FROM`,
+/// `TRUNCATE`, `LOCK TABLES`, `COPY` and `CREATE INDEX ... ON`). Identifier
+/// quoting (backticks, double quotes, brackets) is removed and qualified
+/// names are returned joined with `.`, e.g. `` `shop`.`orders` `` becomes
+/// `shop.orders`.
+pub fn table_name(statement: &str) -> Option<String> {
+    table_name_parts(statement).map(|parts| parts.join("."))
+}
+
+/// The schema qualifying the table a statement operates on, e.g. `shop` for
+/// `INSERT INTO shop.orders ...` (for a three-part name, the middle part).
+/// `None` when the name is unqualified or no table is recognized.
+pub fn schema_name(statement: &str) -> Option<String> {
+    let mut parts = table_name_parts(statement)?;
+    parts.pop();
+    parts.pop()
+}
+
+/// The unquoted parts of the name [`table_name`] recognizes.
+fn table_name_parts(statement: &str) -> Option<Vec<String>> {
+    table_cursor(statement)?.identifier_parts()
+}
+
+/// A cursor at the start of the name [`table_name`] recognizes.
+fn table_cursor(statement: &str) -> Option<Cursor<'_>> {
+    let mut cursor = Cursor::new(statement);
+
+    if cursor.keyword("CREATE") {
+        cursor.keyword("OR");
+        cursor.keyword("REPLACE");
+        cursor.keyword("GLOBAL");
+        cursor.keyword("LOCAL");
+        let _ = cursor.keyword("TEMPORARY") || cursor.keyword("TEMP");
+        cursor.keyword("UNLOGGED");
+        if cursor.keyword("TABLE") {
+            cursor.if_not_exists();
+            return Some(cursor);
+        }
+        cursor.keyword("UNIQUE");
+        if cursor.keyword("INDEX") {
+            cursor.keyword("CONCURRENTLY");
+            cursor.if_not_exists();
+            if !cursor.keyword("ON") {
+                cursor.identifier_parts()?;
+                if !cursor.keyword("ON") {
+                    return None;
+                }
+            }
+            cursor.keyword("ONLY");
+            return Some(cursor);
+        }
+        return None;
+    }
+
+    if cursor.keyword("ALTER") || cursor.keyword("DROP") {
+        if !cursor.keyword("TABLE") {
+            return None;
+        }
+        cursor.if_exists();
+        cursor.keyword("ONLY");
+        return Some(cursor);
+    }
+
+    if cursor.keyword("INSERT") || cursor.keyword("REPLACE") {
+        cursor.keyword("LOW_PRIORITY");
+        cursor.keyword("DELAYED");
+        cursor.keyword("HIGH_PRIORITY");
+        cursor.keyword("IGNORE");
+        if !cursor.keyword("INTO") {
+            return None;
+        }
+        return Some(cursor);
+    }
+
+    if cursor.keyword("UPDATE") {
+        cursor.keyword("LOW_PRIORITY");
+        cursor.keyword("IGNORE");
+        cursor.keyword("ONLY");
+        return Some(cursor);
+    }
+
+    if cursor.keyword("DELETE") {
+        cursor.keyword("LOW_PRIORITY");
+        cursor.keyword("QUICK");
+        cursor.keyword("IGNORE");
+        if !cursor.keyword("FROM") {
+            return None;
+        }
+        cursor.keyword("ONLY");
+        return Some(cursor);
+    }
+
+    if cursor.keyword("TRUNCATE") {
+        cursor.keyword("TABLE");
+        cursor.keyword("ONLY");
+        return Some(cursor);
+    }
+
+    if cursor.keyword("LOCK") {
+        if !(cursor.keyword("TABLES") || cursor.keyword("TABLE")) {
+            return None;
+        }
+        return Some(cursor);
+    }
+
+    if cursor.keyword("COPY") {
+        return Some(cursor);
+    }
+
+    None
+}
+
+/// The table name of a `CREATE TABLE` statement exactly as written, quoting
+/// and qualification included, so a generated statement can refer to the
+/// same table under the same case-folding rules.
+pub fn created_table(statement: &str) -> Option<&str> {
+    let mut cursor = Cursor::new(statement);
+    if !cursor.keyword("CREATE") {
+        return None;
+    }
+    cursor.keyword("GLOBAL");
+    cursor.keyword("LOCAL");
+    let _ = cursor.keyword("TEMPORARY") || cursor.keyword("TEMP");
+    cursor.keyword("UNLOGGED");
+    if !cursor.keyword("TABLE") {
+        return None;
+    }
+    cursor.if_not_exists();
+    let start = cursor.rest;
+    cursor.identifier()?;
+    Some(start[..start.len() - cursor.rest.len()].trim_end())
+}
+
+/// The tables a `CREATE TABLE` statement's `REFERENCES` clauses name
+/// (inline or in `FOREIGN KEY` constraints), unquoted as by [`table_name`],
+/// in order of appearance. Empty for other statements.
+pub fn referenced_tables(statement: &str) -> Vec<String> {
+    let mut tables = Vec::new();
+    if created_table(statement).is_none() {
+        return tables;
+    }
+    let mut lexer = Lexer::new(statement);
+    while let Some(token) = lexer.next_token() {
+        if let Token::Word(start, end) = token {
+            if statement[start..end].eq_ignore_ascii_case("REFERENCES") {
+                if let Some(table) = Cursor::new(&statement[end..]).identifier() {
+                    tables.push(table);
+                }
+            }
+        }
+    }
+    tables
+}
+
+/// Byte ranges of the table names `statement` refers to: the one
+/// [`table_name`] recognizes, then those its `REFERENCES` clauses name.
+/// Each covers just the last part of the name (the table, not its schema),
+/// inside any quotes. Names in string literals and comments aren't found.
+pub fn table_name_ranges(statement: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let Some(range) = table_cursor(statement).and_then(|mut cursor| cursor.last_part(statement))
+    else {
+        return ranges;
+    };
+    ranges.push(range);
+    let mut lexer = Lexer::new(statement);
+    while let Some(token) = lexer.next_token() {
+        if let Token::Word(start, end) = token {
+            if statement[start..end].eq_ignore_ascii_case("REFERENCES") {
+                let mut cursor = Cursor::new(&statement[end..]);
+                if let Some(range) = cursor.last_part(&statement[end..]) {
+                    ranges.push(range.start + end..range.end + end);
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// `statement` with `new` in place of `old` at the start of each table
+/// name [`table_name_ranges`] finds that begins with it (`shop_orders`
+/// becomes `store_orders` for `shop_` and `store_`), or `None` if none
+/// does. String data and schema names are left alone.
+pub fn replace_table_prefix(statement: &str, old: &str, new: &str) -> Option<String> {
+    let mut replaced = statement.to_string();
+    let mut changed = false;
+    // Back to front, so earlier ranges stay valid
+    for range in table_name_ranges(statement).into_iter().rev() {
+        if statement[range.clone()].starts_with(old) {
+            replaced.replace_range(range.start..range.start + old.len(), new);
+            changed = true;
+        }
+    }
+    changed.then_some(replaced)
+}
+
+/// A minimal forward-only tokenizer over the head of a statement.
+pub(crate) struct Cursor<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        let mut cursor = Cursor { rest: input };
+        cursor.skip_trivia();
+        cursor
+    }
+
+    /// Skips whitespace and any leading `--`, `#` or `/* */` comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            self.rest = self.rest.trim_start();
+            if self.rest.starts_with("--") || self.rest.starts_with('#') {
+                self.rest = self.rest.find('\n').map_or("", |i| &self.rest[i + 1..]);
+            } else if self.rest.starts_with("/*") {
+                self.rest = self.rest[2..]
+                    .find("*/")
+                    .map_or("", |i| &self.rest[i + 4..]);
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The input not yet consumed.
+    pub(crate) fn rest(&self) -> &'a str {
+        self.rest
+    }
+
+    /// Consumes the next bare word, if any.
+    pub(crate) fn word(&mut self) -> Option<&'a str> {
+        let end = self
+            .rest
+            .find(|c| !is_identifier_char(c))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let word = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        self.skip_trivia();
+        Some(word)
+    }
+
+    /// Consumes `kw` if it is the next word (case-insensitively).
+    pub(crate) fn keyword(&mut self, kw: &str) -> bool {
+        let len = kw.len();
+        let matches = self.rest.len() >= len
+            && self.rest.is_char_boundary(len)
+            && self.rest[..len].eq_ignore_ascii_case(kw)
+            && !self.rest[len..].starts_with(is_identifier_char);
+        if matches {
+            self.rest = &self.rest[len..];
+            self.skip_trivia();
+        }
+        matches
+    }
+
+    fn if_exists(&mut self) {
+        if self.keyword("IF") {
+            self.keyword("EXISTS");
+        }
+    }
+
+    fn if_not_exists(&mut self) {
+        if self.keyword("IF") {
+            self.keyword("NOT");
+            self.keyword("EXISTS");
+        }
+    }
+
+    /// Consumes a possibly qualified, possibly quoted identifier.
+    pub(crate) fn identifier(&mut self) -> Option<String> {
+        self.identifier_parts().map(|parts| parts.join("."))
+    }
+
+    /// Consumes a possibly qualified, possibly quoted identifier, returning
+    /// its unquoted parts.
+    fn identifier_parts(&mut self) -> Option<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            parts.push(self.identifier_part()?);
+            match self.rest.strip_prefix('.') {
+                Some(rest) => self.rest = rest,
+                None => break,
+            }
+        }
+        self.skip_trivia();
+        Some(parts)
+    }
+
+    /// Consumes a possibly qualified, possibly quoted identifier, returning
+    /// the byte range of its last part's unquoted text in `input`, the text
+    /// this cursor was created over.
+    fn last_part(&mut self, input: &str) -> Option<Range<usize>> {
+        loop {
+            let quoted = self.rest.starts_with(['`', '"', '[']);
+            let start = input.len() - self.rest.len() + usize::from(quoted);
+            let part = self.identifier_part()?;
+            match self.rest.strip_prefix('.') {
+                Some(rest) => self.rest = rest,
+                None => {
+                    self.skip_trivia();
+                    return Some(start..start + part.len());
+                }
+            }
+        }
+    }
+
+    fn identifier_part(&mut self) -> Option<String> {
+        let close = match self.rest.chars().next()? {
+            '`' => '`',
+            '"' => '"',
+            '[' => ']',
+            _ => {
+                let end = self
+                    .rest
+                    .find(|c| !is_identifier_char(c))
+                    .unwrap_or(self.rest.len());
+                if end == 0 {
+                    return None;
+                }
+                let part = self.rest[..end].to_string();
+                self.rest = &self.rest[end..];
+                return Some(part);
+            }
+        };
+        let end = self.rest[1..].find(close)? + 1;
+        let part = self.rest[1..end].to_string();
+        self.rest = &self.rest[end + 1..];
+        Some(part)
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_name_forms() {
+        let cases = [
+            ("CREATE TABLE users (id INT)", Some("users")),
+            (
+                "create table if not exists `audit_log` (id int)",
+                Some("audit_log"),
+            ),
+            (
+                "INSERT INTO \"public\".\"orders\" VALUES (1)",
+                Some("public.orders"),
+            ),
+            ("-- comment\nINSERT IGNORE INTO t1 VALUES (1)", Some("t1")),
+            (
+                "ALTER TABLE ONLY public.items ADD COLUMN x INT",
+                Some("public.items"),
+            ),
+            ("DROP TABLE IF EXISTS [dbo].[Temp]", Some("dbo.Temp")),
+            ("DELETE FROM logs WHERE id = 1", Some("logs")),
+            (
+                "CREATE UNIQUE INDEX idx_a ON accounts (a)",
+                Some("accounts"),
+            ),
+            ("COPY events (id, name) FROM stdin", Some("events")),
+            ("LOCK TABLES `t2` WRITE", Some("t2")),
+            ("SELECT * FROM users", None),
+            ("SET NAMES utf8", None),
+        ];
+        for (statement, expected) in cases {
+            assert_eq!(table_name(statement).as_deref(), expected, "{}", statement);
+        }
+    }
+
+    #[test]
+    fn test_schema_name() {
+        let cases = [
+            ("CREATE TABLE sales.orders (id INT)", Some("sales")),
+            ("INSERT INTO \"Hr\".\"staff\" VALUES (1)", Some("Hr")),
+            ("ALTER TABLE ONLY [db].[dbo].[t] ADD x INT", Some("dbo")),
+            ("COPY audit.events (id) FROM stdin", Some("audit")),
+            ("INSERT INTO t VALUES (1)", None),
+            ("SELECT * FROM a.b", None),
+        ];
+        for (statement, expected) in cases {
+            assert_eq!(schema_name(statement).as_deref(), expected, "{}", statement);
+        }
+    }
+
+    #[test]
+    fn test_referenced_tables() {
+        let statement = "CREATE TABLE `orders` (\n\
+                         id INT, customer_id INT REFERENCES customers (id),\n\
+                         note TEXT DEFAULT 'REFERENCES fake',\n\
+                         CONSTRAINT fk FOREIGN KEY (sku) REFERENCES `shop`.`items`(sku)\n\
+                         )";
+        assert_eq!(
+            referenced_tables(statement),
+            vec!["customers", "shop.items"]
+        );
+        assert!(
+            referenced_tables("ALTER TABLE a ADD FOREIGN KEY (b) REFERENCES c (id)").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_replace_table_prefix() {
+        assert_eq!(
+            replace_table_prefix(
+                "INSERT INTO `shop`.`wp_posts` VALUES (1, 'wp_posts is a table')",
+                "wp_",
+                "blog_"
+            )
+            .as_deref(),
+            Some("INSERT INTO `shop`.`blog_posts` VALUES (1, 'wp_posts is a table')")
+        );
+        assert_eq!(
+            replace_table_prefix(
+                "CREATE TABLE wp_comments (post INT REFERENCES wp_posts (id), \
+                 note TEXT DEFAULT 'REFERENCES wp_x')",
+                "wp_",
+                "blog_"
+            )
+            .as_deref(),
+            Some(
+                "CREATE TABLE blog_comments (post INT REFERENCES blog_posts (id), \
+                 note TEXT DEFAULT 'REFERENCES wp_x')"
+            )
+        );
+        assert_eq!(
+            replace_table_prefix("CREATE INDEX wp_i ON wp_posts (a)", "wp_", "x_").as_deref(),
+            Some("CREATE INDEX wp_i ON x_posts (a)")
+        );
+        assert_eq!(
+            replace_table_prefix("UPDATE users SET a = 1", "wp_", "x_"),
+            None
+        );
+        assert_eq!(
+            replace_table_prefix("SELECT * FROM wp_posts", "wp_", "x_"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_created_table_keeps_quoting() {
+        assert_eq!(
+            created_table("CREATE TABLE IF NOT EXISTS `shop`.`orders` (id INT)"),
+            Some("`shop`.`orders`")
+        );
+        assert_eq!(
+            created_table("create temp table \"Mixed\"(id int)"),
+            Some("\"Mixed\"")
+        );
+        assert_eq!(created_table("CREATE INDEX i ON t (a)"), None);
+        assert_eq!(created_table("DROP TABLE t"), None);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::dialect::Dialect;
+use std::fmt;
+
+/// Words a statement may start with; anything else is flagged as suspect.
+const VERBS: &[&str] = &[
+    "ABORT",
+    "ALTER",
+    "ANALYZE",
+    "BEGIN",
+    "CALL",
+    "CHECK",
+    "CHECKPOINT",
+    "CHECKSUM",
+    "CLOSE",
+    "CLUSTER",
+    "COMMENT",
+    "COMMIT",
+    "COPY",
+    "CREATE",
+    "DEALLOCATE",
+    "DECLARE",
+    "DELETE",
+    "DESCRIBE",
+    "DISCARD",
+    "DO",
+    "DROP",
+    "END",
+    "EXEC",
+    "EXECUTE",
+    "EXPLAIN",
+    "FETCH",
+    "FLUSH",
+    "GRANT",
+    "HANDLER",
+    "INSERT",
+    "KILL",
+    "LISTEN",
+    "LOAD",
+    "LOCK",
+    "MERGE",
+    "NOTIFY",
+    "OPEN",
+    "OPTIMIZE",
+    "PRAGMA",
+    "PREPARE",
+    "REFRESH",
+    "REINDEX",
+    "RELEASE",
+    "RENAME",
+    "REPAIR",
+    "REPLACE",
+    "RESET",
+    "REVOKE",
+    "ROLLBACK",
+    "SAVEPOINT",
+    "SECURITY",
+    "SELECT",
+    "SET",
+    "SHOW",
+    "START",
+    "TABLE",
+    "TRUNCATE",
+    "UNLISTEN",
+    "UNLOCK",
+    "UPDATE",
+    "USE",
+    "VACUUM",
+    "VALUES",
+    "WITH",
+];
+
+/// Why `--validate-sql` flagged a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The first word isn't a known statement verb (`None`: there is none).
+    UnknownVerb(Option<String>),
+    /// More `)` than `(` at some point, or `(` left open at the end.
+    UnbalancedParentheses,
+    /// A string literal or quoted identifier opened with this quote
+    /// runs to the end of the statement.
+    UnclosedQuote(char),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UnknownVerb(Some(word)) => {
+                write!(f, "starts with {:?}, not a SQL statement verb", word)
+            }
+            Problem::UnknownVerb(None) => write!(f, "doesn't start with a SQL statement verb"),
+            Problem::UnbalancedParentheses => write!(f, "has unbalanced parentheses"),
+            Problem::UnclosedQuote(quote) => write!(f, "has an unclosed {} quote", quote),
+        }
+    }
+}
+
+/// A statement `--validate-sql` flagged, by the input line it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Malformed {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "statement on line {} {}", self.line, self.problem)
+    }
+}
+
+/// A cheap sanity check of one statement, not a parse: it must start with
+/// a known verb (or `(`), and its parentheses and quotes must balance
+/// outside comments and string literals. Versioned MySQL comments
+/// (`/*!40101 ... */`) count as code.
+pub fn check(statement: &str, dialect: Dialect) -> Option<Problem> {
+    let mut chars = statement.char_indices().peekable();
+    let mut started = false;
+    let mut depth = 0usize;
+    while let Some((i, c)) = chars.next() {
+        let rest = &statement[i..];
+        match c {
+            c if c.is_whitespace() => continue,
+            '-' if rest.starts_with("--") => {
+                skip_to(
+                    &mut chars,
+                    i + rest.find('\n').map_or(rest.len(), |n| n + 1),
+                );
+                continue;
+            }
+            '#' if dialect == Dialect::Mysql => {
+                skip_to(
+                    &mut chars,
+                    i + rest.find('\n').map_or(rest.len(), |n| n + 1),
+                );
+                continue;
+            }
+            '/' if rest.starts_with("/*!") => {
+                let version = rest[3..].bytes().take_while(u8::is_ascii_digit).count();
+                skip_to(&mut chars, i + 3 + version);
+                continue;
+            }
+            '/' if rest.starts_with("/*") => {
+                skip_to(
+                    &mut chars,
+                    rest[2..].find("*/").map_or(statement.len(), |n| i + n + 4),
+                );
+                continue;
+            }
+            // The end of a versioned comment
+            '*' if rest.starts_with("*/") => {
+                skip_to(&mut chars, i + 2);
+                continue;
+            }
+            _ => {}
+        }
+
+        if !started {
+            let word: String = rest
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            if c != '(' && !VERBS.iter().any(|verb| word.eq_ignore_ascii_case(verb)) {
+                return Some(Problem::UnknownVerb((!word.is_empty()).then_some(word)));
+            }
+            started = true;
+        }
+
+        match c {
+            '\'' | '"' | '`' => match quoted_len(rest, c) {
+                Some(len) => skip_to(&mut chars, i + len),
+                None => return Some(Problem::UnclosedQuote(c)),
+            },
+            '$' if dialect == Dialect::Postgres => {
+                if let Some(tag_len) = dollar_tag_len(rest) {
+                    let tag = &rest[..tag_len];
+                    match rest[tag_len..].find(tag) {
+                        Some(n) => skip_to(&mut chars, i + tag_len + n + tag_len),
+                        None => return Some(Problem::UnclosedQuote('$')),
+                    }
+                }
+            }
+            '(' => depth += 1,
+            ')' => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return Some(Problem::UnbalancedParentheses),
+            },
+            _ => {}
+        }
+    }
+    if !started {
+        return Some(Problem::UnknownVerb(None));
+    }
+    (depth > 0).then_some(Problem::UnbalancedParentheses)
+}
+
+/// Advances `chars` to byte offset `end`.
+fn skip_to(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>, end: usize) {
+    while chars.next_if(|&(i, _)| i < end).is_some() {}
+}
+
+/// Length of the quoted text opening `rest`, quotes included, as the
+/// scanner reads it: a doubled quote or a backslash (outside backticks)
+/// escapes the next character.
+pub(crate) fn quoted_len(rest: &str, quote: char) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' && quote != '`' {
+            chars.next();
+        } else if c == quote {
+            if rest[i + 1..].starts_with(quote) {
+                chars.next();
+                continue;
+            }
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Length of a `$tag$` opening `rest`, if it opens a dollar-quoted string.
+pub(crate) fn dollar_tag_len(rest: &str) -> Option<usize> {
+    let tag = &rest[1..];
+    let name = tag
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(tag.len());
+    let starts_with_digit = tag.starts_with(|c: char| c.is_ascii_digit());
+    (tag[name..].starts_with('$') && !starts_with_digit).then_some(name + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_malformed_statements() {
+        let flagged = |statement: &str| check(statement, Dialect::Generic);
+        assert_eq!(
+            flagged("SELCT * FROM t"),
+            Some(Problem::UnknownVerb(Some("SELCT".to_string())))
+        );
+        assert_eq!(flagged("*/"), Some(Problem::UnknownVerb(None)));
+        assert_eq!(
+            flagged("INSERT INTO t VALUES (1, (2)"),
+            Some(Problem::UnbalancedParentheses)
+        );
+        assert_eq!(
+            flagged("SELECT f(1))"),
+            Some(Problem::UnbalancedParentheses)
+        );
+        assert_eq!(
+            flagged("INSERT INTO t VALUES ('it's')"),
+            Some(Problem::UnclosedQuote('\''))
+        );
+        assert_eq!(
+            check("SELECT $$ ( $ ", Dialect::Postgres),
+            Some(Problem::UnclosedQuote('$'))
+        );
+    }
+
+    #[test]
+    fn test_accepts_well_formed_statements() {
+        for statement in [
+            "-- load\nINSERT INTO t VALUES ('(', 'it''s', 'a\\'b', \"x)\") /* ) */",
+            "/*!40101 SET @OLD_CHARSET=@@CHARACTER_SET_CLIENT */",
+            "(SELECT 1) UNION (SELECT 2)",
+            "create table `odd(name` (id int)",
+        ] {
+            assert_eq!(check(statement, Dialect::Generic), None, "{}", statement);
+        }
+        assert_eq!(
+            check(
+                "CREATE FUNCTION f() AS $body$ SELECT ')' $body$",
+                Dialect::Postgres
+            ),
+            None
+        );
+        assert_eq!(check("# note (\nSELECT 1", Dialect::Mysql), None);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::table::Cursor;
+
+/// A multi-row `INSERT ... VALUES` statement cut into its parts, borrowing
+/// from the statement text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValuesList<'a> {
+    /// Everything up to and including the `VALUES` keyword.
+    pub head: &'a str,
+    /// Each row tuple, parentheses included, e.g. `(1, 'a')`.
+    pub rows: Vec<&'a str>,
+    /// Whatever follows the last row, e.g. ` ON DUPLICATE KEY UPDATE ...`.
+    pub tail: &'a str,
+}
+
+/// Splits an `INSERT`/`REPLACE ... VALUES (...), (...)` statement into its
+/// row tuples. Returns `None` for other statements, including
+/// `INSERT ... SELECT`, and for a malformed values list.
+///
+/// Only a comma between top-level tuples separates rows: parentheses,
+/// commas and semicolons inside string literals (with backslash or doubled
+/// quote escapes), quoted identifiers, comments and nested expressions such
+/// as `(1, CONCAT('a', ')'), (2))` stay within their row.
+pub fn parse_values(statement: &str) -> Option<ValuesList<'_>> {
+    let mut cursor = Cursor::new(statement);
+    if !(cursor.keyword("INSERT") || cursor.keyword("REPLACE")) {
+        return None;
+    }
+
+    let mut lexer = Lexer::new(statement);
+    let values_end = loop {
+        match lexer.next_token()? {
+            Token::Word(start, end) if lexer.depth == 0 => {
+                let word = &statement[start..end];
+                if word.eq_ignore_ascii_case("VALUES") || word.eq_ignore_ascii_case("VALUE") {
+                    break end;
+                }
+                if word.eq_ignore_ascii_case("SELECT") {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+    };
+
+    let mut rows = Vec::new();
+    loop {
+        let start = match lexer.next_token()? {
+            Token::Open(start) if lexer.depth == 1 => start,
+            _ => return None,
+        };
+        let end = loop {
+            if let Token::Close(end) = lexer.next_token()? {
+                if lexer.depth == 0 {
+                    break end;
+                }
+            }
+        };
+        rows.push(&statement[start..end]);
+
+        let after_row = lexer.pos;
+        match lexer.next_token() {
+            Some(Token::Comma) => continue,
+            _ => {
+                return Some(ValuesList {
+                    head: &statement[..values_end],
+                    rows,
+                    tail: &statement[after_row..],
+                })
+            }
+        }
+    }
+}
+
+/// Tokens significant to the values-list structure; byte offsets are into
+/// the statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// A bare word spanning `start..end`.
+    Word(usize, usize),
+    /// `(` at the given offset.
+    Open(usize),
+    /// `)`, with the offset just past it.
+    Close(usize),
+    Comma,
+    /// Any other character, string literal or quoted identifier.
+    Other,
+}
+
+/// Walks a statement skipping whitespace, comments and quoted text while
+/// tracking parenthesis depth.
+pub(crate) struct Lexer<'a> {
+    input: &'a str,
+    pub(crate) pos: usize,
+    pub(crate) depth: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    /// The next token, or `None` at the end of input or inside an
+    /// unterminated string or comment.
+    pub(crate) fn next_token(&mut self) -> Option<Token> {
+        loop {
+            let rest = &self.input[self.pos..];
+            let c = rest.chars().next()?;
+            let start = self.pos;
+            self.pos += c.len_utf8();
+            match c {
+                c if c.is_whitespace() => {}
+                '-' if rest.starts_with("--") => {
+                    self.pos = rest.find('\n').map_or(self.input.len(), |i| start + i + 1);
+                }
+                '/' if rest.starts_with("/*") => {
+                    self.pos = start + 2 + rest[2..].find("*/")? + 2;
+                }
+                '\'' | '"' | '`' => {
+                    self.skip_quoted(c)?;
+                    return Some(Token::Other);
+                }
+                '(' => {
+                    self.depth += 1;
+                    return Some(Token::Open(start));
+                }
+                ')' => {
+                    self.depth = self.depth.checked_sub(1)?;
+                    return Some(Token::Close(self.pos));
+                }
+                ',' => return Some(Token::Comma),
+                c if c.is_alphanumeric() || c == '_' => {
+                    let len = rest
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                        .unwrap_or(rest.len());
+                    self.pos = start + len;
+                    return Some(Token::Word(start, self.pos));
+                }
+                _ => return Some(Token::Other),
+            }
+        }
+    }
+
+    /// Consumes through the quote closing one opened by `quote`. A doubled
+    /// quote is an escaped one; backslashes escape inside string literals.
+    fn skip_quoted(&mut self, quote: char) -> Option<()> {
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' && quote != '`' {
+                chars.next();
+            } else if c == quote {
+                let after = self.pos + i + 1;
+                if self.input[after..].starts_with(quote) {
+                    chars.next();
+                    continue;
+                }
+                self.pos = after;
+                return Some(());
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_split_only_at_top_level() {
+        let statement = "INSERT INTO t (a, b, c) VALUES \
+                         (1, 'x),(y', CONCAT('a', ')')),\n  \
+                         (2, 'it''s; (fine)', (3 + 4) * 2), \
+                         (3, 'back\\\\slash\\', quote', `odd),(col`) \
+                         ON DUPLICATE KEY UPDATE b = VALUES(b)";
+        let values = parse_values(statement).unwrap();
+        assert_eq!(values.head, "INSERT INTO t (a, b, c) VALUES");
+        assert_eq!(
+            values.rows,
+            vec![
+                "(1, 'x),(y', CONCAT('a', ')'))",
+                "(2, 'it''s; (fine)', (3 + 4) * 2)",
+                "(3, 'back\\\\slash\\', quote', `odd),(col`)",
+            ]
+        );
+        assert_eq!(values.tail, " ON DUPLICATE KEY UPDATE b = VALUES(b)");
+    }
+
+    #[test]
+    fn test_comments_and_single_row() {
+        let values =
+            parse_values("-- load\nINSERT INTO t VALUES /* first, (one) */ (1, '/* no */')")
+                .unwrap();
+        assert_eq!(values.rows, vec!["(1, '/* no */')"]);
+        assert_eq!(values.tail, "");
+    }
+
+    #[test]
+    fn test_non_values_statements() {
+        assert_eq!(parse_values("INSERT INTO t SELECT * FROM u"), None);
+        assert_eq!(parse_values("UPDATE t SET a = 1"), None);
+        assert_eq!(parse_values("INSERT INTO t VALUES (1, 'open"), None);
+    }
+}